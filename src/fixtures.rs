@@ -0,0 +1,88 @@
+use crate::{graph::Graph, parser::NetexData};
+
+// one line running three stops on weekdays of a single week
+pub const DOCUMENT: &str = r#"<PublicationDelivery>
+    <Authority id="auth:1"><ShortName>KVB</ShortName></Authority>
+    <Line id="line:1"><ShortName>S1</ShortName><AuthorityRef ref="auth:1"/></Line>
+    <ScheduledStopPoint id="ssp:a">
+        <Name>Köln Hbf</Name>
+        <Location><Longitude>6.958</Longitude><Latitude>50.943</Latitude></Location>
+    </ScheduledStopPoint>
+    <ScheduledStopPoint id="ssp:b">
+        <Name>Köln Messe/Deutz</Name>
+        <Location><Longitude>6.975</Longitude><Latitude>50.940</Latitude></Location>
+    </ScheduledStopPoint>
+    <ScheduledStopPoint id="ssp:c">
+        <Name>Köln-Mülheim</Name>
+        <Location><Longitude>7.006</Longitude><Latitude>50.963</Latitude></Location>
+    </ScheduledStopPoint>
+    <ServiceJourneyPattern id="sjp:1">
+        <Name>Hbf - Mülheim</Name>
+        <LineRef ref="line:1"/>
+        <pointsInSequence>
+            <StopPointInJourneyPattern id="sp:1"><ScheduledStopPointRef ref="ssp:a"/></StopPointInJourneyPattern>
+            <StopPointInJourneyPattern id="sp:2"><ScheduledStopPointRef ref="ssp:b"/></StopPointInJourneyPattern>
+            <StopPointInJourneyPattern id="sp:3"><ScheduledStopPointRef ref="ssp:c"/></StopPointInJourneyPattern>
+        </pointsInSequence>
+    </ServiceJourneyPattern>
+    <UicOperatingPeriod id="op:1">
+        <FromDate>2022-06-13T00:00:00</FromDate>
+        <ToDate>2022-06-19T00:00:00</ToDate>
+        <ValidDayBits>1111100</ValidDayBits>
+    </UicOperatingPeriod>
+    <DayTypeAssignment id="dta:1">
+        <OperatingPeriodRef ref="op:1"/>
+        <DayTypeRef ref="dt:1"/>
+        <isAvailable>true</isAvailable>
+    </DayTypeAssignment>
+    <ServiceJourney id="sj:1">
+        <TransportMode>rail</TransportMode>
+        <dayTypes><DayTypeRef ref="dt:1"/></dayTypes>
+        <ServiceJourneyPatternRef ref="sjp:1"/>
+        <passingTimes>
+            <TimetabledPassingTime>
+                <StopPointInJourneyPatternRef ref="sp:1"/>
+                <DepartureTime>08:00:00</DepartureTime>
+            </TimetabledPassingTime>
+            <TimetabledPassingTime>
+                <StopPointInJourneyPatternRef ref="sp:2"/>
+                <ArrivalTime>08:05:00</ArrivalTime>
+                <DepartureTime>08:06:00</DepartureTime>
+            </TimetabledPassingTime>
+            <TimetabledPassingTime>
+                <StopPointInJourneyPatternRef ref="sp:3"/>
+                <ArrivalTime>08:12:00</ArrivalTime>
+            </TimetabledPassingTime>
+        </passingTimes>
+    </ServiceJourney>
+</PublicationDelivery>"#;
+
+pub fn data() -> NetexData {
+    NetexData::from_xml(DOCUMENT.as_bytes(), DOCUMENT.len()).unwrap()
+}
+
+pub fn graph() -> Graph {
+    Graph::from_data(&[data()], &[])
+}
+
+// the fixture document with the journey repeated at the given hours, e.g. "09"
+pub fn document_with_trips(hours: &[&str]) -> String {
+    let start = DOCUMENT.find("<ServiceJourney ").unwrap();
+    let end = DOCUMENT.find("</PublicationDelivery>").unwrap();
+    let journey = &DOCUMENT[start..end];
+    let trips: String = hours
+        .iter()
+        .map(|hour| {
+            journey
+                .replace("sj:1", &format!("sj:{hour}"))
+                .replace(">08:", &format!(">{hour}:"))
+        })
+        .collect();
+    format!("{}{trips}</PublicationDelivery>", &DOCUMENT[..start])
+}
+
+pub fn graph_with_trips(hours: &[&str]) -> Graph {
+    let xml = document_with_trips(hours);
+    let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+    Graph::from_data(&[data], &[])
+}
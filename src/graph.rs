@@ -1,12 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 
 use base64::engine::Engine;
-use geo::{Centroid, Destination, Haversine};
+use geo::{Centroid, Destination, Distance, Haversine};
 use indicatif::ParallelProgressIterator;
-use rayon::iter::{IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use rayon::iter::{Either, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
 
 use crate::parser::{
-    Authority, DayTypeAssignment, Line, NetexData, ServiceJourney, UicOperatingPeriod,
+    AccessibilityLimitation, Authority, ContactDetails, GroupOfLines, InterchangeWeighting, Line,
+    NetexData, Notice, ServiceJourney, StopPlace, TimetabledPassingTime, TypeOfPlace,
+    UicOperatingPeriod,
 };
 
 #[derive(Clone, Default, Debug)]
@@ -15,14 +17,59 @@ pub struct Node {
     pub short_name: String,
     pub long: f32,
     pub lat: f32,
+    pub min_transfer_seconds: Option<u32>,
+    pub stop_place_type: Option<String>,
+    // from the stop points, otherwise derived from the stop place type
+    pub type_of_place: Option<TypeOfPlace>,
+    pub weighting: Option<InterchangeWeighting>,
+    pub accessibility: AccessibilityLimitation,
+    pub compass_bearing: Option<f32>,
+    // index into the hubs of the graph
+    pub parent_hub: Option<usize>,
+    // town or municipality of the stop places
+    pub locality: Option<String>,
 }
 
-#[derive(Debug, serde::Serialize)]
+impl Node {
+    // when stop places in a cluster disagree, the longest transfer and
+    // the most preferred weighting win, otherwise the first place providing a value
+    fn apply_stop_place(&mut self, place: &StopPlace) {
+        self.min_transfer_seconds = self.min_transfer_seconds.max(place.min_transfer_seconds);
+        self.weighting = self.weighting.max(place.weighting);
+        self.accessibility.fill_unknown(&place.accessibility);
+        if self.stop_place_type.is_none() {
+            self.stop_place_type.clone_from(&place.stop_place_type);
+        }
+        if self.type_of_place.is_none() {
+            self.type_of_place = place.stop_place_type.as_deref().map(TypeOfPlace::parse);
+        }
+        self.compass_bearing = self.compass_bearing.or(place.compass_bearing);
+    }
+
+    // combines the attributes of a node merged into this one
+    pub fn absorb(&mut self, other: &Node) {
+        self.id ^= other.id;
+        self.min_transfer_seconds = self.min_transfer_seconds.max(other.min_transfer_seconds);
+        self.weighting = self.weighting.max(other.weighting);
+        self.accessibility.fill_unknown(&other.accessibility);
+        if self.stop_place_type.is_none() {
+            self.stop_place_type.clone_from(&other.stop_place_type);
+        }
+        self.type_of_place = self.type_of_place.or(other.type_of_place);
+        self.compass_bearing = self.compass_bearing.or(other.compass_bearing);
+        self.parent_hub = self.parent_hub.or(other.parent_hub);
+        if self.locality.is_none() {
+            self.locality.clone_from(&other.locality);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, serde::Serialize)]
 pub struct Journey {
     #[serde(rename(serialize = "d"))]
-    pub departure: u16,
+    pub departure: DayMinute,
     #[serde(rename(serialize = "a"))]
-    pub arrival: u16,
+    pub arrival: DayMinute,
     #[serde(rename(serialize = "t"))]
     pub transport_mode: String,
     #[serde(rename(serialize = "o"))]
@@ -31,6 +78,42 @@ pub struct Journey {
     pub line: String,
     #[serde(rename(serialize = "c"))]
     pub controller: String,
+    // how riders reach the controller, e.g. its service hotline, as index into
+    // the contacts of the graph
+    #[serde(rename(serialize = "k"), skip_serializing_if = "Option::is_none")]
+    pub controller_contact: Option<usize>,
+    #[serde(rename(serialize = "n"), skip_serializing_if = "Option::is_none")]
+    pub pattern_name: Option<String>,
+    // short names from the controller up to its topmost parent organisation, as
+    // index into the authority chains of the graph. None if the controller has
+    // no parent.
+    #[serde(rename(serialize = "h"), skip_serializing_if = "Option::is_none")]
+    pub authority_chain: Option<usize>,
+    // the line provides real-time data
+    #[serde(rename(serialize = "m"), skip_serializing_if = "std::ops::Not::not")]
+    pub monitored: bool,
+    // notices assigned to the service journey, its pattern or its line, as index
+    // into the notices of the graph
+    #[serde(rename(serialize = "x"), skip_serializing_if = "Option::is_none")]
+    pub notices: Option<usize>,
+    #[serde(rename(serialize = "p"), skip_serializing_if = "Option::is_none")]
+    pub public_code: Option<String>,
+    // public code of the quay the journey departs from
+    #[serde(rename(serialize = "q"), skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>,
+    // name of the group of lines or network of the line
+    #[serde(rename(serialize = "g"), skip_serializing_if = "Option::is_none")]
+    pub network: Option<String>,
+    // info page of the line
+    #[serde(rename(serialize = "u"), skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    // hashed id of the service journey this leg belongs to
+    #[serde(skip_serializing)]
+    pub trip: u64,
+    // positions of the departure and arrival stop in the pattern of the service
+    // journey, counting from 1
+    #[serde(skip_serializing)]
+    pub stop_sequence: (u16, u16),
 }
 
 #[derive(Clone, Default, Debug, serde::Serialize)]
@@ -42,9 +125,32 @@ pub struct OperatingPeriod {
     #[serde(rename(serialize = "v"))]
     pub valid_day_bits: String,
     pub valid_day: Vec<u8>,
+    // Netex id of the canonical period this one was built from, see
+    // Graph::period_source. Structurally identical periods collapse into the
+    // first of them, so this is that period's id, not necessarily the one the
+    // journeys referenced.
+    #[serde(skip_serializing)]
+    pub canonical_source_id: Option<String>,
 }
 
-#[derive(Debug, Default, serde::Serialize)]
+// periods are equal by their days, regardless of where they came from
+impl PartialEq for OperatingPeriod {
+    fn eq(&self, other: &Self) -> bool {
+        self.from == other.from && self.to == other.to && self.valid_day == other.valid_day
+    }
+}
+
+impl Eq for OperatingPeriod {}
+
+impl std::hash::Hash for OperatingPeriod {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.from.hash(state);
+        self.to.hash(state);
+        self.valid_day.hash(state);
+    }
+}
+
+#[derive(Clone, Debug, Default, serde::Serialize)]
 pub struct Timetable {
     #[serde(rename(serialize = "j"))]
     pub journeys: Vec<Journey>,
@@ -52,7 +158,68 @@ pub struct Timetable {
     pub periods: Vec<OperatingPeriod>,
 }
 
-#[derive(Debug)]
+impl Timetable {
+    // Drops duplicated periods and journeys, keeping the first occurrence
+    pub fn compact(&mut self) {
+        let mut first_index = HashMap::<&OperatingPeriod, usize>::new();
+        let mut old_to_new = Vec::<usize>::with_capacity(self.periods.len());
+        let mut kept = Vec::<usize>::new();
+        for (idx, period) in self.periods.iter().enumerate() {
+            let new = *first_index.entry(period).or_insert_with(|| {
+                kept.push(idx);
+                kept.len() - 1
+            });
+            old_to_new.push(new);
+        }
+        for journey in &mut self.journeys {
+            journey.operating_period = old_to_new[journey.operating_period];
+        }
+        let mut periods = std::mem::take(&mut self.periods);
+        self.periods = kept
+            .into_iter()
+            .map(|idx| std::mem::take(&mut periods[idx]))
+            .collect();
+        let mut seen = HashSet::<Journey>::new();
+        self.journeys.retain(|journey| seen.insert(journey.clone()));
+    }
+
+    // orders periods by their days and journeys by departure and line,
+    // see Graph::sort_deterministic
+    pub fn sort_deterministic(&mut self) {
+        let mut order: Vec<usize> = (0..self.periods.len()).collect();
+        order.sort_by_key(|idx| {
+            let period = &self.periods[*idx];
+            (
+                days_since_epoch(period.from),
+                days_since_epoch(period.to),
+                period.valid_day.clone(),
+            )
+        });
+        let mut old_to_new = vec![0; self.periods.len()];
+        for (new, old) in order.iter().enumerate() {
+            old_to_new[*old] = new;
+        }
+        let mut periods = std::mem::take(&mut self.periods);
+        self.periods = order
+            .into_iter()
+            .map(|idx| std::mem::take(&mut periods[idx]))
+            .collect();
+        for journey in &mut self.journeys {
+            journey.operating_period = old_to_new[journey.operating_period];
+        }
+        self.journeys.sort_by(|a, b| {
+            (a.departure, &a.line, a.arrival, a.operating_period, a.trip).cmp(&(
+                b.departure,
+                &b.line,
+                b.arrival,
+                b.operating_period,
+                b.trip,
+            ))
+        });
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Edge {
     pub start_node: usize,
     pub end_node: usize,
@@ -60,10 +227,242 @@ pub struct Edge {
     pub walk_seconds: u16,
 }
 
-#[derive(Debug)]
+impl Edge {
+    // moves the journeys of other into this edge, keeping their periods intact
+    pub fn absorb(&mut self, other: Edge) {
+        let offset = self.timetable.periods.len();
+        self.timetable.periods.extend(other.timetable.periods);
+        self.timetable
+            .journeys
+            .extend(other.timetable.journeys.into_iter().map(|mut journey| {
+                journey.operating_period += offset;
+                journey
+            }));
+        self.walk_seconds = self.walk_seconds.min(other.walk_seconds);
+    }
+
+    // the distinct transport modes of the journeys, e.g. bus and tram on a shared corridor
+    pub fn modes(&self) -> BTreeSet<String> {
+        self.timetable
+            .journeys
+            .iter()
+            .map(|journey| journey.transport_mode.clone())
+            .collect()
+    }
+}
+
+// a stop place grouping the stop places of several nodes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hub {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Default)]
 pub struct Graph {
     pub nodes: Vec<Node>,
     pub edges: Vec<Edge>,
+    pub hubs: Vec<Hub>,
+    // contact details of the controllers, shared by their journeys
+    pub contacts: Vec<ContactDetails>,
+    // authority chains of controllers with a parent, shared by their journeys
+    pub authority_chains: Vec<Vec<String>>,
+    // notices of journeys, shared by journeys with the same ones
+    pub notices: Vec<Vec<Notice>>,
+    // journeys dropped for implausible speeds during construction
+    pub speed_outliers: usize,
+}
+
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} @ {},{}", self.short_name, self.lat, self.long)
+    }
+}
+
+impl std::fmt::Display for Journey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {}→{} {}",
+            self.line, self.departure, self.arrival, self.transport_mode
+        )
+    }
+}
+
+// Minutes since midnight of the operating day. Times after midnight of the
+// following days continue counting, e.g. 25:10 is 1510.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+#[serde(transparent)]
+pub struct DayMinute(pub u16);
+
+impl DayMinute {
+    // minutes since midnight of the operating day, past 24:00 for wrapped times
+    pub fn minute_of_day(self) -> u16 {
+        self.0
+    }
+
+    // None if the time no longer fits into a DayMinute
+    pub fn checked_add(self, minutes: u16) -> Option<DayMinute> {
+        self.0.checked_add(minutes).map(DayMinute)
+    }
+
+    // the same time of day, days after the operating day
+    #[must_use]
+    pub fn plus_days(self, days: u16) -> DayMinute {
+        DayMinute(self.0 + days * 24 * 60)
+    }
+
+    // hour of the day the time falls on
+    pub fn hours(self) -> u16 {
+        self.0 / 60 % 24
+    }
+
+    pub fn minutes(self) -> u16 {
+        self.0 % 60
+    }
+
+    // days after the operating day, 0 for times before midnight
+    pub fn wrapped_day(self) -> u16 {
+        self.0 / (24 * 60)
+    }
+}
+
+impl From<u16> for DayMinute {
+    fn from(minutes: u16) -> Self {
+        DayMinute(minutes)
+    }
+}
+
+// the time of day, without the wrapped days
+impl std::fmt::Display for DayMinute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}:{:02}", self.hours(), self.minutes())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    pub const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    // weekday of a day counted by days_since_epoch, 1970-01-01 was a thursday
+    pub fn from_days(days: i64) -> Weekday {
+        Weekday::ALL[usize::try_from((days + 3).rem_euclid(7)).unwrap_or_default()]
+    }
+}
+
+// the weekday flags of a GTFS calendar
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WeeklyCalendar {
+    pub monday: bool,
+    pub tuesday: bool,
+    pub wednesday: bool,
+    pub thursday: bool,
+    pub friday: bool,
+    pub saturday: bool,
+    pub sunday: bool,
+    pub from: u16,
+    pub to: u16,
+}
+
+impl OperatingPeriod {
+    pub fn is_valid_on(&self, date: u16) -> bool {
+        let first = days_since_epoch(self.from);
+        let days = days_since_epoch(date);
+        if days < first || days > days_since_epoch(self.to) {
+            return false;
+        }
+        usize::try_from(days - first).is_ok_and(|day| {
+            self.valid_day
+                .get(day / 8)
+                .is_some_and(|byte| byte & (1 << (day % 8)) != 0)
+        })
+    }
+
+    // Weekday of the bit_index-th day of valid_day, counted from the from date.
+    pub fn weekday_of_bit(&self, bit_index: usize) -> Weekday {
+        // only the offset within the week matters
+        let offset = i64::try_from(bit_index % 7).unwrap_or_default();
+        Weekday::from_days(days_since_epoch(self.from) + offset)
+    }
+
+    // whether any valid day between from and to falls on weekday
+    pub fn runs_on_weekday(&self, weekday: Weekday) -> bool {
+        let span = days_since_epoch(self.to) - days_since_epoch(self.from) + 1;
+        (0..usize::try_from(span).unwrap_or_default()).any(|day| {
+            self.weekday_of_bit(day) == weekday
+                && self
+                    .valid_day
+                    .get(day / 8)
+                    .is_some_and(|byte| byte & (1 << (day % 8)) != 0)
+        })
+    }
+
+    // Detects whether the valid days repeat every week, in which case each
+    // weekday is either always or never operated between from and to.
+    pub fn to_weekly_pattern(&self) -> Option<WeeklyCalendar> {
+        let first = days_since_epoch(self.from);
+        let span = days_since_epoch(self.to) - first + 1;
+        let mut weekdays = [None::<bool>; 7];
+        for day in 0..span {
+            let idx = usize::try_from(day).ok()?;
+            let valid = self
+                .valid_day
+                .get(idx / 8)
+                .is_some_and(|byte| byte & (1 << (idx % 8)) != 0);
+            // 1970-01-01 was a thursday
+            let weekday = usize::try_from((first + day + 3).rem_euclid(7)).ok()?;
+            match weekdays[weekday] {
+                Some(expected) if expected != valid => return None,
+                _ => weekdays[weekday] = Some(valid),
+            }
+        }
+        let [monday, tuesday, wednesday, thursday, friday, saturday, sunday] =
+            weekdays.map(|valid| valid.unwrap_or(false));
+        Some(WeeklyCalendar {
+            monday,
+            tuesday,
+            wednesday,
+            thursday,
+            friday,
+            saturday,
+            sunday,
+            from: self.from,
+            to: self.to,
+        })
+    }
+}
+
+impl std::fmt::Display for OperatingPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}–{}", format_date(self.from), format_date(self.to))
+    }
+}
+
+#[cfg(feature = "petgraph")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeWeight {
+    // shortest ride in minutes, falling back to the walk duration
+    TravelTime,
+    JourneyCount,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -80,8 +479,69 @@ pub struct WalkEdge {
     pub duration: f32,
 }
 
+pub struct GraphOptions {
+    // maps stop names to the key stops are deduplicated by
+    pub name_key: fn(&str) -> String,
+}
+
+impl Default for GraphOptions {
+    fn default() -> Self {
+        GraphOptions {
+            name_key: normalize_name,
+        }
+    }
+}
+
+// trims and collapses whitespace
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// like normalize_name, ignoring case as well
+pub fn normalize_name_lowercase(name: &str) -> String {
+    normalize_name(name).to_lowercase()
+}
+
+#[derive(Default)]
+struct Hubs {
+    vec: Vec<Hub>,
+    index: HashMap<u64, usize>,
+}
+
+impl Hubs {
+    fn index(&mut self, id: u64, stop_places: &HashMap<u64, &StopPlace>) -> usize {
+        *self.index.entry(id).or_insert_with(|| {
+            self.vec.push(Hub {
+                id,
+                name: stop_places.get(&id).and_then(|hub| hub.name.clone()),
+            });
+            self.vec.len() - 1
+        })
+    }
+}
+
+// postal towns of stop places, otherwise the name of their topographic place
+fn localities(data: &[NetexData]) -> HashMap<u64, String> {
+    let towns: HashMap<u64, &String> = data
+        .iter()
+        .flat_map(|d| d.topographic_places.iter())
+        .filter_map(|place| Some((place.id, place.name.as_ref()?)))
+        .collect();
+    data.iter()
+        .flat_map(|d| d.stop_places.iter())
+        .filter_map(|place| {
+            let locality = place
+                .locality
+                .as_ref()
+                .or_else(|| towns.get(&place.topographic_place?).copied())?;
+            Some((place.id, locality.clone()))
+        })
+        .collect()
+}
+
 struct Nodes {
     vec: Vec<Node>,
+    hubs: Vec<Hub>,
     // netex ref to indices
     ref_map: HashMap<u64, Indices>,
     // calculate id to indices
@@ -90,9 +550,9 @@ struct Nodes {
 }
 
 impl Nodes {
-    fn from_data(data: &[NetexData]) -> Nodes {
-        let mut nodes_by_name = HashMap::<&str, Vec<Indices>>::new();
-        let mut ref_to_node_idx = HashMap::<u64, Indices>::new();
+    // stops grouped by the name key of the options
+    fn stops_by_name(data: &[NetexData], options: &GraphOptions) -> HashMap<String, Vec<Indices>> {
+        let mut nodes_by_name = HashMap::<String, Vec<Indices>>::new();
         for (data_idx, one_data) in data.iter().enumerate() {
             for (stop_idx, stop) in one_data.scheduled_stop_points.iter().enumerate() {
                 let indices = Indices {
@@ -101,15 +561,37 @@ impl Nodes {
                     stop: stop_idx,
                 };
                 nodes_by_name
-                    .entry(&stop.short_name)
+                    .entry((options.name_key)(&stop.short_name))
                     .and_modify(|stops| stops.push(indices))
                     .or_insert(vec![indices]);
             }
         }
+        nodes_by_name
+    }
+
+    fn from_data(data: &[NetexData], options: &GraphOptions) -> Nodes {
+        let nodes_by_name = Self::stops_by_name(data, options);
+        let mut ref_to_node_idx = HashMap::<u64, Indices>::new();
+        let stop_places: HashMap<u64, &StopPlace> = data
+            .iter()
+            .flat_map(|d| d.stop_places.iter())
+            .map(|place| (place.id, place))
+            .collect();
+        let stop_ref_to_place: HashMap<u64, &StopPlace> = data
+            .iter()
+            .flat_map(|d| d.passenger_stop_assignments.iter())
+            .filter_map(|psa| {
+                stop_places
+                    .get(&psa.stop_place)
+                    .map(|place| (psa.scheduled_stop_point, *place))
+            })
+            .collect();
+        let localities = localities(data);
         let mut id_map = HashMap::<u64, usize>::new();
         let mut nodes: Vec<Node> = vec![];
+        let mut hubs = Hubs::default();
         let distance = 1000.0; // radius in meters
-        for (name, stops) in nodes_by_name {
+        for stops in nodes_by_name.into_values() {
             type TreeObj<'a> = rstar::primitives::GeomWithData<geo::Coord<f32>, Indices>;
             let mut tree = rstar::RTree::<TreeObj>::bulk_load(
                 stops
@@ -129,45 +611,66 @@ impl Nodes {
                 let aabb =
                     rstar::AABB::<geo::Coord<f32>>::from_corners(corner1.into(), corner2.into());
                 let local: Vec<TreeObj> = tree.drain_in_envelope(aabb).collect();
+                let stop_of =
+                    |point: &TreeObj| &data[point.data.data].scheduled_stop_points[point.data.stop];
                 for point in &local {
-                    let point_idx = point.data;
-                    let point_stop = &data[point_idx.data].scheduled_stop_points[point_idx.stop];
                     ref_to_node_idx.insert(
-                        point_stop.id,
+                        stop_of(point).id,
                         Indices {
                             node: nodes.len(),
-                            data: point_idx.data,
-                            stop: point_idx.stop,
+                            ..point.data
                         },
                     );
                 }
-                let coords: Vec<geo::Coord<f32>> =
-                    local.iter().map(TreeObj::geom).copied().collect();
-                let centroid = geo::LineString::new(coords)
-                    .centroid()
-                    .expect("failed to calculate centroid");
+                let ranked: Vec<_> = local
+                    .iter()
+                    .map(|point| {
+                        // an unknown weighting ranks like noInterchange, so it
+                        // cannot lose to a stop merely for declaring one
+                        let rank = stop_ref_to_place.get(&stop_of(point).id).map_or(
+                            (InterchangeWeighting::NoInterchange, 0),
+                            |place| {
+                                let weighting = place
+                                    .weighting
+                                    .unwrap_or(InterchangeWeighting::NoInterchange);
+                                (weighting, place.quays.len())
+                            },
+                        );
+                        (*point.geom(), rank)
+                    })
+                    .collect();
+                let centroid = cluster_position(&ranked);
                 // current.id is not consistent across runs
                 // there are different scheduled_point_stops with the same name + coords that are different entities
                 // so xor all stops in a cluster, risking hash collisions
-                let mut node_ids: Vec<u64> = local
-                    .iter()
-                    .map(|val| data[val.data.data].scheduled_stop_points[val.data.stop].id)
-                    .collect();
+                let mut node_ids: Vec<u64> = local.iter().map(|val| stop_of(val).id).collect();
                 node_ids.sort_unstable();
                 node_ids.dedup();
-                let node_id = node_ids.into_iter().reduce(|l, r| l ^ r).unwrap();
-                id_map.insert(node_id, nodes.len());
-                nodes.push(Node {
+                let mut node = Node {
                     lat: centroid.y(),
                     long: centroid.x(),
-                    short_name: name.to_owned(),
-                    id: node_id,
-                });
+                    short_name: current.short_name.trim().to_owned(),
+                    type_of_place: local.iter().find_map(|val| stop_of(val).type_of_place),
+                    ..Node::default()
+                };
+                for place in node_ids.iter().filter_map(|id| stop_ref_to_place.get(id)) {
+                    node.apply_stop_place(place);
+                    if node.locality.is_none() {
+                        node.locality = localities.get(&place.id).cloned();
+                    }
+                    if let (None, Some(parent)) = (node.parent_hub, place.parent) {
+                        node.parent_hub = Some(hubs.index(parent, &stop_places));
+                    }
+                }
+                node.id = node_ids.into_iter().reduce(|l, r| l ^ r).unwrap();
+                id_map.insert(node.id, nodes.len());
+                nodes.push(node);
             }
         }
         Nodes {
             id_map,
             vec: nodes,
+            hubs: hubs.vec,
             ref_map: ref_to_node_idx,
         }
     }
@@ -187,25 +690,53 @@ impl Nodes {
 
 pub struct JourneyTransformer {
     authorities: HashMap<u64, Authority>,
-    day_type_assignments: HashMap<u64, DayTypeAssignment>,
+    // one entry per authority with parent organisations
+    authority_chains: Vec<Vec<String>>,
+    // index into authority_chains by authority
+    authority_chain_index: HashMap<u64, usize>,
+    // one entry per authority with contact details
+    contacts: Vec<ContactDetails>,
+    // index into contacts by authority
+    contact_index: HashMap<u64, usize>,
+    // global operating period index per day type
+    day_type_periods: HashMap<u64, usize>,
+    // periods with days of unavailable assignments removed or dated journeys
+    // applied, indexed after all parsed ones
+    derived_periods: Vec<UicOperatingPeriod>,
+    // global operating period index for journeys with dated overrides
+    journey_periods: HashMap<u64, usize>,
     lines: HashMap<u64, Line>,
+    // group or network names by line
+    networks: HashMap<u64, String>,
+    // one entry per distinct set of journey notices
+    notices: Vec<Vec<Notice>>,
+    // index into notices by service journey
+    notice_index: HashMap<u64, usize>,
     pattern_ref_to_line_ref: HashMap<u64, u64>,
+    pattern_names: HashMap<u64, String>,
+    pattern_modes: HashMap<u64, String>,
+    // quay public codes by scheduled stop point
+    platforms: HashMap<u64, String>,
     point_in_journey_to_stop_ref: HashMap<u64, u64>,
-    period_map: HashMap<u64, usize>,
+    // position of each stop point in its pattern, counting from 1
+    stop_positions: HashMap<u64, u16>,
 }
 
 impl JourneyTransformer {
-    fn from_data(data: &[NetexData]) -> JourneyTransformer {
-        let mut point_in_journey_to_stop_ref = HashMap::<u64, u64>::new();
-        for one_data in data {
-            for sequence in &one_data.service_journey_patterns {
-                for stop in &sequence.stops {
-                    point_in_journey_to_stop_ref
-                        .entry(stop.id)
-                        .or_insert(stop.scheduled_stop_point);
-                }
-            }
+    // the mode declared closest to the journey: the journey, its pattern, then its line
+    fn transport_mode(&self, journey: &ServiceJourney, line: &Line) -> String {
+        if !journey.transport_mode.is_empty() {
+            return journey.transport_mode.clone();
         }
+        self.pattern_modes
+            .get(&journey.pattern_ref)
+            .or(line.transport_mode.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn from_data(data: &[NetexData]) -> JourneyTransformer {
+        let (point_in_journey_to_stop_ref, stop_positions) = Self::stop_points(data);
 
         let mut lines = HashMap::<u64, Line>::new();
         for one_data in data {
@@ -220,57 +751,356 @@ impl JourneyTransformer {
                 authorities.insert(authority.id, authority.clone());
             }
         }
+        let (contacts, contact_index) = Self::contacts_by_authority(data);
+        let (authority_chains, authority_chain_index) = Self::authority_chains(data, &authorities);
 
         let mut pattern_ref_to_line_ref = HashMap::<u64, u64>::new();
+        let mut pattern_names = HashMap::<u64, String>::new();
+        let mut pattern_modes = HashMap::<u64, String>::new();
         for one_data in data {
             for journey_pattern in &one_data.service_journey_patterns {
                 pattern_ref_to_line_ref.insert(journey_pattern.id, journey_pattern.line);
+                if let Some(name) = &journey_pattern.name {
+                    pattern_names.insert(journey_pattern.id, name.clone());
+                }
+                if let Some(mode) = &journey_pattern.transport_mode {
+                    pattern_modes.insert(journey_pattern.id, mode.clone());
+                }
+            }
+        }
+
+        let period_map = Self::canonical_periods(data);
+        // available assignments define the period, unavailable ones remove days from it
+        let mut assignments = HashMap::<u64, (Option<u64>, Vec<u64>)>::new();
+        for dta in data.iter().flat_map(|d| d.day_type_assignments.iter()) {
+            let entry = assignments.entry(dta.day_type).or_default();
+            if dta.is_available {
+                entry.0 = Some(dta.operating_period);
+            } else {
+                entry.1.push(dta.operating_period);
+            }
+        }
+        let parsed_periods: usize = data.iter().map(|d| d.operating_periods.len()).sum();
+        let mut day_type_periods = HashMap::<u64, usize>::new();
+        let mut derived_periods = Vec::<UicOperatingPeriod>::new();
+        for (day_type, (available, unavailable)) in assignments {
+            let Some(base_idx) = available
+                .or(unavailable.first().copied())
+                .and_then(|base| period_map.get(&base))
+            else {
+                continue;
+            };
+            if unavailable.is_empty() {
+                day_type_periods.insert(day_type, *base_idx);
+                continue;
+            }
+            let mut period = Graph::lookup_operating_period(data, &[], *base_idx)
+                .expect("failed to look up operating period")
+                .clone();
+            if available.is_none() {
+                period.valid_day_bits.fill(0);
+            }
+            for excluded in unavailable
+                .iter()
+                .filter_map(|id| period_map.get(id))
+                .filter_map(|idx| Graph::lookup_operating_period(data, &[], *idx))
+            {
+                period.subtract(excluded);
+            }
+            day_type_periods.insert(day_type, parsed_periods + derived_periods.len());
+            derived_periods.push(period);
+        }
+
+        let journey_periods =
+            Self::apply_dated_journeys(data, &day_type_periods, &mut derived_periods);
+        let (notices, notice_index) = Self::notices_by_journey(data, &pattern_ref_to_line_ref);
+
+        JourneyTransformer {
+            authorities,
+            authority_chains,
+            authority_chain_index,
+            contacts,
+            contact_index,
+            day_type_periods,
+            derived_periods,
+            journey_periods,
+            networks: Self::networks_by_line(data, &lines),
+            lines,
+            notices,
+            notice_index,
+            pattern_ref_to_line_ref,
+            pattern_names,
+            pattern_modes,
+            platforms: Self::platforms_by_stop(data),
+            point_in_journey_to_stop_ref,
+            stop_positions,
+        }
+    }
+
+    // the scheduled stop point and the position in its pattern of each stop point
+    fn stop_points(data: &[NetexData]) -> (HashMap<u64, u64>, HashMap<u64, u16>) {
+        let mut point_in_journey_to_stop_ref = HashMap::<u64, u64>::new();
+        let mut stop_positions = HashMap::<u64, u16>::new();
+        for one_data in data {
+            for sequence in &one_data.service_journey_patterns {
+                for (position, stop) in (1..=u16::MAX).zip(&sequence.stops) {
+                    point_in_journey_to_stop_ref
+                        .entry(stop.id)
+                        .or_insert(stop.scheduled_stop_point);
+                    stop_positions.entry(stop.id).or_insert(position);
+                }
+            }
+        }
+        (point_in_journey_to_stop_ref, stop_positions)
+    }
+
+    // the contact table of the graph and the entry of each authority in it
+    fn contacts_by_authority(data: &[NetexData]) -> (Vec<ContactDetails>, HashMap<u64, usize>) {
+        let mut contacts = Vec::<ContactDetails>::new();
+        let mut index = HashMap::<u64, usize>::new();
+        for authority in data.iter().flat_map(|d| d.authorities.iter()) {
+            if let Some(contact) = &authority.contact {
+                index.entry(authority.id).or_insert_with(|| {
+                    contacts.push(contact.clone());
+                    contacts.len() - 1
+                });
             }
         }
+        (contacts, index)
+    }
 
+    // the chain table of the graph and the entry of each authority with a parent in it
+    fn authority_chains(
+        data: &[NetexData],
+        authorities: &HashMap<u64, Authority>,
+    ) -> (Vec<Vec<String>>, HashMap<u64, usize>) {
+        let mut chains = Vec::<Vec<String>>::new();
+        let mut index = HashMap::<u64, usize>::new();
+        for authority in data.iter().flat_map(|d| d.authorities.iter()) {
+            if index.contains_key(&authority.id) {
+                continue;
+            }
+            let chain = Self::authority_chain(authorities, authority.id);
+            if chain.len() > 1 {
+                index.insert(authority.id, chains.len());
+                chains.push(chain);
+            }
+        }
+        (chains, index)
+    }
+
+    // Global period index by period id. Structurally identical periods, e.g. the
+    // same calendar repeated across files, all map to the first of them.
+    fn canonical_periods(data: &[NetexData]) -> HashMap<u64, usize> {
+        let mut canonical = HashMap::<(u16, u16, &[u8]), usize>::new();
         let mut period_map = HashMap::<u64, usize>::new();
         for (idx, period) in data
             .iter()
             .flat_map(|d| d.operating_periods.iter())
             .enumerate()
         {
-            period_map.insert(period.id, idx);
+            let key = (period.from, period.to, period.valid_day_bits.as_slice());
+            period_map.insert(period.id, *canonical.entry(key).or_insert(idx));
         }
-        let mut day_type_assignments = HashMap::<u64, DayTypeAssignment>::new();
-        for dta in data.iter().flat_map(|d| d.day_type_assignments.iter()) {
-            day_type_assignments.insert(dta.day_type, dta.clone());
+        period_map
+    }
+
+    // Derives a period per journey that is cancelled or added on single operating days
+    fn apply_dated_journeys(
+        data: &[NetexData],
+        day_type_periods: &HashMap<u64, usize>,
+        derived_periods: &mut Vec<UicOperatingPeriod>,
+    ) -> HashMap<u64, usize> {
+        let days: HashMap<u64, u16> = data
+            .iter()
+            .flat_map(|d| d.operating_days.iter())
+            .map(|day| (day.id, day.date))
+            .collect();
+        let mut overrides = HashMap::<u64, Vec<(u16, bool)>>::new();
+        for dated in data.iter().flat_map(|d| d.dated_service_journeys.iter()) {
+            if let Some(date) = days.get(&dated.operating_day) {
+                overrides
+                    .entry(dated.service_journey)
+                    .or_default()
+                    .push((*date, !dated.cancelled));
+            }
         }
+        let parsed_periods: usize = data.iter().map(|d| d.operating_periods.len()).sum();
+        let mut journey_periods = HashMap::<u64, usize>::new();
+        for journey in data.iter().flat_map(|d| d.service_journeys.iter()) {
+            let (Some(dates), Some(base_idx)) = (
+                overrides.get(&journey.id),
+                day_type_periods.get(&journey.day_type),
+            ) else {
+                continue;
+            };
+            let mut period = Graph::lookup_operating_period(data, derived_periods, *base_idx)
+                .expect("failed to look up operating period")
+                .clone();
+            for (date, valid) in dates {
+                period.set_day(*date, *valid);
+            }
+            journey_periods.insert(journey.id, parsed_periods + derived_periods.len());
+            derived_periods.push(period);
+        }
+        journey_periods
+    }
 
-        JourneyTransformer {
-            authorities,
-            day_type_assignments,
-            lines,
-            pattern_ref_to_line_ref,
-            point_in_journey_to_stop_ref,
-            period_map,
+    fn notices_by_object(data: &[NetexData]) -> HashMap<u64, Vec<Notice>> {
+        let notice_by_id: HashMap<u64, &Notice> = data
+            .iter()
+            .flat_map(|d| d.notices.iter())
+            .map(|notice| (notice.id, notice))
+            .collect();
+        let mut notices = HashMap::<u64, Vec<Notice>>::new();
+        for assignment in data.iter().flat_map(|d| d.notice_assignments.iter()) {
+            if let Some(notice) = notice_by_id.get(&assignment.notice) {
+                notices
+                    .entry(assignment.noticed_object)
+                    .or_default()
+                    .push((*notice).clone());
+            }
+        }
+        notices
+    }
+
+    // The notice table of the graph and the entry of each service journey with
+    // notices on it, its pattern or its line in it. Journeys with the same
+    // notices share an entry.
+    fn notices_by_journey(
+        data: &[NetexData],
+        pattern_ref_to_line_ref: &HashMap<u64, u64>,
+    ) -> (Vec<Vec<Notice>>, HashMap<u64, usize>) {
+        let by_object = Self::notices_by_object(data);
+        let mut notices = Vec::<Vec<Notice>>::new();
+        let mut entries = HashMap::<Vec<Notice>, usize>::new();
+        let mut index = HashMap::<u64, usize>::new();
+        for journey in data.iter().flat_map(|d| d.service_journeys.iter()) {
+            let line = pattern_ref_to_line_ref.get(&journey.pattern_ref).copied();
+            let journey_notices: Vec<Notice> = [Some(journey.id), Some(journey.pattern_ref), line]
+                .iter()
+                .flatten()
+                .filter_map(|id| by_object.get(id))
+                .flatten()
+                .cloned()
+                .collect();
+            if journey_notices.is_empty() {
+                continue;
+            }
+            let entry = *entries.entry(journey_notices).or_insert_with_key(|key| {
+                notices.push(key.clone());
+                notices.len() - 1
+            });
+            index.insert(journey.id, entry);
+        }
+        (notices, index)
+    }
+
+    // A line's RepresentedByGroupRef wins over group membership,
+    // which in turn wins over network membership.
+    fn networks_by_line(data: &[NetexData], lines: &HashMap<u64, Line>) -> HashMap<u64, String> {
+        let groups: HashMap<u64, &GroupOfLines> = data
+            .iter()
+            .flat_map(|d| d.groups_of_lines.iter())
+            .map(|group| (group.id, group))
+            .collect();
+        let mut networks = HashMap::<u64, String>::new();
+        let mut members: Vec<&GroupOfLines> = groups.values().copied().collect();
+        members.sort_by_key(|group| (!group.network, group.id));
+        for group in members {
+            let Some(name) = &group.name else {
+                continue;
+            };
+            for line in &group.lines {
+                networks.insert(*line, name.clone());
+            }
+        }
+        for line in lines.values() {
+            if let Some(name) = line
+                .group
+                .and_then(|group| groups.get(&group))
+                .and_then(|group| group.name.clone())
+            {
+                networks.insert(line.id, name);
+            }
+        }
+        networks
+    }
+
+    fn platforms_by_stop(data: &[NetexData]) -> HashMap<u64, String> {
+        let platform_by_quay: HashMap<u64, &String> = data
+            .iter()
+            .flat_map(|d| d.stop_places.iter())
+            .flat_map(|place| place.quays.iter())
+            .filter_map(|quay| Some((quay.id, quay.public_code.as_ref()?)))
+            .collect();
+        data.iter()
+            .flat_map(|d| d.passenger_stop_assignments.iter())
+            .filter_map(|psa| {
+                let platform = platform_by_quay.get(&psa.quay?)?;
+                Some((psa.scheduled_stop_point, (*platform).clone()))
+            })
+            .collect()
+    }
+
+    fn authority_chain(authorities: &HashMap<u64, Authority>, authority: u64) -> Vec<String> {
+        let mut chain = Vec::<String>::new();
+        let mut visited = Vec::<u64>::new();
+        let mut current = authorities.get(&authority);
+        while let Some(authority) = current {
+            // guard against cyclic parent references
+            if visited.contains(&authority.id) {
+                break;
+            }
+            visited.push(authority.id);
+            chain.push(authority.short_name.clone());
+            current = authority.parent.and_then(|p| authorities.get(&p));
         }
+        chain
+    }
+
+    fn position(&self, stop_point_in_journey_pattern: u64) -> u16 {
+        self.stop_positions
+            .get(&stop_point_in_journey_pattern)
+            .copied()
+            .unwrap_or_default()
     }
 
     fn to_edges(&self, journey: &ServiceJourney, nodes: &Nodes) -> HashMap<(usize, usize), Edge> {
         let mut local_edges = HashMap::<(usize, usize), Edge>::new();
-        for window in journey.passing_times.windows(2) {
-            let pre = &window[0];
-            let current = &window[1];
-            let Some(start_indecies) = nodes.index_by_stop_ref(
-                self.point_in_journey_to_stop_ref[&pre.stop_point_in_journey_pattern],
+        // passing times at via or timing points without a stop do not form edges
+        let stops: Vec<(&TimetabledPassingTime, u64)> = journey
+            .passing_times
+            .iter()
+            .filter_map(|time| {
+                let stop = self
+                    .point_in_journey_to_stop_ref
+                    .get(&time.stop_point_in_journey_pattern)?;
+                Some((time, *stop))
+            })
+            .collect();
+        for window in stops.windows(2) {
+            let (pre, start_stop) = window[0];
+            let (current, end_stop) = window[1];
+            // a stop with a single time is left and reached at it, one without any
+            // time forms no legs
+            let (Some(departure), Some(arrival)) = (
+                pre.departure.or(pre.arrival),
+                current.arrival.or(current.departure),
             ) else {
                 continue;
             };
-            let Some(end_indecies) = nodes.index_by_stop_ref(
-                self.point_in_journey_to_stop_ref[&current.stop_point_in_journey_pattern],
-            ) else {
+            let Some(start_indecies) = nodes.index_by_stop_ref(start_stop) else {
+                continue;
+            };
+            let Some(end_indecies) = nodes.index_by_stop_ref(end_stop) else {
                 continue;
             };
-            let period = self
-                .day_type_assignments
-                .get(&journey.day_type)
-                .expect("Day type without operating period found")
-                .operating_period;
+            let period = *self
+                .journey_periods
+                .get(&journey.id)
+                .or_else(|| self.day_type_periods.get(&journey.day_type))
+                .expect("Day type without operating period found");
 
             let entry = local_edges
                 .entry((start_indecies.node, end_indecies.node))
@@ -282,12 +1112,26 @@ impl JourneyTransformer {
                 });
             let line = &self.lines[&self.pattern_ref_to_line_ref[&journey.pattern_ref]];
             entry.timetable.journeys.push(Journey {
-                departure: pre.departure,
-                arrival: current.arrival,
-                transport_mode: journey.transport_mode.clone(),
-                operating_period: *self.period_map.get(&period).unwrap(),
+                departure,
+                arrival,
+                transport_mode: self.transport_mode(journey, line),
+                operating_period: period,
                 line: line.short_name.clone(),
                 controller: self.authorities[&line.authority].short_name.clone(),
+                controller_contact: self.contact_index.get(&line.authority).copied(),
+                authority_chain: self.authority_chain_index.get(&line.authority).copied(),
+                monitored: line.monitored,
+                notices: self.notice_index.get(&journey.id).copied(),
+                public_code: journey.public_code.clone(),
+                platform: self.platforms.get(&start_stop).cloned(),
+                network: self.networks.get(&line.id).cloned(),
+                url: line.url.clone(),
+                pattern_name: self.pattern_names.get(&journey.pattern_ref).cloned(),
+                trip: journey.id,
+                stop_sequence: (
+                    self.position(pre.stop_point_in_journey_pattern),
+                    self.position(current.stop_point_in_journey_pattern),
+                ),
             });
         }
         local_edges
@@ -296,13 +1140,25 @@ impl JourneyTransformer {
 
 impl Graph {
     pub fn from_data(data: &[NetexData], walk_seconds: &[WalkEdge]) -> Graph {
-        // nodes contains stops deduplicated by short name
-        let nodes = Nodes::from_data(data);
+        Self::from_data_with_options(data, walk_seconds, &GraphOptions::default())
+    }
+
+    pub fn from_data_with_options(
+        data: &[NetexData],
+        walk_seconds: &[WalkEdge],
+        options: &GraphOptions,
+    ) -> Graph {
+        // nodes contains stops deduplicated by their normalized short name
+        let nodes = Nodes::from_data(data, options);
         let journey_transformer = JourneyTransformer::from_data(data);
 
-        let mut edges = data
-            .par_iter()
-            .progress()
+        // no progress bar is constructed at all when quiet
+        let data_iter = if crate::is_quiet() {
+            Either::Left(data.par_iter())
+        } else {
+            Either::Right(data.par_iter().progress())
+        };
+        let mut edges = data_iter
             .flat_map(|d| d.service_journeys.par_iter())
             .map(|journey| journey_transformer.to_edges(journey, &nodes))
             .reduce(HashMap::<(usize, usize), Edge>::new, |a, mut b| {
@@ -313,10 +1169,7 @@ impl Graph {
                         end_node: key.1,
                         timetable: Timetable::default(),
                     });
-                    entry
-                        .timetable
-                        .journeys
-                        .extend(value.timetable.journeys.into_iter());
+                    entry.timetable.journeys.extend(value.timetable.journeys);
                 }
                 b
             });
@@ -327,9 +1180,10 @@ impl Graph {
         }
 
         // filter non-sensical journeys
-        edges.par_iter_mut().for_each(|(_, edge)| {
-            Self::filter_journeys(edge, &nodes);
-        });
+        let speed_outliers = edges
+            .par_iter_mut()
+            .map(|(_, edge)| Self::filter_journeys(edge, &nodes))
+            .sum();
 
         // map global operating period index to local index
         edges.par_iter_mut().for_each(|(_, edge)| {
@@ -344,15 +1198,24 @@ impl Graph {
             }
             let mut local_ops = vec![OperatingPeriod::default(); global_to_local.len()];
             for (global, local) in &global_to_local {
-                let uic_op = Self::lookup_operating_period(data, *global).expect(
-                    "failed to map global operating period index to concrete operating period",
-                );
+                let uic_op = Self::lookup_operating_period(
+                    data,
+                    &journey_transformer.derived_periods,
+                    *global,
+                )
+                .unwrap_or_else(|| {
+                    panic!(
+                        "failed to map global operating period index {global} to one of {} operating periods",
+                        period_count(data, &journey_transformer.derived_periods)
+                    )
+                });
                 local_ops[*local] = OperatingPeriod {
                     from: uic_op.from,
                     to: uic_op.to,
                     valid_day_bits: base64::engine::general_purpose::STANDARD
                         .encode(&uic_op.valid_day_bits),
                     valid_day: uic_op.valid_day_bits.clone(),
+                    canonical_source_id: Some(uic_op.id_ref.clone()),
                 }
             }
             for journey in &mut edge.timetable.journeys {
@@ -366,7 +1229,57 @@ impl Graph {
         Graph {
             nodes: nodes.vec,
             edges: edges.into_values().collect(),
+            hubs: nodes.hubs,
+            contacts: journey_transformer.contacts,
+            authority_chains: journey_transformer.authority_chains,
+            notices: journey_transformer.notices,
+            speed_outliers,
+        }
+    }
+
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> petgraph::Graph<Node, Timetable> {
+        self.map_petgraph(|edge| edge.timetable.clone())
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[allow(clippy::cast_precision_loss, clippy::cast_lossless)]
+    pub fn to_weighted_petgraph(&self, weight: EdgeWeight) -> petgraph::Graph<Node, f32> {
+        self.map_petgraph(|edge| match weight {
+            EdgeWeight::JourneyCount => edge.timetable.journeys.len() as f32,
+            EdgeWeight::TravelTime => edge
+                .timetable
+                .journeys
+                .iter()
+                .map(|j| {
+                    let (arrival, departure) =
+                        (j.arrival.minute_of_day(), j.departure.minute_of_day());
+                    if arrival < departure {
+                        arrival + 24 * 60 - departure
+                    } else {
+                        arrival - departure
+                    }
+                })
+                .min()
+                .map_or(edge.walk_seconds as f32 / 60.0, f32::from),
+        })
+    }
+
+    #[cfg(feature = "petgraph")]
+    fn map_petgraph<E>(&self, weight: impl Fn(&Edge) -> E) -> petgraph::Graph<Node, E> {
+        let mut result = petgraph::Graph::with_capacity(self.nodes.len(), self.edges.len());
+        // node indices match since petgraph assigns them sequentially
+        for node in &self.nodes {
+            result.add_node(node.clone());
+        }
+        for edge in &self.edges {
+            result.add_edge(
+                petgraph::graph::NodeIndex::new(edge.start_node),
+                petgraph::graph::NodeIndex::new(edge.end_node),
+                weight(edge),
+            );
         }
+        result
     }
 
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
@@ -375,7 +1288,9 @@ impl Graph {
             nodes.index_by_id(walk_edge.start),
             nodes.index_by_id(walk_edge.end),
         ) else {
-            println!("{} -> {} borked", walk_edge.start, walk_edge.end);
+            if !crate::is_quiet() {
+                eprintln!("{} -> {} borked", walk_edge.start, walk_edge.end);
+            }
             return;
         };
         let start_node = nodes.get(start_idx);
@@ -403,17 +1318,44 @@ impl Graph {
         backward.walk_seconds = walk_edge.duration as u16;
     }
 
+    // haversine distance between the start and end node in meters
+    pub fn edge_length(&self, edge: &Edge) -> f64 {
+        let start = &self.nodes[edge.start_node];
+        let end = &self.nodes[edge.end_node];
+        Haversine.distance(
+            geo::Point::new(f64::from(start.long), f64::from(start.lat)),
+            geo::Point::new(f64::from(end.long), f64::from(end.lat)),
+        )
+    }
+
+    // initial great circle bearing from the start to the end node in degrees,
+    // clockwise from north in [0, 360)
+    pub fn edge_bearing(&self, edge: &Edge) -> f64 {
+        let start = &self.nodes[edge.start_node];
+        let end = &self.nodes[edge.end_node];
+        if (start.long, start.lat) == (end.long, end.lat) {
+            return 0.0;
+        }
+        let start_lat = f64::from(start.lat).to_radians();
+        let end_lat = f64::from(end.lat).to_radians();
+        let diff_lon = f64::from(end.long - start.long).to_radians();
+        let y = diff_lon.sin() * end_lat.cos();
+        let x = start_lat.cos() * end_lat.sin() - start_lat.sin() * end_lat.cos() * diff_lon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
     #[allow(clippy::cast_lossless)]
-    fn filter_journeys(edge: &mut Edge, nodes: &Nodes) {
+    fn filter_journeys(edge: &mut Edge, nodes: &Nodes) -> usize {
         let start_node = nodes.get(edge.start_node);
         let end_node = nodes.get(edge.end_node);
         let distance = great_circle_distance(
             (start_node.long, start_node.lat),
             (end_node.long, end_node.lat),
         );
+        let before = edge.timetable.journeys.len();
         edge.timetable.journeys.retain(|j| {
-            let departure_min = (j.departure % 60) + ((j.departure / 60) * 60);
-            let mut arrival_min = (j.arrival % 60) + ((j.arrival / 60) * 60);
+            let departure_min = j.departure.minute_of_day();
+            let mut arrival_min = j.arrival.minute_of_day();
             if arrival_min < departure_min {
                 arrival_min += 24 * 60;
             }
@@ -422,23 +1364,104 @@ impl Graph {
             let speed = distance / hours;
             speed < 325.0 || (minutes < 3 && distance < 3.0)
         });
+        before - edge.timetable.journeys.len()
     }
 
-    fn lookup_operating_period(
-        data: &[NetexData],
+    // the netex id of the canonical period behind a period of the given edge
+    pub fn period_source(&self, local_idx: usize, edge: usize) -> Option<&str> {
+        self.edges
+            .get(edge)?
+            .timetable
+            .periods
+            .get(local_idx)?
+            .canonical_source_id
+            .as_deref()
+    }
+
+    fn lookup_operating_period<'a>(
+        data: &'a [NetexData],
+        derived: &'a [UicOperatingPeriod],
         mut global_index: usize,
-    ) -> Option<&UicOperatingPeriod> {
+    ) -> Option<&'a UicOperatingPeriod> {
         for one_data in data {
             if global_index < one_data.operating_periods.len() {
                 return Some(&one_data.operating_periods[global_index]);
             }
             global_index -= one_data.operating_periods.len();
         }
-        None
+        derived.get(global_index)
     }
 }
 
-fn great_circle_distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+fn period_count(data: &[NetexData], derived: &[UicOperatingPeriod]) -> usize {
+    data.iter()
+        .map(|d| d.operating_periods.len())
+        .sum::<usize>()
+        + derived.len()
+}
+
+// The most preferred and connected stop of a cluster keeps its coordinates,
+// the centroid is used when no stop stands out.
+fn cluster_position<R: Ord + Copy>(points: &[(geo::Coord<f32>, R)]) -> geo::Point<f32> {
+    let best = points.iter().map(|(_, rank)| *rank).max();
+    let worst = points.iter().map(|(_, rank)| *rank).min();
+    if best > worst {
+        if let Some((coord, _)) = points.iter().find(|(_, rank)| Some(*rank) == best) {
+            return geo::Point::from(*coord);
+        }
+    }
+    let coords: Vec<geo::Coord<f32>> = points.iter().map(|(coord, _)| *coord).collect();
+    geo::LineString::new(coords)
+        .centroid()
+        .expect("failed to calculate centroid")
+}
+
+// dates are packed as day 5 bit, month 4 bit, year 7 bit
+pub fn unpack_date(date: u16) -> (u16, u16, u16) {
+    let year = date & 0b0111_1111;
+    let month = (date >> 7) & 0b1111;
+    let day = (date >> 11) & 0b1_1111;
+    (2000 + year, month, day)
+}
+
+// days since 1970-01-01, following Howard Hinnant's days_from_civil
+pub fn days_since_epoch(date: u16) -> i64 {
+    let (year, month, day) = unpack_date(date);
+    let (month, day) = (i64::from(month), i64::from(day));
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// inverse of days_since_epoch, following Howard Hinnant's civil_from_days
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn date_from_days(days: i64) -> u16 {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    ((year - 2000) + (month << 7) + (day << 11)) as u16
+}
+
+pub fn format_date(date: u16) -> String {
+    let (year, month, day) = unpack_date(date);
+    format!("{year}-{month:02}-{day:02}")
+}
+
+pub(crate) fn great_circle_distance(a: (f32, f32), b: (f32, f32)) -> f32 {
     use std::f32::consts;
     let a_lon = a.0 * consts::PI / 180.0;
     let a_lat = a.1 * consts::PI / 180.0;
@@ -450,3 +1473,962 @@ fn great_circle_distance(a: (f32, f32), b: (f32, f32)) -> f32 {
     let angle = intermediate.acos();
     6371.009 * angle
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DayMinute, Graph, Journey, Node, OperatingPeriod, WeeklyCalendar};
+    use crate::parser::{
+        AccessibilityLimitation, InterchangeWeighting, LimitationStatus, NetexData,
+        PassengerStopAssignment, Quay, ScheduledStopPoint, StopPlace, TypeOfPlace,
+    };
+
+    #[test]
+    fn display_node() {
+        let node = Node {
+            id: 1,
+            short_name: "Köln Hbf".to_owned(),
+            long: 6.95,
+            lat: 50.94,
+            ..Node::default()
+        };
+        assert_eq!(node.to_string(), "Köln Hbf @ 50.94,6.95");
+    }
+
+    #[test]
+    fn day_minute() {
+        let time = DayMinute(605);
+        assert_eq!(
+            (time.hours(), time.minutes(), time.wrapped_day()),
+            (10, 5, 0)
+        );
+        assert_eq!(time.to_string(), "10:05");
+        let overnight = DayMinute(25 * 60 + 10);
+        assert_eq!(overnight.wrapped_day(), 1);
+        assert_eq!(overnight.to_string(), "01:10");
+        assert_eq!(DayMinute(24 * 60 - 1).wrapped_day(), 0);
+        assert_eq!(overnight.minute_of_day(), 1510);
+        assert_eq!(DayMinute(70).plus_days(1), overnight);
+        assert_eq!(time.checked_add(5), Some(DayMinute(610)));
+        assert_eq!(DayMinute(u16::MAX).checked_add(1), None);
+        assert_eq!(serde_json::to_value(time).unwrap(), 605);
+    }
+
+    #[test]
+    fn display_journey() {
+        let journey = Journey {
+            departure: DayMinute(605),
+            arrival: DayMinute(1439),
+            transport_mode: "rail".to_owned(),
+            operating_period: 0,
+            line: "RE 5".to_owned(),
+            controller: "DB".to_owned(),
+            ..Journey::default()
+        };
+        assert_eq!(journey.to_string(), "RE 5 10:05→23:59 rail");
+    }
+
+    #[test]
+    fn display_operating_period() {
+        let period = OperatingPeriod {
+            // 2022-06-13 and 2022-12-10
+            from: 22 + (6 << 7) + (13 << 11),
+            to: 22 + (12 << 7) + (10 << 11),
+            ..OperatingPeriod::default()
+        };
+        assert_eq!(period.to_string(), "2022-06-13–2022-12-10");
+    }
+
+    #[test]
+    fn edge_bearing() {
+        let node = |long, lat| Node {
+            long,
+            lat,
+            ..Node::default()
+        };
+        let edge = |start_node, end_node| super::Edge {
+            start_node,
+            end_node,
+            timetable: super::Timetable::default(),
+            walk_seconds: u16::MAX,
+        };
+        let graph = Graph {
+            nodes: vec![node(6.95, 50.94), node(6.96, 50.94), node(6.95, 50.93)],
+            ..Graph::default()
+        };
+        assert!((graph.edge_bearing(&edge(0, 1)) - 90.0).abs() < 0.01);
+        assert!((graph.edge_bearing(&edge(1, 0)) - 270.0).abs() < 0.01);
+        assert!((graph.edge_bearing(&edge(0, 2)) - 180.0).abs() < 0.01);
+        assert!(graph.edge_bearing(&edge(0, 0)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn period_source() {
+        let graph = crate::fixtures::graph();
+        assert_eq!(graph.period_source(0, 0), Some("op:1"));
+        assert_eq!(graph.period_source(1, 0), None);
+        assert_eq!(graph.period_source(0, graph.edges.len()), None);
+    }
+
+    #[test]
+    fn canonical_periods() {
+        let xml = crate::fixtures::DOCUMENT
+            .replace("op:1", "op:2")
+            .replace("dt:1", "dt:2")
+            .replace("sj:1", "sj:2")
+            .replace(">08:", ">09:");
+        let copy = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[crate::fixtures::data(), copy], &[]);
+        for edge in &graph.edges {
+            assert_eq!(edge.timetable.journeys.len(), 2);
+            assert_eq!(edge.timetable.periods.len(), 1);
+            assert!(edge
+                .timetable
+                .journeys
+                .iter()
+                .all(|journey| journey.operating_period == 0));
+        }
+        // op:2 collapses into op:1, which is reported as the canonical source
+        assert_eq!(graph.period_source(0, 0), Some("op:1"));
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn edge_modes() {
+        let journey = |mode: &str| Journey {
+            transport_mode: mode.to_owned(),
+            ..Journey::default()
+        };
+        let edge = super::Edge {
+            start_node: 0,
+            end_node: 1,
+            timetable: super::Timetable {
+                journeys: vec![journey("tram"), journey("bus"), journey("tram")],
+                periods: vec![OperatingPeriod::default()],
+            },
+            walk_seconds: u16::MAX,
+        };
+        let modes: Vec<String> = edge.modes().into_iter().collect();
+        assert_eq!(modes, vec!["bus", "tram"]);
+    }
+
+    #[test]
+    fn weekly_pattern() {
+        let mut period = OperatingPeriod {
+            // 2022-06-13 (a monday) and 2022-06-26
+            from: 22 + (6 << 7) + (13 << 11),
+            to: 22 + (6 << 7) + (26 << 11),
+            // 11111001111100
+            valid_day: vec![159, 15],
+            ..OperatingPeriod::default()
+        };
+        assert_eq!(
+            period.to_weekly_pattern(),
+            Some(WeeklyCalendar {
+                monday: true,
+                tuesday: true,
+                wednesday: true,
+                thursday: true,
+                friday: true,
+                saturday: false,
+                sunday: false,
+                from: period.from,
+                to: period.to,
+            })
+        );
+        // 11111001111000, no service on the second friday
+        period.valid_day = vec![159, 7];
+        assert_eq!(period.to_weekly_pattern(), None);
+    }
+
+    #[test]
+    fn normalized_node_names() {
+        let stop = |id, short_name: &str| ScheduledStopPoint {
+            id,
+            short_name: short_name.to_owned(),
+            long: 6.958,
+            lat: 50.943,
+            ..ScheduledStopPoint::default()
+        };
+        let data = || NetexData {
+            scheduled_stop_points: vec![stop(1, "Köln Hbf "), stop(2, "köln  hbf")],
+            ..NetexData::default()
+        };
+        let graph = Graph::from_data(&[data()], &[]);
+        assert_eq!(graph.nodes.len(), 2);
+
+        let options = super::GraphOptions {
+            name_key: super::normalize_name_lowercase,
+        };
+        let graph = Graph::from_data_with_options(&[data()], &[], &options);
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].id, 1 ^ 2);
+        assert_eq!(super::normalize_name("  Köln \t Hbf "), "Köln Hbf");
+    }
+
+    #[test]
+    fn node_parent_hub() {
+        let stop = |id, short_name: &str, long| ScheduledStopPoint {
+            id,
+            short_name: short_name.to_owned(),
+            long,
+            lat: 50.943,
+            ..ScheduledStopPoint::default()
+        };
+        let place = |id, parent| StopPlace {
+            id,
+            parent,
+            ..StopPlace::default()
+        };
+        let assignment = |scheduled_stop_point, stop_place| PassengerStopAssignment {
+            scheduled_stop_point,
+            stop_place,
+            quay: None,
+        };
+        let data = NetexData {
+            scheduled_stop_points: vec![
+                stop(1, "Köln Hbf", 6.958),
+                stop(2, "Köln Hbf ZOB", 6.957),
+                stop(3, "Köln Dom", 6.959),
+            ],
+            stop_places: vec![
+                StopPlace {
+                    name: Some("Köln Hbf".to_owned()),
+                    ..place(10, None)
+                },
+                place(11, Some(10)),
+                place(12, Some(10)),
+            ],
+            passenger_stop_assignments: vec![assignment(1, 11), assignment(2, 12)],
+            ..NetexData::default()
+        };
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(
+            graph.hubs,
+            vec![super::Hub {
+                id: 10,
+                name: Some("Köln Hbf".to_owned())
+            }]
+        );
+        let hub = |name: &str| {
+            graph
+                .nodes
+                .iter()
+                .find(|node| node.short_name == name)
+                .unwrap()
+                .parent_hub
+        };
+        assert_eq!(hub("Köln Hbf"), Some(0));
+        assert_eq!(hub("Köln Hbf ZOB"), Some(0));
+        assert_eq!(hub("Köln Dom"), None);
+    }
+
+    #[test]
+    fn node_min_transfer_seconds() {
+        let data = NetexData {
+            scheduled_stop_points: vec![ScheduledStopPoint {
+                id: 1,
+                short_name: "Köln Hbf".to_owned(),
+                long: 6.95,
+                lat: 50.94,
+                ..ScheduledStopPoint::default()
+            }],
+            stop_places: vec![StopPlace {
+                id: 2,
+                min_transfer_seconds: Some(300),
+                ..StopPlace::default()
+            }],
+            passenger_stop_assignments: vec![PassengerStopAssignment {
+                scheduled_stop_point: 1,
+                stop_place: 2,
+                quay: None,
+            }],
+            ..NetexData::default()
+        };
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(graph.nodes[0].min_transfer_seconds, Some(300));
+    }
+
+    #[test]
+    fn node_stop_place_attributes() {
+        let data = NetexData {
+            scheduled_stop_points: vec![ScheduledStopPoint {
+                id: 1,
+                short_name: "Köln Hbf".to_owned(),
+                long: 6.95,
+                lat: 50.94,
+                ..ScheduledStopPoint::default()
+            }],
+            stop_places: vec![StopPlace {
+                id: 2,
+                stop_place_type: Some("railStation".to_owned()),
+                weighting: Some(InterchangeWeighting::PreferredInterchange),
+                accessibility: AccessibilityLimitation {
+                    step_free_access: LimitationStatus::True,
+                    ..AccessibilityLimitation::default()
+                },
+                compass_bearing: Some(270.0),
+                ..StopPlace::default()
+            }],
+            passenger_stop_assignments: vec![PassengerStopAssignment {
+                scheduled_stop_point: 1,
+                stop_place: 2,
+                quay: None,
+            }],
+            ..NetexData::default()
+        };
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.nodes.len(), 1);
+        assert_eq!(
+            graph.nodes[0].stop_place_type.as_deref(),
+            Some("railStation")
+        );
+        assert_eq!(
+            graph.nodes[0].weighting,
+            Some(InterchangeWeighting::PreferredInterchange)
+        );
+        assert_eq!(
+            graph.nodes[0].accessibility.step_free_access,
+            LimitationStatus::True
+        );
+        assert_eq!(graph.nodes[0].compass_bearing, Some(270.0));
+        assert_eq!(graph.nodes[0].type_of_place, Some(TypeOfPlace::Station));
+    }
+
+    #[test]
+    fn weighted_stop_keeps_coordinates() {
+        let stop = |id, long| ScheduledStopPoint {
+            id,
+            short_name: "Köln Hbf".to_owned(),
+            long,
+            lat: 50.943,
+            ..ScheduledStopPoint::default()
+        };
+        let data = NetexData {
+            scheduled_stop_points: vec![stop(1, 6.955), stop(2, 6.959)],
+            stop_places: vec![StopPlace {
+                id: 3,
+                weighting: Some(InterchangeWeighting::PreferredInterchange),
+                ..StopPlace::default()
+            }],
+            passenger_stop_assignments: vec![PassengerStopAssignment {
+                scheduled_stop_point: 2,
+                stop_place: 3,
+                quay: None,
+            }],
+            ..NetexData::default()
+        };
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.nodes.len(), 1);
+        assert!((graph.nodes[0].long - 6.959).abs() < 1e-6);
+        // without a preferred stop the cluster is placed at its centroid
+        let graph = Graph::from_data(
+            &[NetexData {
+                scheduled_stop_points: vec![stop(1, 6.955), stop(2, 6.959)],
+                ..NetexData::default()
+            }],
+            &[],
+        );
+        assert!((graph.nodes[0].long - 6.957).abs() < 1e-6);
+    }
+
+    #[test]
+    fn unweighted_stop_ranks_like_no_interchange() {
+        let stop = |id, long| ScheduledStopPoint {
+            id,
+            short_name: "Köln Hbf".to_owned(),
+            long,
+            lat: 50.943,
+            ..ScheduledStopPoint::default()
+        };
+        let data = NetexData {
+            scheduled_stop_points: vec![stop(1, 6.955), stop(2, 6.959)],
+            stop_places: vec![
+                StopPlace {
+                    id: 3,
+                    quays: vec![Quay::default(), Quay::default()],
+                    ..StopPlace::default()
+                },
+                StopPlace {
+                    id: 4,
+                    weighting: Some(InterchangeWeighting::NoInterchange),
+                    ..StopPlace::default()
+                },
+            ],
+            passenger_stop_assignments: vec![
+                PassengerStopAssignment {
+                    scheduled_stop_point: 1,
+                    stop_place: 3,
+                    quay: None,
+                },
+                PassengerStopAssignment {
+                    scheduled_stop_point: 2,
+                    stop_place: 4,
+                    quay: None,
+                },
+            ],
+            ..NetexData::default()
+        };
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.nodes.len(), 1);
+        // the stop with more quays wins, the explicit noInterchange does not outrank it
+        assert!((graph.nodes[0].long - 6.955).abs() < 1e-6);
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn to_petgraph_components() {
+        use super::{Edge, EdgeWeight, Timetable};
+        let node = |id: u64| Node {
+            id,
+            ..Node::default()
+        };
+        let edge = |start_node: usize, end_node: usize| Edge {
+            start_node,
+            end_node,
+            timetable: Timetable::default(),
+            walk_seconds: 120,
+        };
+        let graph = Graph {
+            nodes: vec![node(0), node(1), node(2), node(3)],
+            edges: vec![edge(0, 1), edge(1, 0), edge(2, 3)],
+            ..Graph::default()
+        };
+        let petgraph = graph.to_petgraph();
+        assert_eq!(petgraph.node_count(), 4);
+        assert_eq!(petgraph.edge_count(), 3);
+        assert_eq!(petgraph::algo::connected_components(&petgraph), 2);
+        let weighted = graph.to_weighted_petgraph(EdgeWeight::TravelTime);
+        assert!(weighted
+            .edge_weights()
+            .all(|w| (*w - 2.0).abs() < f32::EPSILON));
+    }
+
+    #[test]
+    fn journey_pattern_name() {
+        let graph = crate::fixtures::graph();
+        assert_eq!(graph.edges.len(), 2);
+        for edge in &graph.edges {
+            assert_eq!(
+                edge.timetable.journeys[0].pattern_name.as_deref(),
+                Some("Hbf - Mülheim")
+            );
+        }
+    }
+
+    #[test]
+    fn date_from_days() {
+        for date in [
+            22 + (6 << 7) + (13 << 11),
+            24 + (2 << 7) + (29 << 11),
+            23 + (12 << 7) + (31 << 11),
+        ] {
+            assert_eq!(super::date_from_days(super::days_since_epoch(date)), date);
+        }
+    }
+
+    #[test]
+    fn weekday_of_bit() {
+        let period = OperatingPeriod {
+            // 2022-06-13 was a monday
+            from: 22 + (6 << 7) + (13 << 11),
+            to: 22 + (6 << 7) + (19 << 11),
+            ..OperatingPeriod::default()
+        };
+        assert_eq!(period.weekday_of_bit(0), super::Weekday::Monday);
+        assert_eq!(period.weekday_of_bit(6), super::Weekday::Sunday);
+        assert_eq!(period.weekday_of_bit(7), super::Weekday::Monday);
+        // 2024-02-28 was a wednesday, the leap day makes bit 2 a friday
+        let leap = OperatingPeriod {
+            from: 24 + (2 << 7) + (28 << 11),
+            ..OperatingPeriod::default()
+        };
+        assert_eq!(leap.weekday_of_bit(0), super::Weekday::Wednesday);
+        assert_eq!(leap.weekday_of_bit(2), super::Weekday::Friday);
+    }
+
+    #[test]
+    fn days_since_epoch() {
+        // 2022-06-13 and 2000-03-01
+        assert_eq!(super::days_since_epoch(22 + (6 << 7) + (13 << 11)), 19_156);
+        assert_eq!(super::days_since_epoch((3 << 7) + (1 << 11)), 11_017);
+    }
+
+    #[test]
+    fn unavailable_assignment_removes_service() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ServiceJourney ",
+            r#"<DayTypeAssignment id="dta:2">
+                <OperatingPeriodRef ref="op:1"/>
+                <DayTypeRef ref="dt:1"/>
+                <isAvailable>false</isAvailable>
+            </DayTypeAssignment>
+            <ServiceJourney "#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[data], &[]);
+        for edge in &graph.edges {
+            assert_eq!(edge.timetable.periods.len(), 1);
+            assert_eq!(edge.timetable.periods[0].valid_day, vec![0]);
+        }
+        let graph = crate::fixtures::graph();
+        assert_eq!(
+            graph.edges[0].timetable.periods[0].valid_day,
+            vec![0b1_1111]
+        );
+    }
+
+    #[test]
+    fn authority_chain() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            r#"<Authority id="auth:1"><ShortName>KVB</ShortName></Authority>"#,
+            r#"<Authority id="auth:0"><ShortName>VRS</ShortName></Authority>
+            <Authority id="auth:1">
+                <ShortName>KVB</ShortName>
+                <ParentOrganisationRef ref="auth:0"/>
+            </Authority>"#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.authority_chains, vec![vec!["KVB", "VRS"]]);
+        // every journey of the controller references the same entry
+        assert!(graph
+            .edges
+            .iter()
+            .flat_map(|edge| edge.timetable.journeys.iter())
+            .all(|journey| journey.authority_chain == Some(0)));
+        let json = serde_json::to_value(&graph.edges[0].timetable.journeys[0]).unwrap();
+        assert_eq!(json["h"], 0);
+
+        let graph = crate::fixtures::graph();
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert!(graph.authority_chains.is_empty());
+        assert_eq!(journey.authority_chain, None);
+        assert!(serde_json::to_value(journey).unwrap().get("h").is_none());
+    }
+
+    #[test]
+    fn controller_contact() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            r#"<Authority id="auth:1"><ShortName>KVB</ShortName></Authority>"#,
+            r#"<Authority id="auth:1">
+                <ShortName>KVB</ShortName>
+                <ContactDetails><Phone>+49 221 547 0</Phone><Url>https://www.kvb.koeln</Url></ContactDetails>
+                <CustomerServiceContactDetails><Phone>+49 221 547 3333</Phone></CustomerServiceContactDetails>
+            </Authority>"#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let contact = data.authorities[0].contact.clone().unwrap();
+        assert_eq!(contact.phone.as_deref(), Some("+49 221 547 3333"));
+        assert_eq!(contact.url.as_deref(), Some("https://www.kvb.koeln"));
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.contacts, vec![contact]);
+        // every journey of the controller references the same entry
+        assert!(graph
+            .edges
+            .iter()
+            .flat_map(|edge| edge.timetable.journeys.iter())
+            .all(|journey| journey.controller_contact == Some(0)));
+        let journey = &graph.edges[0].timetable.journeys[0];
+        let json = serde_json::to_value(journey).unwrap();
+        assert_eq!(json["k"], 0);
+
+        let graph = crate::fixtures::graph();
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert!(serde_json::to_value(journey).unwrap().get("k").is_none());
+    }
+
+    #[test]
+    fn dated_journey_cancellation() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "</PublicationDelivery>",
+            r#"<OperatingDay id="od:1"><CalendarDate>2022-06-14</CalendarDate></OperatingDay>
+            <DatedServiceJourney id="dsj:1">
+                <ServiceJourneyRef ref="sj:1"/>
+                <OperatingDayRef ref="od:1"/>
+                <ServiceAlteration>cancellation</ServiceAlteration>
+            </DatedServiceJourney>
+            </PublicationDelivery>"#,
+        );
+        // 2022-06-13 and 2022-06-14
+        let monday = 22 + (6 << 7) + (13 << 11);
+        let tuesday = 22 + (6 << 7) + (14 << 11);
+        let graph = crate::fixtures::graph();
+        let timetable = &graph.edges[0].timetable;
+        let period = &timetable.periods[timetable.journeys[0].operating_period];
+        assert!(period.is_valid_on(monday));
+        assert!(period.is_valid_on(tuesday));
+
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[data], &[]);
+        for edge in &graph.edges {
+            let timetable = &edge.timetable;
+            let period = &timetable.periods[timetable.journeys[0].operating_period];
+            assert!(period.is_valid_on(monday));
+            assert!(!period.is_valid_on(tuesday));
+        }
+    }
+
+    #[test]
+    fn dated_journey_outside_period() {
+        // op:1 runs from 2022-06-13 to 2022-06-19
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "</PublicationDelivery>",
+            r#"<OperatingDay id="od:1"><CalendarDate>2022-06-11</CalendarDate></OperatingDay>
+            <OperatingDay id="od:2"><CalendarDate>2022-06-25</CalendarDate></OperatingDay>
+            <DatedServiceJourney id="dsj:1">
+                <ServiceJourneyRef ref="sj:1"/>
+                <OperatingDayRef ref="od:1"/>
+            </DatedServiceJourney>
+            <DatedServiceJourney id="dsj:2">
+                <ServiceJourneyRef ref="sj:1"/>
+                <OperatingDayRef ref="od:2"/>
+            </DatedServiceJourney>
+            </PublicationDelivery>"#,
+        );
+        let june = |day: u16| 22 + (6 << 7) + (day << 11);
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[data], &[]);
+        let timetable = &graph.edges[0].timetable;
+        let period = &timetable.periods[timetable.journeys[0].operating_period];
+        assert_eq!((period.from, period.to), (june(11), june(25)));
+        let valid: Vec<u16> = (10..=26)
+            .filter(|day| period.is_valid_on(june(*day)))
+            .collect();
+        assert_eq!(valid, vec![11, 13, 14, 15, 16, 17, 25]);
+    }
+
+    #[test]
+    fn journey_notices() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "</PublicationDelivery>",
+            r#"<Notice id="n:1"><Text lang="de">Nur an Schultagen</Text></Notice>
+            <NoticeAssignment id="na:1">
+                <NoticeRef ref="n:1"/>
+                <NoticedObjectRef ref="sj:1"/>
+            </NoticeAssignment>
+            </PublicationDelivery>"#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.notices.len(), 1);
+        assert_eq!(graph.notices[0].len(), 1);
+        assert_eq!(graph.notices[0][0].text, "Nur an Schultagen");
+        assert_eq!(graph.notices[0][0].lang.as_deref(), Some("de"));
+        for edge in &graph.edges {
+            assert_eq!(edge.timetable.journeys[0].notices, Some(0));
+        }
+        let json = serde_json::to_value(&graph.edges[0].timetable.journeys[0]).unwrap();
+        assert_eq!(json["x"], 0);
+        let graph = crate::fixtures::graph();
+        assert!(graph.notices.is_empty());
+        assert_eq!(graph.edges[0].timetable.journeys[0].notices, None);
+    }
+
+    #[test]
+    fn journey_public_code() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            r#"<ServiceJourney id="sj:1">"#,
+            r#"<ServiceJourney id="sj:1"><PrivateCode>4711</PrivateCode><PublicCode>ICE 123</PublicCode>"#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.service_journeys[0].public_code.as_deref(),
+            Some("ICE 123")
+        );
+        let graph = Graph::from_data(&[data], &[]);
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert_eq!(journey.public_code.as_deref(), Some("ICE 123"));
+        assert_eq!(serde_json::to_value(journey).unwrap()["p"], "ICE 123");
+    }
+
+    #[test]
+    fn monitored_line() {
+        let graph = crate::fixtures::graph();
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert!(!journey.monitored);
+        assert!(serde_json::to_value(journey).unwrap().get("m").is_none());
+
+        let xml = crate::fixtures::DOCUMENT.replace(
+            r#"<AuthorityRef ref="auth:1"/></Line>"#,
+            r#"<AuthorityRef ref="auth:1"/><Monitored>true</Monitored></Line>"#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert!(data.lines[0].monitored);
+        let graph = Graph::from_data(&[data], &[]);
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert!(journey.monitored);
+        assert_eq!(serde_json::to_value(journey).unwrap()["m"], true);
+    }
+
+    #[test]
+    fn inherit_line_transport_mode() {
+        let xml = crate::fixtures::DOCUMENT
+            .replace("<TransportMode>rail</TransportMode>", "")
+            .replace(
+                r#"<AuthorityRef ref="auth:1"/></Line>"#,
+                r#"<AuthorityRef ref="auth:1"/><TransportMode>rail</TransportMode></Line>"#,
+            );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert!(data.service_journeys[0].transport_mode.is_empty());
+        assert_eq!(data.service_journey_patterns[0].transport_mode, None);
+        let graph = Graph::from_data(&[data], &[]);
+        assert!(graph
+            .edges
+            .iter()
+            .flat_map(|edge| edge.timetable.journeys.iter())
+            .all(|journey| journey.transport_mode == "rail"));
+    }
+
+    #[test]
+    fn transport_mode_precedence() {
+        let xml = |journey_mode: &str| {
+            crate::fixtures::DOCUMENT
+                .replace("<TransportMode>rail</TransportMode>", journey_mode)
+                .replace(
+                    r#"<AuthorityRef ref="auth:1"/></Line>"#,
+                    r#"<AuthorityRef ref="auth:1"/><TransportMode>rail</TransportMode></Line>"#,
+                )
+                .replace(
+                    "<Name>Hbf - Mülheim</Name>",
+                    "<Name>Hbf - Mülheim</Name><TransportMode>bus</TransportMode>",
+                )
+        };
+        let modes = |xml: String| {
+            let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+            let graph = Graph::from_data(&[data], &[]);
+            graph
+                .edges
+                .iter()
+                .flat_map(|edge| edge.timetable.journeys.iter())
+                .map(|journey| journey.transport_mode.clone())
+                .collect::<std::collections::HashSet<String>>()
+        };
+        assert_eq!(modes(xml("")), ["bus".to_owned()].into());
+        assert_eq!(
+            modes(xml("<TransportMode>tram</TransportMode>")),
+            ["tram".to_owned()].into()
+        );
+    }
+
+    #[test]
+    fn quay_platform() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ServiceJourneyPattern ",
+            r#"<StopPlace id="sp:hbf">
+                <quays>
+                    <Quay id="q:2"><PublicCode>Gleis 2</PublicCode></Quay>
+                    <Quay id="q:3"><PublicCode>Gleis 3</PublicCode></Quay>
+                </quays>
+            </StopPlace>
+            <PassengerStopAssignment id="psa:1">
+                <ScheduledStopPointRef ref="ssp:a"/>
+                <StopPlaceRef ref="sp:hbf"/>
+                <QuayRef ref="q:3"/>
+            </PassengerStopAssignment>
+            <ServiceJourneyPattern "#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.stop_places[0].quays.len(), 2);
+        let graph = Graph::from_data(&[data], &[]);
+        let platform = |name: &str| {
+            let edge = graph
+                .edges
+                .iter()
+                .find(|edge| graph.nodes[edge.start_node].short_name == name)
+                .unwrap();
+            edge.timetable.journeys[0].platform.clone()
+        };
+        assert_eq!(platform("Köln Hbf").as_deref(), Some("Gleis 3"));
+        assert_eq!(platform("Köln Messe/Deutz"), None);
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert_eq!(
+            serde_json::to_value(journey).unwrap().get("q").is_some(),
+            journey.platform.is_some()
+        );
+    }
+
+    #[test]
+    fn line_network() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            r#"<Line id="line:1">"#,
+            r#"<Network id="nw:1">
+                <Name>VRS</Name>
+                <members><LineRef ref="line:1"/></members>
+                <groupsOfLines>
+                    <GroupOfLines id="gol:1">
+                        <Name>S-Bahn</Name>
+                        <members><LineRef ref="line:1"/></members>
+                    </GroupOfLines>
+                </groupsOfLines>
+            </Network>
+            <Line id="line:1">"#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.groups_of_lines.len(), 2);
+        let graph = Graph::from_data(&[data], &[]);
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert_eq!(journey.network.as_deref(), Some("S-Bahn"));
+        assert_eq!(serde_json::to_value(journey).unwrap()["g"], "S-Bahn");
+        let graph = crate::fixtures::graph();
+        assert_eq!(graph.edges[0].timetable.journeys[0].network, None);
+    }
+
+    #[test]
+    fn via_points() {
+        let xml = crate::fixtures::DOCUMENT
+            .replace(
+                "<ServiceJourneyPattern ",
+                r#"<RoutePoint id="rp:a"><projections><PointProjection><ProjectToPointRef ref="ssp:a"/></PointProjection></projections></RoutePoint>
+                <RoutePoint id="rp:v"><Location><Longitude>6.966</Longitude><Latitude>50.945</Latitude></Location></RoutePoint>
+                <RoutePoint id="rp:b"><projections><PointProjection><ProjectToPointRef ref="ssp:b"/></PointProjection></projections></RoutePoint>
+                <Route id="r:1"><pointsInSequence>
+                    <PointOnRoute order="1"><RoutePointRef ref="rp:a"/></PointOnRoute>
+                    <PointOnRoute order="2"><RoutePointRef ref="rp:v"/></PointOnRoute>
+                    <PointOnRoute order="3"><RoutePointRef ref="rp:b"/></PointOnRoute>
+                </pointsInSequence></Route>
+                <ServiceJourneyPattern "#,
+            )
+            .replace(
+                r#"<StopPointInJourneyPattern id="sp:2">"#,
+                r#"<TimingPointInJourneyPattern id="tp:v"><TimingPointRef ref="rp:v"/></TimingPointInJourneyPattern>
+                <StopPointInJourneyPattern id="sp:2">"#,
+            )
+            .replace(
+                r#"<TimetabledPassingTime>
+                <StopPointInJourneyPatternRef ref="sp:2"/>"#,
+                r#"<TimetabledPassingTime>
+                <StopPointInJourneyPatternRef ref="tp:v"/>
+                <DepartureTime>08:03:00</DepartureTime>
+            </TimetabledPassingTime>
+            <TimetabledPassingTime>
+                <StopPointInJourneyPatternRef ref="sp:2"/>"#,
+            );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.service_journeys[0].passing_times.len(), 4);
+        let via: Vec<bool> = data.route_points.iter().map(|point| point.is_via).collect();
+        assert_eq!(via, vec![false, true, false]);
+        let shape = data.route_shape(crate::parser::hash_id("r:1"));
+        assert_eq!(
+            shape,
+            vec![(6.958, 50.943), (6.966, 50.945), (6.975, 50.940)]
+        );
+
+        let graph = Graph::from_data(&[data], &[]);
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.edges.len(), 2);
+        let hbf = graph
+            .edges
+            .iter()
+            .find(|edge| graph.nodes[edge.start_node].short_name == "Köln Hbf")
+            .unwrap();
+        assert_eq!(graph.nodes[hbf.end_node].short_name, "Köln Messe/Deutz");
+        assert_eq!(hbf.timetable.journeys[0].arrival.minute_of_day(), 485);
+    }
+
+    #[test]
+    fn missing_times() {
+        let legs = |xml: String| {
+            let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+            let graph = Graph::from_data(&[data], &[]);
+            let mut legs: Vec<(String, u16, u16)> = graph
+                .edges
+                .iter()
+                .flat_map(|edge| {
+                    let name = graph.nodes[edge.start_node].short_name.clone();
+                    edge.timetable.journeys.iter().map(move |j| {
+                        (
+                            name.clone(),
+                            j.departure.minute_of_day(),
+                            j.arrival.minute_of_day(),
+                        )
+                    })
+                })
+                .collect();
+            legs.sort();
+            legs
+        };
+        let departure_only = crate::fixtures::DOCUMENT.replace(
+            "<ArrivalTime>08:05:00</ArrivalTime>\n                <DepartureTime>08:06:00</DepartureTime>",
+            "<DepartureTime>08:06:00</DepartureTime>",
+        );
+        let data = NetexData::from_xml(departure_only.as_bytes(), departure_only.len()).unwrap();
+        let times = &data.service_journeys[0].passing_times;
+        assert_eq!(times[0].arrival, None);
+        assert_eq!(times[1].arrival, None);
+        assert_eq!(times[2].arrival, Some(DayMinute(492)));
+        assert_eq!(times[2].departure, None);
+        // Deutz is reached at its departure time
+        assert_eq!(
+            legs(departure_only),
+            vec![
+                ("Köln Hbf".to_owned(), 480, 486),
+                ("Köln Messe/Deutz".to_owned(), 486, 492)
+            ]
+        );
+
+        // without any time at Deutz there is neither a leg into nor out of it,
+        // and the arrival only terminal has no outgoing leg
+        let untimed = crate::fixtures::DOCUMENT.replace(
+            "<ArrivalTime>08:05:00</ArrivalTime>\n                <DepartureTime>08:06:00</DepartureTime>",
+            "",
+        );
+        assert!(legs(untimed).is_empty());
+    }
+
+    #[test]
+    fn line_url() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ShortName>S1</ShortName>",
+            "<ShortName>S1</ShortName><Url>https://www.kvb.koeln/s1</Url>",
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.lines[0].url.as_deref(),
+            Some("https://www.kvb.koeln/s1")
+        );
+        let graph = Graph::from_data(&[data], &[]);
+        let journey = &graph.edges[0].timetable.journeys[0];
+        assert_eq!(journey.url.as_deref(), Some("https://www.kvb.koeln/s1"));
+        assert_eq!(
+            serde_json::to_value(journey).unwrap()["u"],
+            "https://www.kvb.koeln/s1"
+        );
+        let graph = crate::fixtures::graph();
+        assert_eq!(graph.edges[0].timetable.journeys[0].url, None);
+    }
+
+    #[test]
+    fn topographic_locality() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ServiceJourneyPattern ",
+            r#"<TopographicPlace id="tp:koeln"><Descriptor><Name>Köln</Name></Descriptor></TopographicPlace>
+            <StopPlace id="sp:hbf"><TopographicPlaceRef ref="tp:koeln"/></StopPlace>
+            <StopPlace id="sp:deutz">
+                <TopographicPlaceRef ref="tp:koeln"/>
+                <PostalAddress id="pa:1"><Town>Köln-Deutz</Town></PostalAddress>
+            </StopPlace>
+            <PassengerStopAssignment id="psa:1">
+                <ScheduledStopPointRef ref="ssp:a"/><StopPlaceRef ref="sp:hbf"/>
+            </PassengerStopAssignment>
+            <PassengerStopAssignment id="psa:2">
+                <ScheduledStopPointRef ref="ssp:b"/><StopPlaceRef ref="sp:deutz"/>
+            </PassengerStopAssignment>
+            <ServiceJourneyPattern "#,
+        );
+        let data = NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = Graph::from_data(&[data], &[]);
+        let locality = |name: &str| {
+            graph
+                .nodes
+                .iter()
+                .find(|node| node.short_name == name)
+                .unwrap()
+                .locality
+                .clone()
+        };
+        assert_eq!(locality("Köln Hbf").as_deref(), Some("Köln"));
+        assert_eq!(locality("Köln Messe/Deutz").as_deref(), Some("Köln-Deutz"));
+        assert_eq!(locality("Köln-Mülheim"), None);
+    }
+}
@@ -0,0 +1,433 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::Path,
+};
+
+use crate::{
+    graph::{
+        date_from_days, days_since_epoch, unpack_date, DayMinute, Graph, Journey, OperatingPeriod,
+        Weekday,
+    },
+    timetable::Leg,
+};
+
+impl Graph {
+    // Writes a complete feed into one zip. Agencies are synthesized from the
+    // controllers of the journeys and service ids from their operating periods.
+    // Netex does not carry the timezone of an authority, so it is given for all.
+    // An agency url is the controller's contact url, otherwise that of one of its
+    // lines. Controllers without either fail the export, as GTFS requires one.
+    pub fn to_gtfs_zip(
+        &self,
+        path: impl AsRef<Path>,
+        timezone: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let trips = self.trip_legs(|_| true);
+        let mut agencies = BTreeMap::<&str, (usize, Option<&str>)>::new();
+        // a line served with several modes becomes one route per mode
+        let mut routes = BTreeMap::<(&str, &str, &str), usize>::new();
+        let mut services = HashMap::<&OperatingPeriod, usize>::new();
+        let mut periods = Vec::<&OperatingPeriod>::new();
+        for legs in trips.values() {
+            let journey = legs[0].journey;
+            let agency_count = agencies.len();
+            let contact_url = journey
+                .controller_contact
+                .and_then(|idx| self.contacts[idx].url.as_deref());
+            let agency = agencies
+                .entry(&journey.controller)
+                .or_insert((agency_count, contact_url));
+            agency.1 = agency.1.or(contact_url).or(journey.url.as_deref());
+            let route_count = routes.len();
+            routes.entry(route_key(journey)).or_insert(route_count);
+            let period = self.trip_period(legs);
+            services.entry(period).or_insert_with(|| {
+                periods.push(period);
+                periods.len() - 1
+            });
+        }
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("agency.txt", options)?;
+        writeln!(zip, "agency_id,agency_name,agency_url,agency_timezone")?;
+        for (name, (idx, url)) in &agencies {
+            let Some(url) = url else {
+                return Err(format!("agency {name} has neither a contact nor a line url").into());
+            };
+            let (name, url, timezone) = (csv_field(name), csv_field(url), csv_field(timezone));
+            writeln!(zip, "{idx},{name},{url},{timezone}")?;
+        }
+        zip.start_file("stops.txt", options)?;
+        writeln!(zip, "stop_id,stop_name,stop_lat,stop_lon")?;
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let name = csv_field(&node.short_name);
+            writeln!(zip, "{idx},{name},{},{}", node.lat, node.long)?;
+        }
+        zip.start_file("routes.txt", options)?;
+        writeln!(zip, "route_id,agency_id,route_short_name,route_type")?;
+        for ((line, controller, mode), idx) in &routes {
+            let agency = agencies[controller].0;
+            let route_type = route_type(mode);
+            writeln!(zip, "{idx},{agency},{},{route_type}", csv_field(line))?;
+        }
+        zip.start_file("trips.txt", options)?;
+        writeln!(zip, "route_id,service_id,trip_id")?;
+        for (trip, legs) in &trips {
+            let journey = legs[0].journey;
+            let route = routes[&route_key(journey)];
+            let period = self.trip_period(legs);
+            writeln!(zip, "{route},{},{trip}", services[period])?;
+        }
+        zip.start_file("stop_times.txt", options)?;
+        writeln!(
+            zip,
+            "trip_id,arrival_time,departure_time,stop_id,stop_sequence"
+        )?;
+        for (trip, legs) in &trips {
+            write_stop_times(&mut zip, *trip, legs)?;
+        }
+        write_calendars(&mut zip, options, &periods)?;
+        zip.finish()?;
+        Ok(())
+    }
+
+    // the operating period of a trip, taken from its first leg
+    fn trip_period(&self, legs: &[Leg]) -> &OperatingPeriod {
+        let leg = &legs[0];
+        &self.edges[leg.edge].timetable.periods[leg.journey.operating_period]
+    }
+}
+
+// stop_sequence follows the order of the journey's pattern, not of its passing times
+fn write_stop_times(mut w: impl Write, trip: u64, legs: &[Leg]) -> std::io::Result<()> {
+    let first = &legs[0];
+    let departure = format_time(first.journey.departure);
+    let mut sequence = first.journey.stop_sequence.0;
+    writeln!(
+        w,
+        "{trip},{departure},{departure},{},{sequence}",
+        first.start
+    )?;
+    for (idx, leg) in legs.iter().enumerate() {
+        let arrival = format_time(leg.journey.arrival);
+        // intermediate stops depart with the following leg
+        let departure = legs
+            .get(idx + 1)
+            .map_or(arrival.clone(), |next| format_time(next.journey.departure));
+        // segments of a densified leg share its positions, so they count on
+        sequence = leg.journey.stop_sequence.1.max(sequence + 1);
+        writeln!(w, "{trip},{arrival},{departure},{},{sequence}", leg.end)?;
+    }
+    Ok(())
+}
+
+fn route_key(journey: &Journey) -> (&str, &str, &str) {
+    (&journey.line, &journey.controller, &journey.transport_mode)
+}
+
+// Weekly periods go to calendar.txt, others list each valid day in calendar_dates.txt.
+fn write_calendars(
+    zip: &mut zip::ZipWriter<std::fs::File>,
+    options: zip::write::SimpleFileOptions,
+    periods: &[&OperatingPeriod],
+) -> zip::result::ZipResult<()> {
+    let (calendar, dates) = calendar_rows(periods);
+    zip.start_file("calendar.txt", options)?;
+    writeln!(
+        zip,
+        "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date"
+    )?;
+    for row in calendar {
+        writeln!(zip, "{row}")?;
+    }
+    zip.start_file("calendar_dates.txt", options)?;
+    writeln!(zip, "service_id,date,exception_type")?;
+    for row in dates {
+        writeln!(zip, "{row}")?;
+    }
+    Ok(())
+}
+
+// Rows of calendar.txt and calendar_dates.txt. Periods not repeating weekly get
+// the weekdays operated in most weeks, with the deviating days as exceptions:
+// 1 adds a day outside the pattern, 2 removes one inside it.
+fn calendar_rows(periods: &[&OperatingPeriod]) -> (Vec<String>, Vec<String>) {
+    let mut calendar = Vec::new();
+    let mut dates = Vec::new();
+    for (idx, period) in periods.iter().enumerate() {
+        let (first, last) = (days_since_epoch(period.from), days_since_epoch(period.to));
+        let week = period.to_weekly_pattern().map_or_else(
+            || majority_week(period),
+            |week| {
+                [
+                    week.monday,
+                    week.tuesday,
+                    week.wednesday,
+                    week.thursday,
+                    week.friday,
+                    week.saturday,
+                    week.sunday,
+                ]
+            },
+        );
+        let days = week.map(|valid| if valid { "1" } else { "0" }).join(",");
+        let (from, to) = (gtfs_date(period.from), gtfs_date(period.to));
+        calendar.push(format!("{idx},{days},{from},{to}"));
+        for days in first..=last {
+            let date = date_from_days(days);
+            let expected = week[Weekday::from_days(days) as usize];
+            match (expected, period.is_valid_on(date)) {
+                (false, true) => dates.push(format!("{idx},{},1", gtfs_date(date))),
+                (true, false) => dates.push(format!("{idx},{},2", gtfs_date(date))),
+                _ => {}
+            }
+        }
+    }
+    (calendar, dates)
+}
+
+// weekdays operated on more than half of their dates in the period
+fn majority_week(period: &OperatingPeriod) -> [bool; 7] {
+    // (valid, total) days per weekday
+    let mut counts = [(0_u32, 0_u32); 7];
+    for days in days_since_epoch(period.from)..=days_since_epoch(period.to) {
+        let count = &mut counts[Weekday::from_days(days) as usize];
+        count.0 += u32::from(period.is_valid_on(date_from_days(days)));
+        count.1 += 1;
+    }
+    counts.map(|(valid, total)| valid * 2 > total)
+}
+
+// GTFS dates are YYYYMMDD
+fn gtfs_date(date: u16) -> String {
+    let (year, month, day) = unpack_date(date);
+    format!("{year}{month:02}{day:02}")
+}
+
+fn route_type(mode: &str) -> u8 {
+    match mode {
+        "tram" => 0,
+        "metro" => 1,
+        "rail" => 2,
+        "water" => 4,
+        "cableway" => 6,
+        "funicular" => 7,
+        _ => 3,
+    }
+}
+
+// quotes fields containing separators, escaping inner quotes
+pub(crate) fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+// GTFS times are HH:MM:SS and may exceed 24 hours
+fn format_time(time: DayMinute) -> String {
+    let hours = time.hours() + 24 * time.wrapped_day();
+    format!("{hours:02}:{:02}:00", time.minutes())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::hash_id;
+
+    // the fixture graph with an info page on its line
+    fn line_url_graph() -> crate::graph::Graph {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ShortName>S1</ShortName>",
+            "<ShortName>S1</ShortName><Url>https://www.kvb.koeln/s1</Url>",
+        );
+        let data = crate::parser::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        crate::graph::Graph::from_data(&[data], &[])
+    }
+
+    #[test]
+    fn stop_times_follow_pattern_order() {
+        // the pattern passes a point the journey has no time at
+        let xml = crate::fixtures::DOCUMENT
+            .replace(
+                "<ShortName>S1</ShortName>",
+                "<ShortName>S1</ShortName><Url>https://www.kvb.koeln/s1</Url>",
+            )
+            .replace(
+                r#"<StopPointInJourneyPattern id="sp:2">"#,
+                r#"<StopPointInJourneyPattern id="sp:x"><ScheduledStopPointRef ref="ssp:x"/></StopPointInJourneyPattern>
+                <StopPointInJourneyPattern id="sp:2">"#,
+            );
+        let data = crate::parser::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = crate::graph::Graph::from_data(&[data], &[]);
+        let files = write_feed(&graph, "Europe/Berlin", &["stop_times.txt"]);
+        let rows: Vec<Vec<&str>> = files[0]
+            .lines()
+            .skip(1)
+            .map(|l| l.split(',').collect())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        let sequences: Vec<&str> = rows.iter().map(|r| r[4]).collect();
+        assert_eq!(sequences, vec!["1", "3", "4"]);
+        // stop ids are those of stops.txt
+        let start = graph
+            .nodes
+            .iter()
+            .position(|node| node.short_name == "Köln Hbf");
+        assert_eq!(rows[0][3], start.unwrap().to_string());
+        assert_eq!((rows[0][1], rows[0][2]), ("08:00:00", "08:00:00"));
+        assert_eq!((rows[1][1], rows[1][2]), ("08:05:00", "08:06:00"));
+        assert_eq!((rows[2][1], rows[2][2]), ("08:12:00", "08:12:00"));
+        assert_eq!(rows[2][0], hash_id("sj:1").to_string());
+    }
+
+    #[test]
+    fn agency_without_url() {
+        let path = std::env::temp_dir().join(format!(
+            "netex-parse-gtfs-{}-{}.zip",
+            std::process::id(),
+            COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        let err = crate::fixtures::graph()
+            .to_gtfs_zip(&path, "Europe/Berlin")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "agency KVB has neither a contact nor a line url"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn calendar_exceptions() {
+        let date = |month: u16, day: u16| 22 + (month << 7) + (day << 11);
+        // weekdays of three weeks from monday 2022-06-13, with saturday 06-18
+        // added and wednesday 06-22 removed
+        let mut valid_day = vec![0_u8; 3];
+        for day in 0..21_usize {
+            let weekday = day % 7;
+            if (weekday < 5 && day != 9) || day == 5 {
+                valid_day[day / 8] |= 1 << (day % 8);
+            }
+        }
+        let period = crate::graph::OperatingPeriod {
+            from: date(6, 13),
+            to: date(7, 3),
+            valid_day,
+            ..crate::graph::OperatingPeriod::default()
+        };
+        let (calendar, dates) = super::calendar_rows(&[&period]);
+        assert_eq!(calendar, vec!["0,1,1,1,1,1,0,0,20220613,20220703"]);
+        assert_eq!(dates, vec!["0,20220618,1", "0,20220622,2"]);
+
+        let regular = crate::fixtures::graph().edges[0].timetable.periods[0].clone();
+        let (calendar, dates) = super::calendar_rows(&[&regular]);
+        assert_eq!(calendar, vec!["0,1,1,1,1,1,0,0,20220613,20220619"]);
+        assert!(dates.is_empty());
+    }
+
+    // contents of the named files of the feed written for graph
+    fn write_feed(graph: &crate::graph::Graph, timezone: &str, names: &[&str]) -> Vec<String> {
+        let path = std::env::temp_dir().join(format!(
+            "netex-parse-gtfs-{}-{}.zip",
+            std::process::id(),
+            COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+        ));
+        graph.to_gtfs_zip(&path, timezone).unwrap();
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        let files = names
+            .iter()
+            .map(|name| {
+                let mut text = String::new();
+                let mut file = archive.by_name(name).unwrap();
+                std::io::Read::read_to_string(&mut file, &mut text).unwrap();
+                text
+            })
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        files
+    }
+
+    // tests run in parallel, so every feed gets its own file
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+    #[test]
+    fn to_gtfs_zip() {
+        let headers = [
+            ("agency.txt", "agency_id,agency_name,agency_url,agency_timezone"),
+            ("stops.txt", "stop_id,stop_name,stop_lat,stop_lon"),
+            ("routes.txt", "route_id,agency_id,route_short_name,route_type"),
+            ("trips.txt", "route_id,service_id,trip_id"),
+            (
+                "stop_times.txt",
+                "trip_id,arrival_time,departure_time,stop_id,stop_sequence",
+            ),
+            (
+                "calendar.txt",
+                "service_id,monday,tuesday,wednesday,thursday,friday,saturday,sunday,start_date,end_date",
+            ),
+            ("calendar_dates.txt", "service_id,date,exception_type"),
+        ];
+        let names: Vec<&str> = headers.iter().map(|(name, _)| *name).collect();
+        let files = write_feed(&line_url_graph(), "Europe/Berlin", &names);
+        for (text, (_, header)) in files.iter().zip(headers) {
+            assert_eq!(text.lines().next(), Some(header));
+        }
+        // without a contact url the agency links the line
+        assert_eq!(
+            files[0].lines().nth(1),
+            Some("0,KVB,https://www.kvb.koeln/s1,Europe/Berlin")
+        );
+        assert_eq!(files[2].lines().nth(1), Some("0,0,S1,2"));
+        assert_eq!(files[4].lines().count(), 4);
+        assert_eq!(
+            files[5].lines().nth(1),
+            Some("0,1,1,1,1,1,0,0,20220613,20220619")
+        );
+    }
+
+    #[test]
+    fn agency_url_and_route_per_mode() {
+        let mut graph = crate::fixtures::graph_with_trips(&["07", "09"]);
+        graph.contacts.push(crate::parser::ContactDetails {
+            url: Some("https://www.kvb.koeln".to_owned()),
+            ..Default::default()
+        });
+        let later = hash_id("sj:09");
+        for journey in graph
+            .edges
+            .iter_mut()
+            .flat_map(|edge| edge.timetable.journeys.iter_mut())
+        {
+            journey.controller_contact = Some(0);
+            if journey.trip == later {
+                journey.transport_mode = "bus".to_owned();
+            }
+        }
+        let files = write_feed(
+            &graph,
+            "Europe/Vienna",
+            &["agency.txt", "routes.txt", "trips.txt"],
+        );
+        let agencies: Vec<&str> = files[0].lines().skip(1).collect();
+        assert_eq!(agencies, vec!["0,KVB,https://www.kvb.koeln,Europe/Vienna"]);
+        // route ids follow trip order, so they are looked up by route type
+        let routes: std::collections::HashMap<&str, &str> = files[1]
+            .lines()
+            .skip(1)
+            .map(|line| {
+                (
+                    &line[line.rfind(',').unwrap() + 1..],
+                    &line[..line.find(',').unwrap()],
+                )
+            })
+            .collect();
+        assert_eq!(routes.len(), 2);
+        assert!(files[1].lines().skip(1).all(|line| line.contains(",0,S1,")));
+        let trips: Vec<&str> = files[2].lines().skip(1).collect();
+        assert!(trips.contains(&format!("{},0,{later}", routes["3"]).as_str()));
+        assert!(trips.contains(&format!("{},0,{}", routes["2"], hash_id("sj:07")).as_str()));
+    }
+}
@@ -0,0 +1,34 @@
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate
+)]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(test)]
+mod fixtures;
+pub mod graph;
+pub mod gtfs;
+#[cfg(feature = "mvt")]
+pub mod mvt;
+pub mod parser;
+pub mod query;
+pub mod report;
+pub mod serialize;
+pub mod timetable;
+pub mod transform;
+pub mod validate;
+
+static QUIET: AtomicBool = AtomicBool::new(true);
+
+/// Controls whether progress bars and diagnostics are written to the terminal.
+/// The crate is quiet unless this is explicitly disabled.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+#[must_use]
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
@@ -1,14 +1,11 @@
 use std::{io::Write, path::PathBuf};
 
 use clap::{Parser, ValueEnum};
-use indicatif::ParallelProgressIterator;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use zip::ZipArchive;
 
-use crate::{graph::WalkEdge, parser::NetexData};
-
-mod graph;
-mod parser;
+use netex_parse::{
+    graph::{self, WalkEdge},
+    parser::{NetexData, ParseOptions},
+};
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 pub enum OutputFormat {
@@ -36,65 +33,56 @@ pub struct Args {
     /// Substring the netex documents file names must included.
     #[clap(short, long, default_value = "")]
     filter: String,
+
+    /// Do not print progress bars or diagnostics.
+    #[clap(short, long)]
+    quiet: bool,
 }
 
 fn main() {
     let args = Args::parse();
-    let zip_stream = std::fs::File::open(args.netex_file).expect("failed to open data");
-    let zip_memmap = unsafe { memmap2::Mmap::map(&zip_stream).expect("failed mmap") };
-    let zip_cursor = std::io::Cursor::new(&zip_memmap);
-    let archive = ZipArchive::new(zip_cursor).expect("failed to read zip");
-    let documents: Vec<String> = archive
-        .file_names()
-        .filter(|f| f.contains(&args.filter))
-        .map(str::to_owned)
-        .collect();
+    netex_parse::set_quiet(args.quiet);
     let walkways: Vec<WalkEdge> = match args.walkways {
         None => vec![],
         Some(path) => {
-            println!("loading walk data");
+            if !netex_parse::is_quiet() {
+                println!("loading walk data");
+            }
             let walk_bytes = std::fs::read(path).expect("failed to read walk data");
             serde_json::from_slice(&walk_bytes).expect("failed to deserialize json")
         }
     };
-    let graph = parse(&zip_memmap, &documents, &walkways);
-    println!(
-        "{} has {} deduped nodes and {} deduped edges.",
-        args.filter,
-        graph.nodes.len(),
-        graph.edges.len(),
-    );
+    // documents that fail to parse are skipped, as one broken file should not end the run
+    let (data, errors) = NetexData::from_zip_skipping_errors(
+        &args.netex_file,
+        &args.filter,
+        &ParseOptions::default(),
+    )
+    .expect("failed to read zip");
+    if !netex_parse::is_quiet() {
+        for err in &errors {
+            eprintln!("{} had err: {}", err.document, err.source);
+        }
+    }
+    let graph = parse(data, &walkways);
+    if !netex_parse::is_quiet() {
+        println!(
+            "{} has {} deduped nodes and {} deduped edges.",
+            args.filter,
+            graph.nodes.len(),
+            graph.edges.len(),
+        );
+    }
     match args.output_format {
         OutputFormat::Csv => dump_csv(&graph).expect("failed to dump csv"),
         OutputFormat::Binary => dump_binary(&graph).expect("failed to dump binary"),
     }
 }
 
-fn parse(archive: &memmap2::Mmap, documents: &[String], walkways: &[WalkEdge]) -> graph::Graph {
-    let mut data = documents
-        .par_iter()
-        .progress_count(documents.len() as u64)
-        .map(|doc| {
-            let zip_cursor = std::io::Cursor::new(archive);
-            let mut archive = ZipArchive::new(zip_cursor).expect("failed to read zip");
-            let file = match archive.by_name(doc) {
-                Ok(f) => f,
-                Err(err) => {
-                    println!("{doc} had err: {err}");
-                    return Vec::new();
-                }
-            };
-            if file.is_dir() {
-                return Vec::new();
-            }
-            let size = file.size().try_into().expect("u64 does not fit usize");
-            vec![parser::NetexData::from_xml(file, size).unwrap_or_default()]
-        })
-        .reduce(Vec::<NetexData>::new, |mut accum, item| {
-            accum.extend(item);
-            accum
-        });
-    println!("deduping...");
+fn parse(mut data: Vec<NetexData>, walkways: &[WalkEdge]) -> graph::Graph {
+    if !netex_parse::is_quiet() {
+        println!("deduping...");
+    }
     for d in &mut data {
         d.scheduled_stop_points.retain(|stop| {
             stop.long > 5.5 && stop.long < 15.5 && stop.lat > 47.0 && stop.lat < 55.5
@@ -188,7 +176,11 @@ fn dump_binary(graph: &graph::Graph) -> Result<(), Box<dyn std::error::Error>> {
         let journeys_len: u16 = journeys.len().try_into()?;
         writer.write_all(&journeys_len.to_le_bytes())?;
         for journey in journeys {
-            writer.write_all(&encode_arrival_departure(journey.arrival, journey.departure))?;
+            let encoded = encode_arrival_departure(
+                journey.arrival.minute_of_day(),
+                journey.departure.minute_of_day(),
+            );
+            writer.write_all(&encoded)?;
             writer.write_all(&(journey.operating_period as u16).to_le_bytes())?;
         }
         let mut periods = Vec::<u8>::new();
@@ -236,8 +228,6 @@ fn dump_binary(graph: &graph::Graph) -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-
-
 fn encode_arrival_departure(arrival: u16, departure: u16) -> [u8; 3] {
     let mut result = [0_u8, 0, 0];
     result[0] = arrival.to_le_bytes()[0];
@@ -249,7 +239,7 @@ fn encode_arrival_departure(arrival: u16, departure: u16) -> [u8; 3] {
 
 #[cfg(test)]
 mod tests {
-    use crate::{encode_arrival_departure};
+    use crate::encode_arrival_departure;
 
     fn decode_arrival_departure(data: [u8; 3]) -> (u16, u16) {
         let arrival = u16::from(data[0]) | u16::from(data[1] & 0xF0) << 4;
@@ -0,0 +1,154 @@
+use ::mvt::{Error, GeomEncoder, GeomType, Tile};
+
+use crate::graph::Graph;
+
+// tile coordinates run from 0 to EXTENT on both axes
+pub const EXTENT: u32 = 4096;
+
+impl Graph {
+    // Encodes the nodes within tile z/x/y as a "stops" layer of points and the
+    // edges crossing it as an "edges" layer of lines clipped to the tile.
+    // Stops carry their name, edges their journey count. Edges collapsing to
+    // a single tile coordinate are left out, as a line needs two distinct points.
+    pub fn to_mvt(&self, z: u32, x: u32, y: u32) -> Result<Vec<u8>, Error> {
+        let project = |node: usize| {
+            let node = &self.nodes[node];
+            tile_position(z, x, y, f64::from(node.long), f64::from(node.lat))
+        };
+        let extent = f64::from(EXTENT);
+        let mut tile = Tile::new(EXTENT);
+        let mut stops = tile.create_layer("stops");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let (px, py) = project(idx);
+            if !(0.0..extent).contains(&px) || !(0.0..extent).contains(&py) {
+                continue;
+            }
+            let geometry = GeomEncoder::new(GeomType::Point).point(px, py)?.encode()?;
+            let mut feature = stops.into_feature(geometry);
+            feature.set_id(idx as u64);
+            feature.add_tag_string("name", &node.short_name);
+            stops = feature.into_layer();
+        }
+        tile.add_layer(stops)?;
+        let mut edges = tile.create_layer("edges");
+        for (idx, edge) in self.edges.iter().enumerate() {
+            let Some((start, end)) = clip(project(edge.start_node), project(edge.end_node), extent)
+            else {
+                continue;
+            };
+            if rounded(start) == rounded(end) {
+                continue;
+            }
+            let geometry = GeomEncoder::new(GeomType::Linestring)
+                .point(start.0, start.1)?
+                .point(end.0, end.1)?
+                .encode()?;
+            let mut feature = edges.into_feature(geometry);
+            feature.set_id(idx as u64);
+            feature.add_tag_uint("journeys", edge.timetable.journeys.len() as u64);
+            edges = feature.into_layer();
+        }
+        tile.add_layer(edges)?;
+        tile.to_bytes()
+    }
+}
+
+// position of a coordinate in web mercator tile space relative to tile z/x/y
+fn tile_position(z: u32, x: u32, y: u32, long: f64, lat: f64) -> (f64, f64) {
+    let scale = f64::from(1_u32 << z.min(31));
+    let lat = lat.clamp(-85.051_128, 85.051_128).to_radians();
+    let world_x = (long + 180.0) / 360.0 * scale;
+    let world_y = (1.0 - lat.tan().asinh() / std::f64::consts::PI) / 2.0 * scale;
+    let extent = f64::from(EXTENT);
+    (
+        (world_x - f64::from(x)) * extent,
+        (world_y - f64::from(y)) * extent,
+    )
+}
+
+// tile coordinate a position is encoded at
+#[allow(clippy::cast_possible_truncation)]
+fn rounded((px, py): (f64, f64)) -> (i64, i64) {
+    (px.round() as i64, py.round() as i64)
+}
+
+// Liang-Barsky clipping of a segment to the square from 0 to extent
+fn clip(start: (f64, f64), end: (f64, f64), extent: f64) -> Option<((f64, f64), (f64, f64))> {
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let (mut enter, mut exit) = (0.0_f64, 1.0_f64);
+    for (p, q) in [
+        (-dx, start.0),
+        (dx, extent - start.0),
+        (-dy, start.1),
+        (dy, extent - start.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 {
+            enter = enter.max(t);
+        } else {
+            exit = exit.min(t);
+        }
+    }
+    if enter > exit {
+        return None;
+    }
+    let at = |t: f64| (start.0 + t * dx, start.1 + t * dy);
+    Some((at(enter), at(exit)))
+}
+
+#[cfg(test)]
+mod tests {
+    use mvt_reader::{feature::Value, Reader};
+
+    #[test]
+    fn to_mvt() {
+        let graph = crate::fixtures::graph();
+        // zoom 9 tile containing all of the fixture's stops in Köln
+        let reader = Reader::new(graph.to_mvt(9, 265, 171).unwrap()).unwrap();
+        assert_eq!(reader.get_layer_names().unwrap(), vec!["stops", "edges"]);
+        let stops = reader.get_features(0).unwrap();
+        assert_eq!(stops.len(), graph.nodes.len());
+        let names: Vec<_> = stops
+            .iter()
+            .filter_map(|stop| stop.properties.as_ref()?.get("name"))
+            .collect();
+        assert!(names.contains(&&Value::String("Köln Hbf".to_owned())));
+        let edges = reader.get_features(1).unwrap();
+        assert_eq!(edges.len(), graph.edges.len());
+        for edge in &edges {
+            let geo::Geometry::LineString(line) = &edge.geometry else {
+                panic!("expected a line string, got {:?}", edge.geometry);
+            };
+            assert_eq!(line.0.len(), 2);
+            assert_ne!(line.0[0], line.0[1]);
+            let journeys = edge.properties.as_ref().unwrap().get("journeys");
+            assert!(matches!(journeys, Some(Value::UInt(count)) if *count > 0));
+        }
+
+        let empty = Reader::new(graph.to_mvt(9, 0, 0).unwrap()).unwrap();
+        assert!(empty.get_features(0).unwrap().is_empty());
+        assert!(empty.get_features(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn to_mvt_skips_zero_length_edges() {
+        let graph = crate::fixtures::graph();
+        // Hbf and Deutz fall onto the same coordinate of the world tile
+        let reader = Reader::new(graph.to_mvt(0, 0, 0).unwrap()).unwrap();
+        assert_eq!(reader.get_features(0).unwrap().len(), graph.nodes.len());
+        let edges = reader.get_features(1).unwrap();
+        assert!(edges.len() < graph.edges.len());
+        for edge in &edges {
+            let geo::Geometry::LineString(line) = &edge.geometry else {
+                panic!("expected a line string, got {:?}", edge.geometry);
+            };
+            assert_ne!(line.0[0], line.0[1]);
+        }
+    }
+}
@@ -1,9 +1,42 @@
-use xxhash_rust::xxh3::xxh3_64;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use indicatif::ParallelProgressIterator;
+use rayon::iter::{Either, IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use xxhash_rust::xxh3::xxh3_64_with_seed;
+use zip::ZipArchive;
+
+use crate::graph::DayMinute;
 
 #[derive(Clone, Default)]
 pub struct Authority {
     pub id: u64,
     pub short_name: String,
+    // parent organisation, if the authority is part of a larger one
+    pub parent: Option<u64>,
+    // customer service contact where given, otherwise the general one
+    pub contact: Option<ContactDetails>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct ContactDetails {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phone: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+impl ContactDetails {
+    // takes the fields self is missing from other
+    fn fill_missing(&mut self, other: ContactDetails) {
+        self.phone = self.phone.take().or(other.phone);
+        self.url = self.url.take().or(other.url);
+        self.email = self.email.take().or(other.email);
+    }
 }
 
 #[derive(Clone, Default)]
@@ -11,21 +44,181 @@ pub struct Line {
     pub id: u64,
     pub short_name: String,
     pub authority: u64,
+    // real-time data is available for the line
+    pub monitored: bool,
+    // mode of journeys not declaring their own
+    pub transport_mode: Option<String>,
+    // group of lines the line is represented by
+    pub group: Option<u64>,
+    // hex rgb from the presentation, e.g. FFD800
+    pub color: Option<String>,
+    pub text_color: Option<String>,
+    // info page of the line from Url or InfoLink, the first one wins
+    pub url: Option<String>,
+}
+
+impl Line {
+    // WCAG contrast between color and text color, from 1 to 21
+    pub fn contrast_ratio(&self) -> Option<f64> {
+        let color = relative_luminance(self.color.as_deref()?)?;
+        let text = relative_luminance(self.text_color.as_deref()?)?;
+        Some((color.max(text) + 0.05) / (color.min(text) + 0.05))
+    }
+}
+
+fn relative_luminance(hex: &str) -> Option<f64> {
+    let hex = hex.trim().trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |idx: usize| -> Option<f64> {
+        let value = f64::from(u8::from_str_radix(hex.get(idx..idx + 2)?, 16).ok()?) / 255.0;
+        Some(if value <= 0.039_28 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        })
+    };
+    Some(0.2126 * channel(0)? + 0.7152 * channel(2)? + 0.0722 * channel(4)?)
+}
+
+// A GroupOfLines or Network, e.g. S-Bahn
+#[derive(Clone, Default)]
+pub struct GroupOfLines {
+    pub id: u64,
+    pub name: Option<String>,
+    pub lines: Vec<u64>,
+    // networks group more broadly than plain groups of lines
+    pub network: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, serde::Serialize)]
+pub struct Notice {
+    #[serde(skip_serializing)]
+    pub id: u64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_code: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct NoticeAssignment {
+    pub notice: u64,
+    // journey, pattern or line the notice applies to
+    pub noticed_object: u64,
+}
+
+#[derive(Clone, Default)]
+pub struct DayType {
+    pub id: u64,
+    // e.g. Mondays to Fridays
+    pub name: Option<String>,
 }
 
 #[derive(Clone, Default)]
 pub struct DayTypeAssignment {
     pub operating_period: u64,
     pub day_type: u64,
+    // unhashed day type ref for reporting
+    pub day_type_ref: String,
     pub is_available: bool,
 }
 
 #[derive(Clone, Default)]
 pub struct UicOperatingPeriod {
     pub id: u64,
+    // unhashed id for tracing graph periods back to the feed
+    pub id_ref: String,
     pub from: u16,
     pub to: u16,
     pub valid_day_bits: Vec<u8>,
+    pub source: Option<u32>,
+}
+
+impl UicOperatingPeriod {
+    // Clears all days from this period that are valid in other
+    pub fn subtract(&mut self, other: &UicOperatingPeriod) {
+        let offset =
+            crate::graph::days_since_epoch(self.from) - crate::graph::days_since_epoch(other.from);
+        for day in 0..self.valid_day_bits.len() * 8 {
+            let Some(other_day) = i64::try_from(day)
+                .ok()
+                .and_then(|day| usize::try_from(day + offset).ok())
+            else {
+                continue;
+            };
+            let other_valid = other
+                .valid_day_bits
+                .get(other_day / 8)
+                .is_some_and(|bits| bits & (1 << (other_day % 8)) != 0);
+            if other_valid {
+                self.valid_day_bits[day / 8] &= !(1 << (day % 8));
+            }
+        }
+    }
+
+    // Marks a single date as valid or not. Valid dates outside of from and to
+    // widen the period, invalid ones are not part of it anyway.
+    pub fn set_day(&mut self, date: u16, valid: bool) {
+        let mut first = crate::graph::days_since_epoch(self.from);
+        let days = crate::graph::days_since_epoch(date);
+        let last = crate::graph::days_since_epoch(self.to);
+        if days < first || days > last {
+            if !valid {
+                return;
+            }
+            if days < first {
+                self.shift_days(first - days);
+                self.from = date;
+                first = days;
+            } else {
+                self.to = date;
+            }
+        }
+        let Ok(day) = usize::try_from(days - first) else {
+            return;
+        };
+        if self.valid_day_bits.len() <= day / 8 {
+            self.valid_day_bits.resize(day / 8 + 1, 0);
+        }
+        if valid {
+            self.valid_day_bits[day / 8] |= 1 << (day % 8);
+        } else {
+            self.valid_day_bits[day / 8] &= !(1 << (day % 8));
+        }
+    }
+
+    // moves every valid day count days later in the bits
+    fn shift_days(&mut self, count: i64) {
+        let Ok(count) = usize::try_from(count) else {
+            return;
+        };
+        let mut bits = vec![0_u8; (self.valid_day_bits.len() * 8 + count).div_ceil(8)];
+        for day in 0..self.valid_day_bits.len() * 8 {
+            if self.valid_day_bits[day / 8] & (1 << (day % 8)) != 0 {
+                let shifted = day + count;
+                bits[shifted / 8] |= 1 << (shifted % 8);
+            }
+        }
+        self.valid_day_bits = bits;
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct OperatingDay {
+    pub id: u64,
+    pub date: u16,
+}
+
+// Overrides a service journey on a single operating day
+#[derive(Clone, Default)]
+pub struct DatedServiceJourney {
+    pub id: u64,
+    pub service_journey: u64,
+    pub operating_day: u64,
+    pub cancelled: bool,
 }
 
 #[derive(Default)]
@@ -34,238 +227,2235 @@ pub struct ScheduledStopPoint {
     pub short_name: String,
     pub long: f32,
     pub lat: f32,
+    pub source: Option<u32>,
+    pub type_of_place: Option<TypeOfPlace>,
+}
+
+#[derive(Clone, Default)]
+pub struct StopPlace {
+    pub id: u64,
+    pub min_transfer_seconds: Option<u32>,
+    // e.g. railStation, busStation or metroStation
+    pub stop_place_type: Option<String>,
+    pub weighting: Option<InterchangeWeighting>,
+    pub accessibility: AccessibilityLimitation,
+    // orientation of the first quay providing one in degrees, 0 is north
+    pub compass_bearing: Option<f32>,
+    pub name: Option<String>,
+    // multimodal hub the place belongs to
+    pub parent: Option<u64>,
+    pub quays: Vec<Quay>,
+    // town of the postal address
+    pub locality: Option<String>,
+    pub topographic_place: Option<u64>,
+}
+
+// namespace of the ids of a data source, e.g. DE for ids like DE::Quay:1
+#[derive(Clone, Default)]
+pub struct Codespace {
+    pub id: u64,
+    pub xmlns: String,
+    pub url: Option<String>,
+}
+
+// town or municipality stop places are located in
+#[derive(Clone, Default)]
+pub struct TopographicPlace {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct Quay {
+    pub id: u64,
+    // platform passengers board at, e.g. Gleis 3
+    pub public_code: Option<String>,
+    // limitations assessed for the quay itself, unknown where only the stop place has them
+    pub accessibility: AccessibilityLimitation,
+}
+
+// ordered from least to most preferred
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InterchangeWeighting {
+    NoInterchange,
+    InterchangeAllowed,
+    RecommendedInterchange,
+    PreferredInterchange,
+}
+
+impl InterchangeWeighting {
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "noInterchange" => Some(Self::NoInterchange),
+            "interchangeAllowed" => Some(Self::InterchangeAllowed),
+            "recommendedInterchange" => Some(Self::RecommendedInterchange),
+            "preferredInterchange" => Some(Self::PreferredInterchange),
+            _ => None,
+        }
+    }
+}
+
+// whether passengers board at the kerb or inside a station
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeOfPlace {
+    OnStreet,
+    Station,
+    Other,
+}
+
+impl TypeOfPlace {
+    // accepts StopPlaceType and StopType values as well as TypeOfPlaceRef ids
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let text = text.rsplit(':').next().unwrap_or(text);
+        match text {
+            _ if text.starts_with("onstreet") => Self::OnStreet,
+            "busStop" | "tramStop" | "taxiStand" => Self::OnStreet,
+            _ if text.ends_with("Station") || text.ends_with("Port") => Self::Station,
+            "airport" | "railPlatform" | "platform" => Self::Station,
+            _ => Self::Other,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LimitationStatus {
+    #[default]
+    Unknown,
+    True,
+    False,
+    Partial,
+}
+
+impl LimitationStatus {
+    fn parse(text: &str) -> Self {
+        match text.trim() {
+            "true" => Self::True,
+            "false" => Self::False,
+            "partial" => Self::Partial,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AccessibilityLimitation {
+    pub wheelchair_access: LimitationStatus,
+    pub step_free_access: LimitationStatus,
+    pub audible_signals_available: LimitationStatus,
+    pub visual_signs_available: LimitationStatus,
+}
+
+impl AccessibilityLimitation {
+    fn parse(node: &roxmltree::Node) -> Self {
+        let mut result = Self::default();
+        for child in node.children() {
+            let status = LimitationStatus::parse(child.text().unwrap_or_default());
+            match child.tag_name().name() {
+                "WheelchairAccess" => result.wheelchair_access = status,
+                "StepFreeAccess" => result.step_free_access = status,
+                "AudibleSignalsAvailable" => result.audible_signals_available = status,
+                "VisualSignsAvailable" => result.visual_signs_available = status,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    // takes the values of other for all fields still unknown
+    pub fn fill_unknown(&mut self, other: &AccessibilityLimitation) {
+        for (own, theirs) in [
+            (&mut self.wheelchair_access, other.wheelchair_access),
+            (&mut self.step_free_access, other.step_free_access),
+            (
+                &mut self.audible_signals_available,
+                other.audible_signals_available,
+            ),
+            (
+                &mut self.visual_signs_available,
+                other.visual_signs_available,
+            ),
+        ] {
+            if *own == LimitationStatus::Unknown {
+                *own = theirs;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct PassengerStopAssignment {
+    pub scheduled_stop_point: u64,
+    pub stop_place: u64,
+    pub quay: Option<u64>,
+}
+
+#[derive(Clone, Default)]
+pub struct RoutePoint {
+    pub id: u64,
+    pub scheduled_stop_point: u64,
+    // points not projecting onto a scheduled stop point only shape the route
+    pub is_via: bool,
+    // (long, lat) of the point itself, used for via points
+    pub location: Option<(f32, f32)>,
+}
+
+#[derive(Clone, Default)]
+pub struct Route {
+    pub id: u64,
+    // route point ids in travel order
+    pub points: Vec<u64>,
+}
+
+// Connects two points of a pattern, the run times of patterns refer to it
+#[derive(Clone, Default)]
+pub struct TimingLink {
+    pub id: u64,
+    // scheduled stop points or timing points at the ends of the link
+    pub from: u64,
+    pub to: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StopOrderDiscrepancy {
+    pub pattern: u64,
+    pub route: u64,
+    // stop that comes before its predecessor on the route
+    pub scheduled_stop_point: u64,
+}
+
+// A reference of the line, route, pattern and calendar chain that points nowhere
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainError {
+    MissingPattern(u64),
+    MissingRoute(u64),
+    MissingLine(u64),
+    // no available assignment for the day type
+    MissingCalendar(u64),
+    MissingPeriod(u64),
+}
+
+impl std::fmt::Display for ChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChainError::MissingPattern(id) => write!(f, "unknown journey pattern {id}"),
+            ChainError::MissingRoute(id) => write!(f, "unknown route {id}"),
+            ChainError::MissingLine(id) => write!(f, "unknown line {id}"),
+            ChainError::MissingCalendar(id) => write!(f, "day type {id} has no operating period"),
+            ChainError::MissingPeriod(id) => write!(f, "unknown operating period {id}"),
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+// A journey with everything it references. Links that could not be followed
+// are None and listed in errors.
+pub struct ResolvedJourney<'a> {
+    pub journey: &'a ServiceJourney,
+    pub pattern: Option<&'a ServiceJourneyPattern>,
+    // None as well for patterns without a route
+    pub route: Option<&'a Route>,
+    pub line: Option<&'a Line>,
+    pub periods: Vec<&'a UicOperatingPeriod>,
+    // days added by dated journeys
+    pub operating_days: Vec<&'a OperatingDay>,
+    pub errors: Vec<ChainError>,
 }
 
 #[derive(Default)]
 pub struct StopPointInJourneyPattern {
     pub id: u64,
     pub scheduled_stop_point: u64,
+    // dwell from the pattern's wait times, used to synthesize passing times
+    pub wait_minutes: u16,
+    // the schedule is held here, as opposed to stops passed through on time
+    pub is_timing_point: bool,
+    // sequence index from the order attribute, e.g. for counting fare hops
+    pub order: Option<u32>,
 }
 
 #[derive(Default)]
 pub struct ServiceJourneyPattern {
     pub stops: Vec<StopPointInJourneyPattern>,
     pub line: u64,
+    pub route: u64,
     pub id: u64,
+    pub name: Option<String>,
+    // mode of the journeys following the pattern, unless they declare their own
+    pub transport_mode: Option<String>,
+    // run times in document order, empty without run times
+    pub run_times: Vec<RunTime>,
+    pub source: Option<u32>,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct RunTime {
+    pub timing_link: Option<u64>,
+    pub minutes: u16,
 }
 
 #[derive(Default)]
 pub struct TimetabledPassingTime {
     pub stop_point_in_journey_pattern: u64,
-    pub arrival: u16,
-    pub departure: u16,
+    // None where the stop has no such time, e.g. no departure at the terminal
+    pub arrival: Option<DayMinute>,
+    pub departure: Option<DayMinute>,
+    // original hh:mm:ss text, only kept with ParseOptions::keep_raw_times
+    pub arrival_raw: Option<String>,
+    pub departure_raw: Option<String>,
+}
+
+impl TimetabledPassingTime {
+    // Whether the stop point of this passing time is a timing point of its
+    // pattern in data, false when the stop point is unknown.
+    pub fn at_timing_point(&self, data: &NetexData) -> bool {
+        data.service_journey_patterns
+            .iter()
+            .flat_map(|pattern| pattern.stops.iter())
+            .find(|stop| stop.id == self.stop_point_in_journey_pattern)
+            .is_some_and(|stop| stop.is_timing_point)
+    }
 }
 
 #[derive(Default)]
 pub struct ServiceJourney {
+    pub id: u64,
     pub passing_times: Vec<TimetabledPassingTime>,
     pub day_type: u64,
     pub transport_mode: String,
     pub pattern_ref: u64,
+    // train number passengers know the journey by, e.g. ICE 123
+    pub public_code: Option<String>,
+    // start time of journeys timed by their pattern instead of passing times
+    pub departure_time: Option<DayMinute>,
+    pub source: Option<u32>,
+}
+
+impl ServiceJourney {
+    // Names of the first and last stop served, in the order of the journey's pattern.
+    // None when the pattern or the stop points are not part of data.
+    pub fn terminals<'a>(&self, data: &'a NetexData) -> Option<(&'a str, &'a str)> {
+        let pattern = data
+            .service_journey_patterns
+            .iter()
+            .find(|pattern| pattern.id == self.pattern_ref)?;
+        let served: HashSet<u64> = self
+            .passing_times
+            .iter()
+            .map(|time| time.stop_point_in_journey_pattern)
+            .collect();
+        let mut stops = pattern
+            .stops
+            .iter()
+            .filter(|stop| served.contains(&stop.id));
+        let first = stops.next()?;
+        let last = stops.next_back().unwrap_or(first);
+        let name = |stop: &StopPointInJourneyPattern| {
+            data.scheduled_stop_points
+                .iter()
+                .find(|point| point.id == stop.scheduled_stop_point)
+                .map(|point| point.short_name.as_str())
+        };
+        Some((name(first)?, name(last)?))
+    }
 }
 
 #[derive(Default)]
 pub struct NetexData {
     pub scheduled_stop_points: Vec<ScheduledStopPoint>,
+    pub stop_places: Vec<StopPlace>,
+    pub passenger_stop_assignments: Vec<PassengerStopAssignment>,
     pub service_journey_patterns: Vec<ServiceJourneyPattern>,
+    pub route_points: Vec<RoutePoint>,
+    pub routes: Vec<Route>,
     pub service_journeys: Vec<ServiceJourney>,
     pub operating_periods: Vec<UicOperatingPeriod>,
     pub day_type_assignments: Vec<DayTypeAssignment>,
     pub lines: Vec<Line>,
     pub authorities: Vec<Authority>,
+    pub notices: Vec<Notice>,
+    pub notice_assignments: Vec<NoticeAssignment>,
+    pub dated_service_journeys: Vec<DatedServiceJourney>,
+    pub operating_days: Vec<OperatingDay>,
+    pub day_types: Vec<DayType>,
+    pub groups_of_lines: Vec<GroupOfLines>,
+    pub topographic_places: Vec<TopographicPlace>,
+    pub codespaces: Vec<Codespace>,
+    pub timing_links: Vec<TimingLink>,
+    // PublicationTimestamp of the delivery, e.g. 2022-06-01T12:00:00
+    pub published: Option<String>,
 }
 
-impl NetexData {
-    pub fn from_xml(
-        mut read: impl std::io::Read,
-        size: usize,
-    ) -> Result<NetexData, Box<dyn std::error::Error>> {
-        let mut data = NetexData::default();
-        let mut buf = Vec::<u8>::with_capacity(size);
-        read.read_to_end(&mut buf)?;
-        let text = unsafe { String::from_utf8_unchecked(buf) };
-        let document = roxmltree::Document::parse(&text)?;
+#[derive(Clone)]
+pub struct ParseOptions {
+    // element names used as stop name, most preferred first
+    pub name_preference: Vec<String>,
+    // seed for hashing netex ids, see hash_id_with_seed
+    pub id_seed: u64,
+    // retain arrival and departure text next to the parsed minutes
+    pub keep_raw_times: bool,
+    // decimal places coordinates are rounded to, keeping dedup stable across feed revisions
+    pub coordinate_precision: Option<u8>,
+    // element categories to extract, others are not scanned for at all
+    pub elements: Elements,
+    // mix the Xmlns of the document's first codespace into id_seed, keeping
+    // equal ids of different sources apart. References into another
+    // document's codespace no longer resolve then.
+    pub codespace_ids: bool,
+}
 
-        let nodes: Result<Vec<ScheduledStopPoint>, Box<dyn std::error::Error>> = document
-            .descendants()
-            .filter(|node| node.tag_name().name() == "ScheduledStopPoint")
-            .map(|node| NetexData::parse_scheduled_stop_point(&node))
-            .collect();
-        data.scheduled_stop_points = nodes?;
+impl Default for ParseOptions {
+    fn default() -> Self {
+        ParseOptions {
+            name_preference: vec!["ShortName".to_owned(), "Name".to_owned()],
+            id_seed: 0,
+            keep_raw_times: false,
+            coordinate_precision: None,
+            elements: Elements::ALL,
+            codespace_ids: false,
+        }
+    }
+}
 
-        let points: Vec<ServiceJourneyPattern> = document
-            .descendants()
-            .filter(|node| node.tag_name().name() == "ServiceJourneyPattern")
-            .map(|node| NetexData::parse_service_journey_pattern(&node))
-            .collect();
-        data.service_journey_patterns = points;
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Elements(u8);
 
-        let journeys: Vec<ServiceJourney> = document
-            .descendants()
-            .filter(|node| node.tag_name().name() == "ServiceJourney")
-            .map(|node| NetexData::parse_service_journey(&node))
-            .collect();
-        data.service_journeys = journeys;
+impl Elements {
+    // scheduled stop points and stop places
+    pub const STOPS: Elements = Elements(1);
+    // service journeys with their patterns, routes, lines, authorities, notices
+    // and dated overrides
+    pub const JOURNEYS: Elements = Elements(1 << 1);
+    // operating periods and days as well as day types
+    pub const PERIODS: Elements = Elements(1 << 2);
+    // passenger stop, day type and notice assignments
+    pub const ASSIGNMENTS: Elements = Elements(1 << 3);
+    pub const ALL: Elements = Elements(0b1111);
 
-        let operating_periods: Vec<UicOperatingPeriod> = document
-            .descendants()
-            .filter(|node| node.tag_name().name() == "UicOperatingPeriod")
-            .map(|node| NetexData::parse_operating_period(&node))
-            .collect();
-        data.operating_periods = operating_periods;
+    pub fn contains(self, other: Elements) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
 
-        let day_type_assignments: Result<Vec<DayTypeAssignment>, Box<dyn std::error::Error>> =
-            document
-                .descendants()
-                .filter(|node| node.tag_name().name() == "DayTypeAssignment")
-                .map(|node| NetexData::parse_day_type_assignment(&node))
-                .collect();
-        data.day_type_assignments = day_type_assignments?;
+impl std::ops::BitOr for Elements {
+    type Output = Elements;
 
-        let lines: Vec<Line> = document
-            .descendants()
-            .filter(|node| node.tag_name().name() == "Line")
-            .map(|node| NetexData::parse_line(&node))
-            .collect();
-        data.lines = lines;
+    fn bitor(self, rhs: Elements) -> Elements {
+        Elements(self.0 | rhs.0)
+    }
+}
 
-        let authorities: Vec<Authority> = document
-            .descendants()
-            .filter(|node| node.tag_name().name() == "Authority")
-            .map(|node| NetexData::parse_authority(&node))
-            .collect();
-        data.authorities = authorities;
-        Ok(data)
+fn round_to(value: f32, precision: u8) -> f32 {
+    let factor = 10_f64.powi(i32::from(precision));
+    #[allow(clippy::cast_possible_truncation)]
+    let rounded = ((f64::from(value) * factor).round() / factor) as f32;
+    rounded
+}
+
+// The northern utm zone of ETRS89 (EPSG:258xx) and WGS84 (EPSG:326xx) grids,
+// accepting plain EPSG:code as well as urn and url notations.
+fn utm_zone(srs_name: &str) -> Option<u8> {
+    let start = srs_name
+        .trim_end_matches(|c: char| c.is_ascii_digit())
+        .len();
+    let code: u32 = srs_name[start..].parse().ok()?;
+    match code {
+        25828..=25838 => u8::try_from(code - 25800).ok(),
+        32601..=32660 => u8::try_from(code - 32600).ok(),
+        _ => None,
     }
+}
 
-    fn parse_scheduled_stop_point(
-        node: &roxmltree::Node,
-    ) -> Result<ScheduledStopPoint, Box<dyn std::error::Error>> {
-        let mut result = ScheduledStopPoint {
-            id: xxh3_64(node.attribute("id").unwrap_or_default().as_bytes()),
-            ..ScheduledStopPoint::default()
-        };
-        for child in node.descendants() {
-            match child.tag_name().name() {
-                "ShortName" | "Name" => {
-                    result.short_name = child.text().unwrap_or_default().replace('"', "");
-                }
-                "Longitude" => {
-                    result.long = child
-                        .text()
-                        .unwrap_or_default()
-                        .parse::<f32>()?
-                        .clamp(-180.0, 180.0);
-                }
-                "Latitude" => {
-                    result.lat = child
-                        .text()
-                        .unwrap_or_default()
-                        .parse::<f32>()?
-                        .clamp(-90.0, 90.0);
-                }
-                _ => {}
-            }
+// Geographic WGS84 as EPSG:4326 in any notation or by name
+fn is_wgs84(srs_name: &str) -> bool {
+    srs_name.trim_end().ends_with("4326") || srs_name.eq_ignore_ascii_case("WGS84")
+}
+
+// Inverse transverse mercator after Snyder, accurate to well below a meter
+// within a zone. ETRS89 and WGS84 are treated as identical.
+fn utm_to_wgs84(easting: f64, northing: f64, zone: u8) -> (f64, f64) {
+    const A: f64 = 6_378_137.0;
+    const F: f64 = 1.0 / 298.257_223_563;
+    const K0: f64 = 0.9996;
+    let e2 = F * (2.0 - F);
+    let ep2 = e2 / (1.0 - e2);
+    let x = easting - 500_000.0;
+    let mu =
+        northing / K0 / (A * (1.0 - e2 / 4.0 - 3.0 * e2.powi(2) / 64.0 - 5.0 * e2.powi(3) / 256.0));
+    let e1 = (1.0 - (1.0 - e2).sqrt()) / (1.0 + (1.0 - e2).sqrt());
+    let phi1 = mu
+        + (3.0 * e1 / 2.0 - 27.0 * e1.powi(3) / 32.0) * (2.0 * mu).sin()
+        + (21.0 * e1.powi(2) / 16.0 - 55.0 * e1.powi(4) / 32.0) * (4.0 * mu).sin()
+        + (151.0 * e1.powi(3) / 96.0) * (6.0 * mu).sin()
+        + (1097.0 * e1.powi(4) / 512.0) * (8.0 * mu).sin();
+    let (sin1, cos1, tan1) = (phi1.sin(), phi1.cos(), phi1.tan());
+    let n1 = A / (1.0 - e2 * sin1.powi(2)).sqrt();
+    let t1 = tan1.powi(2);
+    let c1 = ep2 * cos1.powi(2);
+    let r1 = A * (1.0 - e2) / (1.0 - e2 * sin1.powi(2)).powf(1.5);
+    let d = x / (n1 * K0);
+    let lat = phi1
+        - (n1 * tan1 / r1)
+            * (d.powi(2) / 2.0
+                - (5.0 + 3.0 * t1 + 10.0 * c1 - 4.0 * c1.powi(2) - 9.0 * ep2) * d.powi(4) / 24.0
+                + (61.0 + 90.0 * t1 + 298.0 * c1 + 45.0 * t1.powi(2)
+                    - 252.0 * ep2
+                    - 3.0 * c1.powi(2))
+                    * d.powi(6)
+                    / 720.0);
+    let long = (d - (1.0 + 2.0 * t1 + c1) * d.powi(3) / 6.0
+        + (5.0 - 2.0 * c1 + 28.0 * t1 - 3.0 * c1.powi(2) + 8.0 * ep2 + 24.0 * t1.powi(2))
+            * d.powi(5)
+            / 120.0)
+        / cos1;
+    let central_meridian = f64::from(zone) * 6.0 - 183.0;
+    (lat.to_degrees(), central_meridian + long.to_degrees())
+}
+
+// Hashes a netex id string to the u64 used for references
+pub fn hash_id(id: &str) -> u64 {
+    hash_id_with_seed(id, 0)
+}
+
+// Seed 0 yields the same values as hash_id. Parsing with another seed
+// changes which distinct ids collide, which helps to work around collisions.
+pub fn hash_id_with_seed(id: &str, seed: u64) -> u64 {
+    xxh3_64_with_seed(id.as_bytes(), seed)
+}
+
+// Returns pairs of distinct id attribute values that hash to the same u64
+pub fn find_id_collisions(
+    mut read: impl std::io::Read,
+    size: usize,
+    seed: u64,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut text = String::with_capacity(size);
+    read.read_to_string(&mut text)?;
+    let document = roxmltree::Document::parse(&text)?;
+    let ids = document
+        .descendants()
+        .filter_map(|node| node.attribute("id"));
+    Ok(collisions_by(ids, |id| hash_id_with_seed(id, seed)))
+}
+
+// An element skipped by a lenient parse
+#[derive(Debug)]
+pub struct NetexError {
+    pub element: String,
+    pub id: Option<String>,
+    pub message: String,
+}
+
+impl NetexError {
+    fn new(node: &roxmltree::Node, err: &dyn std::error::Error) -> Self {
+        NetexError {
+            element: node.tag_name().name().to_owned(),
+            id: node.attribute("id").map(str::to_owned),
+            message: err.to_string(),
         }
-        Ok(result)
     }
+}
 
-    fn parse_service_journey_pattern(node: &roxmltree::Node) -> ServiceJourneyPattern {
-        let mut result = ServiceJourneyPattern {
-            id: xxh3_64(node.attribute("id").unwrap_or_default().as_bytes()),
-            ..ServiceJourneyPattern::default()
-        };
-        for sub_node in node.descendants() {
-            if sub_node.tag_name().name() == "LineRef" {
-                result.line = xxh3_64(sub_node.attribute("ref").unwrap_or_default().as_bytes());
-            }
-            if sub_node.tag_name().name() != "StopPointInJourneyPattern" {
-                continue;
-            }
-            let mut stop = StopPointInJourneyPattern {
-                id: xxh3_64(sub_node.attribute("id").unwrap_or_default().as_bytes()),
-                ..StopPointInJourneyPattern::default()
-            };
-            stop.scheduled_stop_point = xxh3_64(
-                sub_node
-                    .descendants()
-                    .find(|child| child.tag_name().name() == "ScheduledStopPointRef")
-                    .map(|node| node.attribute("ref").unwrap_or_default().as_bytes())
-                    .unwrap_or_default(),
-            );
-            result.stops.push(stop);
+impl std::fmt::Display for NetexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.id {
+            Some(id) => write!(f, "{} {}: {}", self.element, id, self.message),
+            None => write!(f, "{}: {}", self.element, self.message),
         }
-        result
     }
+}
 
-    fn parse_service_journey(node: &roxmltree::Node) -> ServiceJourney {
-        let day_type = node
-            .descendants()
-            .find(|node| node.tag_name().name() == "DayTypeRef")
-            .unwrap()
-            .attribute("ref")
-            .unwrap_or_default();
-        let transport_mode = node
-            .descendants()
-            .find(|node| node.tag_name().name() == "TransportMode")
-            .unwrap()
-            .text()
-            .unwrap_or_default();
-        let pattern_ref = node
-            .descendants()
-            .find(|node| node.tag_name().name() == "ServiceJourneyPatternRef")
-            .unwrap()
-            .attribute("ref")
-            .unwrap_or_default();
-        let mut result = ServiceJourney {
-            day_type: xxh3_64(day_type.as_bytes()),
-            transport_mode: transport_mode.to_owned(),
-            pattern_ref: xxh3_64(pattern_ref.as_bytes()),
-            ..ServiceJourney::default()
-        };
-        let passing_times_node = node
-            .descendants()
-            .find(|node| node.tag_name().name() == "passingTimes")
-            .expect("ServiceJouney tag has no passing times");
-        for timetabled in passing_times_node
-            .descendants()
-            .filter(|node| node.tag_name().name() == "TimetabledPassingTime")
-        {
-            let mut timetabled_passing_time = TimetabledPassingTime::default();
-            for child in timetabled.descendants() {
+impl std::error::Error for NetexError {}
+
+// A document of a zip or a path list that failed to parse
+#[derive(Debug)]
+pub struct DocumentError {
+    pub document: String,
+    pub source: Box<dyn std::error::Error + Send + Sync>,
+}
+
+impl DocumentError {
+    fn new(document: &str, source: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        DocumentError {
+            document: document.to_owned(),
+            source,
+        }
+    }
+}
+
+impl std::fmt::Display for DocumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.document, self.source)
+    }
+}
+
+impl std::error::Error for DocumentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&*self.source)
+    }
+}
+
+// a document of a zip, None for directories
+type ParsedDocument = Result<Option<NetexData>, DocumentError>;
+
+// Passes on a parsed element. Errors fail the parse unless errors collects them.
+fn check_element<T>(
+    node: &roxmltree::Node,
+    parsed: Result<T, Box<dyn std::error::Error + Send + Sync>>,
+    errors: Option<&mut Vec<NetexError>>,
+) -> Result<Option<T>, Box<dyn std::error::Error + Send + Sync>> {
+    match (parsed, errors) {
+        (Ok(element), _) => Ok(Some(element)),
+        (Err(err), Some(errors)) => {
+            errors.push(NetexError::new(node, err.as_ref()));
+            Ok(None)
+        }
+        (Err(err), None) => Err(err),
+    }
+}
+
+// appends the items whose key is not yet present in target
+fn extend_unique<T>(target: &mut Vec<T>, items: Vec<T>, key: impl Fn(&T) -> u64) {
+    let mut seen: HashSet<u64> = target.iter().map(&key).collect();
+    target.extend(items.into_iter().filter(|item| seen.insert(key(item))));
+}
+
+// Like extend_unique, but items may share a key as long as no earlier
+// extension claimed it.
+fn extend_unclaimed<T>(target: &mut Vec<T>, items: Vec<T>, key: impl Fn(&T) -> u64) {
+    let claimed: HashSet<u64> = target.iter().map(&key).collect();
+    target.extend(
+        items
+            .into_iter()
+            .filter(|item| !claimed.contains(&key(item))),
+    );
+}
+
+// Receives every element recognized while parsing a document, see NetexData::visit.
+// All methods default to ignoring the element.
+pub trait NetexVisitor {
+    fn on_stop(&mut self, _stop: ScheduledStopPoint) {}
+    fn on_stop_place(&mut self, _place: StopPlace) {}
+    fn on_passenger_stop_assignment(&mut self, _assignment: PassengerStopAssignment) {}
+    fn on_day_type_assignment(&mut self, _assignment: DayTypeAssignment) {}
+    fn on_journey_pattern(&mut self, _pattern: ServiceJourneyPattern) {}
+    fn on_route_point(&mut self, _point: RoutePoint) {}
+    fn on_route(&mut self, _route: Route) {}
+    fn on_journey(&mut self, _journey: ServiceJourney) {}
+    fn on_line(&mut self, _line: Line) {}
+    fn on_authority(&mut self, _authority: Authority) {}
+    fn on_period(&mut self, _period: UicOperatingPeriod) {}
+    fn on_dated_journey(&mut self, _journey: DatedServiceJourney) {}
+    fn on_operating_day(&mut self, _day: OperatingDay) {}
+    fn on_notice(&mut self, _notice: Notice) {}
+    fn on_notice_assignment(&mut self, _assignment: NoticeAssignment) {}
+    fn on_day_type(&mut self, _day_type: DayType) {}
+    fn on_group_of_lines(&mut self, _group: GroupOfLines) {}
+    fn on_topographic_place(&mut self, _place: TopographicPlace) {}
+    fn on_codespace(&mut self, _codespace: Codespace) {}
+    fn on_timing_link(&mut self, _link: TimingLink) {}
+    fn on_published(&mut self, _timestamp: String) {}
+}
+
+// the default parse collects all elements
+impl NetexVisitor for NetexData {
+    fn on_stop(&mut self, stop: ScheduledStopPoint) {
+        self.scheduled_stop_points.push(stop);
+    }
+
+    fn on_stop_place(&mut self, place: StopPlace) {
+        self.stop_places.push(place);
+    }
+
+    fn on_passenger_stop_assignment(&mut self, assignment: PassengerStopAssignment) {
+        self.passenger_stop_assignments.push(assignment);
+    }
+
+    fn on_day_type_assignment(&mut self, assignment: DayTypeAssignment) {
+        self.day_type_assignments.push(assignment);
+    }
+
+    fn on_journey_pattern(&mut self, pattern: ServiceJourneyPattern) {
+        self.service_journey_patterns.push(pattern);
+    }
+
+    fn on_route_point(&mut self, point: RoutePoint) {
+        self.route_points.push(point);
+    }
+
+    fn on_route(&mut self, route: Route) {
+        self.routes.push(route);
+    }
+
+    fn on_journey(&mut self, journey: ServiceJourney) {
+        self.service_journeys.push(journey);
+    }
+
+    fn on_line(&mut self, line: Line) {
+        self.lines.push(line);
+    }
+
+    fn on_authority(&mut self, authority: Authority) {
+        self.authorities.push(authority);
+    }
+
+    fn on_period(&mut self, period: UicOperatingPeriod) {
+        self.operating_periods.push(period);
+    }
+
+    fn on_dated_journey(&mut self, journey: DatedServiceJourney) {
+        self.dated_service_journeys.push(journey);
+    }
+
+    fn on_operating_day(&mut self, day: OperatingDay) {
+        self.operating_days.push(day);
+    }
+
+    fn on_notice(&mut self, notice: Notice) {
+        self.notices.push(notice);
+    }
+
+    fn on_notice_assignment(&mut self, assignment: NoticeAssignment) {
+        self.notice_assignments.push(assignment);
+    }
+
+    fn on_codespace(&mut self, codespace: Codespace) {
+        self.codespaces.push(codespace);
+    }
+
+    fn on_timing_link(&mut self, link: TimingLink) {
+        self.timing_links.push(link);
+    }
+
+    fn on_published(&mut self, timestamp: String) {
+        self.published = Some(timestamp);
+    }
+
+    fn on_day_type(&mut self, day_type: DayType) {
+        self.day_types.push(day_type);
+    }
+
+    fn on_group_of_lines(&mut self, group: GroupOfLines) {
+        self.groups_of_lines.push(group);
+    }
+
+    fn on_topographic_place(&mut self, place: TopographicPlace) {
+        self.topographic_places.push(place);
+    }
+}
+
+// The run and wait times of patterns with the timing links they refer to
+#[derive(Default)]
+struct Schedules {
+    // stop point, scheduled stop point and wait minutes of each stop
+    stops: HashMap<u64, Vec<(u64, u64, u16)>>,
+    run_times: HashMap<u64, Vec<RunTime>>,
+    // end points by timing link
+    links: HashMap<u64, (u64, u64)>,
+}
+
+impl Schedules {
+    fn add_pattern(&mut self, pattern: &ServiceJourneyPattern) {
+        let stops = pattern
+            .stops
+            .iter()
+            .map(|stop| (stop.id, stop.scheduled_stop_point, stop.wait_minutes))
+            .collect();
+        self.stops.insert(pattern.id, stops);
+        self.run_times.insert(pattern.id, pattern.run_times.clone());
+    }
+
+    // Run minutes from each stop to the next. Run times referring to timing
+    // links apply to the link between its points, others go by document order.
+    fn run_minutes(&self, pattern: u64) -> Option<Vec<u16>> {
+        let stops = self.stops.get(&pattern)?;
+        let run_times = self.run_times.get(&pattern)?;
+        let by_points: HashMap<(u64, u64), u16> = run_times
+            .iter()
+            .filter_map(|run| Some((*self.links.get(&run.timing_link?)?, run.minutes)))
+            .collect();
+        let linked: Option<Vec<u16>> = stops
+            .windows(2)
+            .map(|pair| by_points.get(&(pair[0].1, pair[1].1)).copied())
+            .collect();
+        linked.or_else(|| {
+            (run_times.len() + 1 == stops.len())
+                .then(|| run_times.iter().map(|run| run.minutes).collect())
+        })
+    }
+
+    // fills in passing times of a journey without any, unless they would
+    // exceed the minutes of a DayMinute
+    fn fill(&self, journey: &mut ServiceJourney) {
+        let (true, Some(mut time)) = (journey.passing_times.is_empty(), journey.departure_time)
+        else {
+            return;
+        };
+        let (Some(stops), Some(run_minutes)) = (
+            self.stops.get(&journey.pattern_ref),
+            self.run_minutes(journey.pattern_ref),
+        ) else {
+            return;
+        };
+        let mut times = Vec::<TimetabledPassingTime>::with_capacity(stops.len());
+        for (idx, (stop, _, wait)) in stops.iter().enumerate() {
+            let arrival = time;
+            if idx > 0 {
+                let Some(departure) = time.checked_add(*wait) else {
+                    return;
+                };
+                time = departure;
+            }
+            times.push(TimetabledPassingTime {
+                stop_point_in_journey_pattern: *stop,
+                arrival: Some(arrival),
+                departure: Some(time),
+                ..TimetabledPassingTime::default()
+            });
+            let Some(next) = time.checked_add(run_minutes.get(idx).copied().unwrap_or_default())
+            else {
+                return;
+            };
+            time = next;
+        }
+        journey.passing_times = times;
+    }
+}
+
+// Hands elements on to visitor, holding back journeys without passing times
+// until the patterns and timing links of the whole document have been seen.
+struct Synthesizer<'a, V> {
+    visitor: &'a mut V,
+    schedules: Schedules,
+    pending: Vec<ServiceJourney>,
+}
+
+impl<'a, V: NetexVisitor> Synthesizer<'a, V> {
+    fn new(visitor: &'a mut V) -> Self {
+        Synthesizer {
+            visitor,
+            schedules: Schedules::default(),
+            pending: Vec::new(),
+        }
+    }
+
+    fn finish(self) {
+        for mut journey in self.pending {
+            self.schedules.fill(&mut journey);
+            self.visitor.on_journey(journey);
+        }
+    }
+}
+
+impl<V: NetexVisitor> NetexVisitor for Synthesizer<'_, V> {
+    fn on_stop(&mut self, stop: ScheduledStopPoint) {
+        self.visitor.on_stop(stop);
+    }
+
+    fn on_stop_place(&mut self, place: StopPlace) {
+        self.visitor.on_stop_place(place);
+    }
+
+    fn on_passenger_stop_assignment(&mut self, assignment: PassengerStopAssignment) {
+        self.visitor.on_passenger_stop_assignment(assignment);
+    }
+
+    fn on_day_type_assignment(&mut self, assignment: DayTypeAssignment) {
+        self.visitor.on_day_type_assignment(assignment);
+    }
+
+    fn on_journey_pattern(&mut self, pattern: ServiceJourneyPattern) {
+        self.schedules.add_pattern(&pattern);
+        self.visitor.on_journey_pattern(pattern);
+    }
+
+    fn on_route_point(&mut self, point: RoutePoint) {
+        self.visitor.on_route_point(point);
+    }
+
+    fn on_route(&mut self, route: Route) {
+        self.visitor.on_route(route);
+    }
+
+    fn on_journey(&mut self, journey: ServiceJourney) {
+        if journey.passing_times.is_empty() && journey.departure_time.is_some() {
+            self.pending.push(journey);
+        } else {
+            self.visitor.on_journey(journey);
+        }
+    }
+
+    fn on_line(&mut self, line: Line) {
+        self.visitor.on_line(line);
+    }
+
+    fn on_authority(&mut self, authority: Authority) {
+        self.visitor.on_authority(authority);
+    }
+
+    fn on_period(&mut self, period: UicOperatingPeriod) {
+        self.visitor.on_period(period);
+    }
+
+    fn on_dated_journey(&mut self, journey: DatedServiceJourney) {
+        self.visitor.on_dated_journey(journey);
+    }
+
+    fn on_operating_day(&mut self, day: OperatingDay) {
+        self.visitor.on_operating_day(day);
+    }
+
+    fn on_notice(&mut self, notice: Notice) {
+        self.visitor.on_notice(notice);
+    }
+
+    fn on_notice_assignment(&mut self, assignment: NoticeAssignment) {
+        self.visitor.on_notice_assignment(assignment);
+    }
+
+    fn on_day_type(&mut self, day_type: DayType) {
+        self.visitor.on_day_type(day_type);
+    }
+
+    fn on_group_of_lines(&mut self, group: GroupOfLines) {
+        self.visitor.on_group_of_lines(group);
+    }
+
+    fn on_topographic_place(&mut self, place: TopographicPlace) {
+        self.visitor.on_topographic_place(place);
+    }
+
+    fn on_codespace(&mut self, codespace: Codespace) {
+        self.visitor.on_codespace(codespace);
+    }
+
+    fn on_timing_link(&mut self, link: TimingLink) {
+        self.schedules.links.insert(link.id, (link.from, link.to));
+        self.visitor.on_timing_link(link);
+    }
+
+    fn on_published(&mut self, timestamp: String) {
+        self.visitor.on_published(timestamp);
+    }
+}
+
+fn collisions_by<'a>(
+    ids: impl Iterator<Item = &'a str>,
+    hash: impl Fn(&str) -> u64,
+) -> Vec<(String, String)> {
+    let mut seen = HashMap::<u64, &str>::new();
+    let mut result = Vec::<(String, String)>::new();
+    for id in ids {
+        let first = *seen.entry(hash(id)).or_insert(id);
+        if first != id && !result.iter().any(|(a, b)| a == first && b == id) {
+            result.push((first.to_owned(), id.to_owned()));
+        }
+    }
+    result
+}
+
+// Parses every element with one of tags in document order.
+fn parse_elements<T>(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+    tags: &[&str],
+    parse: impl Fn(&roxmltree::Node, &ParseOptions) -> T,
+) -> Vec<T> {
+    let seeded = codespace_options(document, options);
+    let options = seeded.as_ref().unwrap_or(options);
+    document
+        .descendants()
+        .filter(|node| tags.contains(&node.tag_name().name()))
+        .map(|node| parse(&node, options))
+        .collect()
+}
+
+// The free parse functions extract a single element kind from an already
+// parsed document, ignoring the elements selected by options.
+pub fn parse_stops(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Result<Vec<ScheduledStopPoint>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_elements(
+        document,
+        options,
+        &["ScheduledStopPoint"],
+        NetexData::parse_scheduled_stop_point,
+    )
+    .into_iter()
+    .collect()
+}
+
+pub fn parse_stop_places(document: &roxmltree::Document, options: &ParseOptions) -> Vec<StopPlace> {
+    parse_elements(document, options, &["StopPlace"], |node, options| {
+        NetexData::parse_stop_place(node, options.id_seed)
+    })
+}
+
+pub fn parse_topographic_places(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<TopographicPlace> {
+    parse_elements(document, options, &["TopographicPlace"], |node, options| {
+        NetexData::parse_topographic_place(node, options.id_seed)
+    })
+}
+
+// options with the seed derived from the document's codespace, if it asks for one
+fn codespace_options(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Option<ParseOptions> {
+    let node = document
+        .descendants()
+        .find(|node| options.codespace_ids && node.tag_name().name() == "Codespace")?;
+    Some(ParseOptions {
+        id_seed: hash_id_with_seed(&NetexData::parse_codespace(&node, 0).xmlns, options.id_seed),
+        ..options.clone()
+    })
+}
+
+pub fn parse_codespaces(document: &roxmltree::Document, options: &ParseOptions) -> Vec<Codespace> {
+    parse_elements(document, options, &["Codespace"], |node, options| {
+        NetexData::parse_codespace(node, options.id_seed)
+    })
+}
+
+pub fn parse_passenger_stop_assignments(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<PassengerStopAssignment> {
+    parse_elements(
+        document,
+        options,
+        &["PassengerStopAssignment"],
+        |node, options| NetexData::parse_passenger_stop_assignment(node, options.id_seed),
+    )
+}
+
+pub fn parse_day_type_assignments(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Result<Vec<DayTypeAssignment>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_elements(
+        document,
+        options,
+        &["DayTypeAssignment"],
+        |node, options| NetexData::parse_day_type_assignment(node, options.id_seed),
+    )
+    .into_iter()
+    .collect()
+}
+
+pub fn parse_journey_patterns(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<ServiceJourneyPattern> {
+    parse_elements(
+        document,
+        options,
+        &["ServiceJourneyPattern"],
+        |node, options| NetexData::parse_service_journey_pattern(node, options.id_seed),
+    )
+}
+
+pub fn parse_route_points(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<RoutePoint> {
+    parse_elements(document, options, &["RoutePoint"], |node, options| {
+        NetexData::parse_route_point(node, options.id_seed)
+    })
+}
+
+pub fn parse_timing_links(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<TimingLink> {
+    parse_elements(document, options, &["TimingLink"], |node, options| {
+        NetexData::parse_timing_link(node, options.id_seed)
+    })
+}
+
+pub fn parse_routes(document: &roxmltree::Document, options: &ParseOptions) -> Vec<Route> {
+    parse_elements(document, options, &["Route"], |node, options| {
+        NetexData::parse_route(node, options.id_seed)
+    })
+}
+
+pub fn parse_journeys(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Result<Vec<ServiceJourney>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_elements(
+        document,
+        options,
+        &["ServiceJourney"],
+        NetexData::parse_service_journey,
+    )
+    .into_iter()
+    .collect()
+}
+
+pub fn parse_lines(document: &roxmltree::Document, options: &ParseOptions) -> Vec<Line> {
+    parse_elements(document, options, &["Line"], |node, options| {
+        NetexData::parse_line(node, options.id_seed)
+    })
+}
+
+pub fn parse_authorities(document: &roxmltree::Document, options: &ParseOptions) -> Vec<Authority> {
+    parse_elements(document, options, &["Authority"], |node, options| {
+        NetexData::parse_authority(node, options.id_seed)
+    })
+}
+
+pub fn parse_periods(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Result<Vec<UicOperatingPeriod>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_elements(
+        document,
+        options,
+        &["UicOperatingPeriod", "OperatingPeriod"],
+        |node, options| NetexData::parse_operating_period(node, options.id_seed),
+    )
+    .into_iter()
+    .collect()
+}
+
+pub fn parse_dated_journeys(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<DatedServiceJourney> {
+    parse_elements(
+        document,
+        options,
+        &["DatedServiceJourney"],
+        |node, options| NetexData::parse_dated_service_journey(node, options.id_seed),
+    )
+}
+
+pub fn parse_day_types(document: &roxmltree::Document, options: &ParseOptions) -> Vec<DayType> {
+    parse_elements(document, options, &["DayType"], |node, options| {
+        NetexData::parse_day_type(node, options.id_seed)
+    })
+}
+
+pub fn parse_operating_days(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Result<Vec<OperatingDay>, Box<dyn std::error::Error + Send + Sync>> {
+    parse_elements(document, options, &["OperatingDay"], |node, options| {
+        NetexData::parse_operating_day(node, options.id_seed)
+    })
+    .into_iter()
+    .collect()
+}
+
+pub fn parse_groups_of_lines(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<GroupOfLines> {
+    parse_elements(
+        document,
+        options,
+        &["GroupOfLines", "Network"],
+        |node, options| NetexData::parse_group_of_lines(node, options.id_seed),
+    )
+}
+
+pub fn parse_notices(document: &roxmltree::Document, options: &ParseOptions) -> Vec<Notice> {
+    parse_elements(document, options, &["Notice"], |node, options| {
+        NetexData::parse_notice(node, options.id_seed)
+    })
+}
+
+pub fn parse_notice_assignments(
+    document: &roxmltree::Document,
+    options: &ParseOptions,
+) -> Vec<NoticeAssignment> {
+    parse_elements(document, options, &["NoticeAssignment"], |node, options| {
+        NetexData::parse_notice_assignment(node, options.id_seed)
+    })
+}
+
+impl NetexData {
+    pub fn from_xml(
+        read: impl std::io::Read,
+        size: usize,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_xml_with_options(read, size, &ParseOptions::default())
+    }
+
+    // Parses all documents in a zip file whose names contain filter.
+    // Elements are tagged with the index of their document among the matches.
+    pub fn from_zip(
+        path: impl AsRef<Path>,
+        filter: &str,
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_zip_with_options(path, filter, &ParseOptions::default())
+    }
+
+    pub fn from_zip_with_options(
+        path: impl AsRef<Path>,
+        filter: &str,
+        options: &ParseOptions,
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        let zip_stream = std::fs::File::open(path)?;
+        let zip_memmap = unsafe { memmap2::Mmap::map(&zip_stream)? };
+        Self::from_zip_bytes(&zip_memmap, filter, options)
+    }
+
+    // Like from_zip for archives not backed by a file. The archive is read
+    // into memory as a whole, so documents can be parsed in parallel.
+    pub fn from_zip_reader(
+        read: impl std::io::Read + std::io::Seek,
+        filter: &str,
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_zip_reader_with_options(read, filter, &ParseOptions::default())
+    }
+
+    pub fn from_zip_reader_with_options(
+        mut read: impl std::io::Read + std::io::Seek,
+        filter: &str,
+        options: &ParseOptions,
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut buf = Vec::<u8>::new();
+        read.rewind()?;
+        read.read_to_end(&mut buf)?;
+        Self::from_zip_bytes(&buf, filter, options)
+    }
+
+    // fails with the first document that does not parse
+    fn from_zip_bytes(
+        bytes: &[u8],
+        filter: &str,
+        options: &ParseOptions,
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        let data: Vec<Option<NetexData>> = Self::parse_zip_documents(bytes, filter, options)?
+            .into_iter()
+            .collect::<Result<_, _>>()?;
+        let mut data: Vec<NetexData> = data.into_iter().flatten().collect();
+        Self::synthesize_passing_times(&mut data);
+        Ok(data)
+    }
+
+    // Like from_zip, but documents that do not parse are skipped and returned
+    // next to the parsed ones. Only an unreadable archive fails.
+    pub fn from_zip_skipping_errors(
+        path: impl AsRef<Path>,
+        filter: &str,
+        options: &ParseOptions,
+    ) -> Result<(Vec<NetexData>, Vec<DocumentError>), Box<dyn std::error::Error + Send + Sync>>
+    {
+        let zip_stream = std::fs::File::open(path)?;
+        let zip_memmap = unsafe { memmap2::Mmap::map(&zip_stream)? };
+        let mut data = Vec::<NetexData>::new();
+        let mut errors = Vec::<DocumentError>::new();
+        for document in Self::parse_zip_documents(&zip_memmap, filter, options)? {
+            match document {
+                Ok(parsed) => data.extend(parsed),
+                Err(err) => errors.push(err),
+            }
+        }
+        Self::synthesize_passing_times(&mut data);
+        Ok((data, errors))
+    }
+
+    // every document matching filter in archive order
+    fn parse_zip_documents(
+        bytes: &[u8],
+        filter: &str,
+        options: &ParseOptions,
+    ) -> Result<Vec<ParsedDocument>, Box<dyn std::error::Error + Send + Sync>> {
+        let archive = ZipArchive::new(std::io::Cursor::new(bytes))?;
+        let documents: Vec<&str> = archive
+            .file_names()
+            .filter(|f| f.contains(filter))
+            .collect();
+        let doc_iter = if crate::is_quiet() {
+            Either::Left(documents.par_iter())
+        } else {
+            Either::Right(documents.par_iter().progress_count(documents.len() as u64))
+        };
+        Ok(doc_iter
+            .enumerate()
+            .map(|(idx, doc)| {
+                let parse = || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                    let mut archive = ZipArchive::new(std::io::Cursor::new(bytes))?;
+                    let file = archive.by_name(doc)?;
+                    if file.is_dir() {
+                        return Ok(None);
+                    }
+                    let size = file.size().try_into()?;
+                    let mut data = NetexData::from_xml_with_options(file, size, options)?;
+                    data.tag_source(idx.try_into()?);
+                    Ok(Some(data))
+                };
+                parse().map_err(|err| DocumentError::new(doc, err))
+            })
+            .collect())
+    }
+
+    // Parses the given files, tagging elements with the index of their path.
+    pub fn from_paths(
+        paths: &[impl AsRef<Path> + Sync],
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_paths_with_options(paths, &ParseOptions::default())
+    }
+
+    pub fn from_paths_with_options(
+        paths: &[impl AsRef<Path> + Sync],
+        options: &ParseOptions,
+    ) -> Result<Vec<NetexData>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = paths
+            .par_iter()
+            .enumerate()
+            .map(|(idx, path)| {
+                let parse = || -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+                    let file = std::fs::File::open(path)?;
+                    let size = file.metadata()?.len().try_into()?;
+                    let mut data = NetexData::from_xml_with_options(file, size, options)?;
+                    data.tag_source(idx.try_into()?);
+                    Ok(data)
+                };
+                parse().map_err(|err| DocumentError::new(&path.as_ref().to_string_lossy(), err))
+            })
+            .collect::<Result<Vec<NetexData>, _>>()?;
+        Self::synthesize_passing_times(&mut data);
+        Ok(data)
+    }
+
+    // Parses the given files into a single feed, see merge.
+    pub fn from_paths_merged(
+        paths: &[impl AsRef<Path> + Sync],
+        options: &ParseOptions,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self::merge(Self::from_paths_with_options(paths, options)?))
+    }
+
+    // Xmlns of the first codespace declared, the namespace of the feed's ids
+    pub fn codespace(&self) -> Option<&str> {
+        self.codespaces
+            .first()
+            .map(|codespace| codespace.xmlns.as_str())
+    }
+
+    // Combines several feeds into one. Where ids collide the element of the most
+    // recently published feed wins, feeds without a readable timestamp count as oldest.
+    pub fn merge(mut sources: Vec<NetexData>) -> NetexData {
+        sources.sort_by_key(|data| {
+            std::cmp::Reverse(data.published.as_deref().and_then(Self::parse_timestamp))
+        });
+        let mut result = NetexData {
+            published: sources.first().and_then(|d| d.published.clone()),
+            ..NetexData::default()
+        };
+        for data in sources {
+            extend_unique(
+                &mut result.scheduled_stop_points,
+                data.scheduled_stop_points,
+                |e| e.id,
+            );
+            extend_unique(&mut result.stop_places, data.stop_places, |e| e.id);
+            extend_unique(
+                &mut result.passenger_stop_assignments,
+                data.passenger_stop_assignments,
+                |e| e.scheduled_stop_point,
+            );
+            extend_unique(
+                &mut result.service_journey_patterns,
+                data.service_journey_patterns,
+                |e| e.id,
+            );
+            extend_unique(&mut result.route_points, data.route_points, |e| e.id);
+            extend_unique(&mut result.routes, data.routes, |e| e.id);
+            extend_unique(&mut result.service_journeys, data.service_journeys, |e| {
+                e.id
+            });
+            extend_unique(&mut result.operating_periods, data.operating_periods, |e| {
+                e.id
+            });
+            extend_unique(&mut result.lines, data.lines, |e| e.id);
+            extend_unique(&mut result.authorities, data.authorities, |e| e.id);
+            extend_unique(&mut result.notices, data.notices, |e| e.id);
+            extend_unique(
+                &mut result.dated_service_journeys,
+                data.dated_service_journeys,
+                |e| e.id,
+            );
+            extend_unique(&mut result.operating_days, data.operating_days, |e| e.id);
+            extend_unique(&mut result.day_types, data.day_types, |e| e.id);
+            extend_unique(&mut result.groups_of_lines, data.groups_of_lines, |e| e.id);
+            extend_unique(
+                &mut result.topographic_places,
+                data.topographic_places,
+                |e| e.id,
+            );
+            extend_unique(&mut result.codespaces, data.codespaces, |e| e.id);
+            extend_unique(&mut result.timing_links, data.timing_links, |e| e.id);
+            // an older feed may not add to what a newer one already assigned
+            extend_unclaimed(
+                &mut result.day_type_assignments,
+                data.day_type_assignments,
+                |e| e.day_type,
+            );
+            extend_unclaimed(
+                &mut result.notice_assignments,
+                data.notice_assignments,
+                |e| e.noticed_object,
+            );
+        }
+        result
+    }
+
+    fn tag_source(&mut self, source: u32) {
+        for stop in &mut self.scheduled_stop_points {
+            stop.source = Some(source);
+        }
+        for pattern in &mut self.service_journey_patterns {
+            pattern.source = Some(source);
+        }
+        for journey in &mut self.service_journeys {
+            journey.source = Some(source);
+        }
+        for period in &mut self.operating_periods {
+            period.source = Some(source);
+        }
+    }
+
+    // Reads the document without blocking the executor, parsing itself is synchronous
+    #[cfg(feature = "tokio")]
+    pub async fn from_async_reader(
+        mut read: impl tokio::io::AsyncRead + Unpin,
+        size: usize,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        use tokio::io::AsyncReadExt;
+        let mut buf = Vec::<u8>::with_capacity(size);
+        read.read_to_end(&mut buf).await?;
+        Self::from_xml(buf.as_slice(), size)
+    }
+
+    // Skips elements that fail to parse instead of rejecting the whole document.
+    // An unreadable document yields no data and a single error.
+    pub fn from_xml_lenient(read: impl std::io::Read, size: usize) -> (NetexData, Vec<NetexError>) {
+        let mut errors = Vec::new();
+        match Self::parse_xml(read, size, &ParseOptions::default(), Some(&mut errors)) {
+            Ok(data) => (data, errors),
+            Err(err) => {
+                errors.push(NetexError {
+                    element: "PublicationDelivery".to_owned(),
+                    id: None,
+                    message: err.to_string(),
+                });
+                (NetexData::default(), errors)
+            }
+        }
+    }
+
+    pub fn from_xml_with_options(
+        read: impl std::io::Read,
+        size: usize,
+        options: &ParseOptions,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        Self::parse_xml(read, size, options, None)
+    }
+
+    // Collects the elements of an already parsed document, like from_xml.
+    pub fn from_document(
+        document: &roxmltree::Document,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        Self::from_document_with_options(document, &ParseOptions::default())
+    }
+
+    pub fn from_document_with_options(
+        document: &roxmltree::Document,
+        options: &ParseOptions,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = NetexData::default();
+        Self::visit_document(document, options, &mut data, None)?;
+        Ok(data)
+    }
+
+    // Parses the document, handing every recognized element to visitor
+    // instead of collecting them into NetexData.
+    pub fn visit(
+        read: impl std::io::Read,
+        size: usize,
+        visitor: &mut impl NetexVisitor,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Self::visit_xml(read, size, &ParseOptions::default(), visitor, None)
+    }
+
+    fn parse_xml(
+        read: impl std::io::Read,
+        size: usize,
+        options: &ParseOptions,
+        errors: Option<&mut Vec<NetexError>>,
+    ) -> Result<NetexData, Box<dyn std::error::Error + Send + Sync>> {
+        let mut data = NetexData::default();
+        Self::visit_xml(read, size, options, &mut data, errors)?;
+        Ok(data)
+    }
+
+    fn visit_xml(
+        mut read: impl std::io::Read,
+        size: usize,
+        options: &ParseOptions,
+        visitor: &mut impl NetexVisitor,
+        errors: Option<&mut Vec<NetexError>>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut buf = Vec::<u8>::with_capacity(size);
+        read.read_to_end(&mut buf)?;
+        let text = unsafe { String::from_utf8_unchecked(buf) };
+        let document = roxmltree::Document::parse(&text)?;
+        Self::visit_document(&document, options, visitor, errors)
+    }
+
+    fn visit_document(
+        document: &roxmltree::Document,
+        options: &ParseOptions,
+        visitor: &mut impl NetexVisitor,
+        mut errors: Option<&mut Vec<NetexError>>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let seeded = codespace_options(document, options);
+        let options = seeded.as_ref().unwrap_or(options);
+        // a single pass over the document, routing every element to its parser
+        let mut synthesizer = Synthesizer::new(visitor);
+        for node in document.descendants() {
+            Self::visit_node(&node, options, &mut synthesizer, errors.as_deref_mut())?;
+        }
+        synthesizer.finish();
+        Ok(())
+    }
+
+    fn visit_node(
+        node: &roxmltree::Node,
+        options: &ParseOptions,
+        visitor: &mut impl NetexVisitor,
+        errors: Option<&mut Vec<NetexError>>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let elements = options.elements;
+        let seed = options.id_seed;
+        match node.tag_name().name() {
+            "ScheduledStopPoint" if elements.contains(Elements::STOPS) => {
+                if let Some(stop) = check_element(
+                    node,
+                    NetexData::parse_scheduled_stop_point(node, options),
+                    errors,
+                )? {
+                    visitor.on_stop(stop);
+                }
+            }
+            "StopPlace" if elements.contains(Elements::STOPS) => {
+                visitor.on_stop_place(NetexData::parse_stop_place(node, seed));
+            }
+            "TopographicPlace" if elements.contains(Elements::STOPS) => {
+                visitor.on_topographic_place(NetexData::parse_topographic_place(node, seed));
+            }
+            "PassengerStopAssignment" if elements.contains(Elements::ASSIGNMENTS) => {
+                visitor.on_passenger_stop_assignment(NetexData::parse_passenger_stop_assignment(
+                    node, seed,
+                ));
+            }
+            "DayTypeAssignment" if elements.contains(Elements::ASSIGNMENTS) => {
+                if let Some(assignment) = check_element(
+                    node,
+                    NetexData::parse_day_type_assignment(node, seed),
+                    errors,
+                )? {
+                    visitor.on_day_type_assignment(assignment);
+                }
+            }
+            "ServiceJourneyPattern" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_journey_pattern(NetexData::parse_service_journey_pattern(node, seed));
+            }
+            "RoutePoint" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_route_point(NetexData::parse_route_point(node, seed));
+            }
+            "Route" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_route(NetexData::parse_route(node, seed));
+            }
+            "TimingLink" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_timing_link(NetexData::parse_timing_link(node, seed));
+            }
+            "ServiceJourney" if elements.contains(Elements::JOURNEYS) => {
+                if let Some(journey) = check_element(
+                    node,
+                    NetexData::parse_service_journey(node, options),
+                    errors,
+                )? {
+                    visitor.on_journey(journey);
+                }
+            }
+            "Line" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_line(NetexData::parse_line(node, seed));
+            }
+            "Authority" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_authority(NetexData::parse_authority(node, seed));
+            }
+            "UicOperatingPeriod" | "OperatingPeriod" if elements.contains(Elements::PERIODS) => {
+                if let Some(period) =
+                    check_element(node, NetexData::parse_operating_period(node, seed), errors)?
+                {
+                    visitor.on_period(period);
+                }
+            }
+            "DatedServiceJourney" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_dated_journey(NetexData::parse_dated_service_journey(node, seed));
+            }
+            "DayType" if elements.contains(Elements::PERIODS) => {
+                visitor.on_day_type(NetexData::parse_day_type(node, seed));
+            }
+            "OperatingDay" if elements.contains(Elements::PERIODS) => {
+                if let Some(day) =
+                    check_element(node, NetexData::parse_operating_day(node, seed), errors)?
+                {
+                    visitor.on_operating_day(day);
+                }
+            }
+            "GroupOfLines" | "Network" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_group_of_lines(NetexData::parse_group_of_lines(node, seed));
+            }
+            "Notice" if elements.contains(Elements::JOURNEYS) => {
+                visitor.on_notice(NetexData::parse_notice(node, seed));
+            }
+            "NoticeAssignment" if elements.contains(Elements::ASSIGNMENTS) => {
+                visitor.on_notice_assignment(NetexData::parse_notice_assignment(node, seed));
+            }
+            "Codespace" => {
+                visitor.on_codespace(NetexData::parse_codespace(node, seed));
+            }
+            "PublicationTimestamp" => {
+                if let Some(text) = node.text() {
+                    visitor.on_published(text.trim().to_owned());
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn parse_scheduled_stop_point(
+        node: &roxmltree::Node,
+        options: &ParseOptions,
+    ) -> Result<ScheduledStopPoint, Box<dyn std::error::Error + Send + Sync>> {
+        let name_preference = &options.name_preference;
+        let mut result = ScheduledStopPoint {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), options.id_seed),
+            ..ScheduledStopPoint::default()
+        };
+        let mut name_rank = usize::MAX;
+        // utm zone of a projected srsName and the easting and northing in it
+        let mut zone = None::<u8>;
+        let (mut easting, mut northing) = (None::<f64>, None::<f64>);
+        // Longitude and Latitude are always WGS84, whatever srsName the Location
+        // declares, and like a WGS84 pos win over projected coordinates
+        let mut geographic = false;
+        for child in node.descendants() {
+            match child.tag_name().name() {
+                tag if name_preference.iter().any(|pref| pref == tag) => {
+                    let rank = name_preference.iter().position(|pref| pref == tag).unwrap();
+                    if rank < name_rank {
+                        name_rank = rank;
+                        result.short_name = child.text().unwrap_or_default().replace('"', "");
+                    }
+                }
+                "Location" => zone = child.attribute("srsName").and_then(utm_zone),
+                // EPSG:4326 orders latitude first
+                "pos" if child.attribute("srsName").is_some_and(is_wgs84) => {
+                    let mut values = child.text().unwrap_or_default().split_whitespace();
+                    if let (Some(lat), Some(long)) = (values.next(), values.next()) {
+                        result.lat = lat.parse::<f32>()?.clamp(-90.0, 90.0);
+                        result.long = long.parse::<f32>()?.clamp(-180.0, 180.0);
+                        geographic = true;
+                    }
+                }
+                "pos" => {
+                    zone = child.attribute("srsName").and_then(utm_zone).or(zone);
+                    let mut values = child.text().unwrap_or_default().split_whitespace();
+                    if let (Some(_), Some(e), Some(n)) = (zone, values.next(), values.next()) {
+                        easting = Some(e.parse()?);
+                        northing = Some(n.parse()?);
+                    }
+                }
+                "Longitude" => {
+                    result.long = child
+                        .text()
+                        .unwrap_or_default()
+                        .parse::<f32>()?
+                        .clamp(-180.0, 180.0);
+                    geographic = true;
+                }
+                "Latitude" => {
+                    result.lat = child
+                        .text()
+                        .unwrap_or_default()
+                        .parse::<f32>()?
+                        .clamp(-90.0, 90.0);
+                    geographic = true;
+                }
+                "StopType" | "StopPlaceType" => {
+                    result.type_of_place = child.text().map(TypeOfPlace::parse);
+                }
+                "TypeOfPlaceRef" if result.type_of_place.is_none() => {
+                    result.type_of_place = child.attribute("ref").map(TypeOfPlace::parse);
+                }
+                _ => {}
+            }
+        }
+        if let (false, Some(zone), Some(easting), Some(northing)) =
+            (geographic, zone, easting, northing)
+        {
+            let (lat, long) = utm_to_wgs84(easting, northing, zone);
+            #[allow(clippy::cast_possible_truncation)]
+            {
+                result.lat = (lat as f32).clamp(-90.0, 90.0);
+                result.long = (long as f32).clamp(-180.0, 180.0);
+            }
+        }
+        if let Some(precision) = options.coordinate_precision {
+            result.long = round_to(result.long, precision);
+            result.lat = round_to(result.lat, precision);
+        }
+        Ok(result)
+    }
+
+    fn parse_stop_place(node: &roxmltree::Node, seed: u64) -> StopPlace {
+        let mut result = StopPlace {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            ..StopPlace::default()
+        };
+        for child in node.descendants() {
+            match child.tag_name().name() {
+                // values of quays and nested stop places are their own
+                "MinimumTransferTime" | "DefaultDuration" if Self::owner(&child) == Some(*node) => {
+                    result.min_transfer_seconds =
+                        Self::parse_duration_seconds(child.text().unwrap_or_default());
+                }
+                "Weighting" | "InterchangeWeighting" if Self::owner(&child) == Some(*node) => {
+                    result.weighting = result.weighting.max(InterchangeWeighting::parse(
+                        child.text().unwrap_or_default(),
+                    ));
+                }
+                // limitations of quays are kept with those
+                "AccessibilityLimitation" if Self::owner(&child) == Some(*node) => {
+                    result
+                        .accessibility
+                        .fill_unknown(&AccessibilityLimitation::parse(&child));
+                }
+                // bearings of quays in nested stop places belong to those
+                "CompassBearing"
+                    if result.compass_bearing.is_none()
+                        && Self::stop_place_of(&child) == Some(*node) =>
+                {
+                    // values outside of 0 to 360 degrees are dropped
+                    result.compass_bearing = child
+                        .text()
+                        .and_then(|text| text.trim().parse::<f32>().ok())
+                        .filter(|bearing| (0.0..=360.0).contains(bearing))
+                        .map(|bearing| bearing % 360.0);
+                }
+                // quays of nested stop places belong to those
+                "Quay" if Self::stop_place_of(&child) == Some(*node) => {
+                    let mut accessibility = AccessibilityLimitation::default();
+                    for limitation in child
+                        .descendants()
+                        .filter(|c| c.tag_name().name() == "AccessibilityLimitation")
+                    {
+                        accessibility.fill_unknown(&AccessibilityLimitation::parse(&limitation));
+                    }
+                    result.quays.push(Quay {
+                        id: hash_id_with_seed(child.attribute("id").unwrap_or_default(), seed),
+                        public_code: child
+                            .children()
+                            .find(|c| c.tag_name().name() == "PublicCode")
+                            .and_then(|c| c.text())
+                            .map(str::to_owned),
+                        accessibility,
+                    });
+                }
+                _ => {}
+            }
+        }
+        for child in node.children() {
+            match child.tag_name().name() {
+                "StopPlaceType" => result.stop_place_type = child.text().map(str::to_owned),
+                "Name" => result.name = child.text().map(str::to_owned),
+                "TopographicPlaceRef" => {
+                    result.topographic_place = child
+                        .attribute("ref")
+                        .map(|place| hash_id_with_seed(place, seed));
+                }
+                "PostalAddress" => {
+                    result.locality = child
+                        .children()
+                        .find(|address| address.tag_name().name() == "Town")
+                        .and_then(|town| town.text())
+                        .map(str::to_owned);
+                }
+                "ParentSiteRef" => {
+                    result.parent = child
+                        .attribute("ref")
+                        .map(|parent| hash_id_with_seed(parent, seed));
+                }
+                _ => {}
+            }
+        }
+        // child places may also be nested into their hub
+        if result.parent.is_none() {
+            result.parent = node
+                .ancestors()
+                .skip(1)
+                .find(|ancestor| ancestor.tag_name().name() == "StopPlace")
+                .and_then(|ancestor| ancestor.attribute("id"))
+                .map(|parent| hash_id_with_seed(parent, seed));
+        }
+        result
+    }
+
+    // the closest stop place or quay enclosing node
+    fn owner<'a, 'input>(
+        node: &roxmltree::Node<'a, 'input>,
+    ) -> Option<roxmltree::Node<'a, 'input>> {
+        node.ancestors()
+            .find(|ancestor| matches!(ancestor.tag_name().name(), "StopPlace" | "Quay"))
+    }
+
+    fn stop_place_of<'a, 'input>(
+        node: &roxmltree::Node<'a, 'input>,
+    ) -> Option<roxmltree::Node<'a, 'input>> {
+        node.ancestors()
+            .find(|ancestor| ancestor.tag_name().name() == "StopPlace")
+    }
+
+    fn parse_passenger_stop_assignment(
+        node: &roxmltree::Node,
+        seed: u64,
+    ) -> PassengerStopAssignment {
+        let mut result = PassengerStopAssignment::default();
+        for child in node.descendants() {
+            match child.tag_name().name() {
+                "ScheduledStopPointRef" => {
+                    result.scheduled_stop_point =
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
+                }
+                "StopPlaceRef" => {
+                    result.stop_place =
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
+                }
+                "QuayRef" => {
+                    result.quay = child
+                        .attribute("ref")
+                        .map(|quay| hash_id_with_seed(quay, seed));
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    // Derives passing times of journeys that only state their departure from
+    // the run and wait times of their pattern. Patterns and timing links may
+    // come from any of data, e.g. a shared service frame.
+    pub fn synthesize_passing_times(data: &mut [NetexData]) {
+        let mut schedules = Schedules::default();
+        for one_data in data.iter() {
+            for pattern in &one_data.service_journey_patterns {
+                schedules.add_pattern(pattern);
+            }
+            for link in &one_data.timing_links {
+                schedules.links.insert(link.id, (link.from, link.to));
+            }
+        }
+        for journey in data.iter_mut().flat_map(|d| d.service_journeys.iter_mut()) {
+            schedules.fill(journey);
+        }
+    }
+
+    fn parse_service_journey_pattern(node: &roxmltree::Node, seed: u64) -> ServiceJourneyPattern {
+        let mut result = ServiceJourneyPattern {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            // only direct children, stops and destination displays have names too
+            name: node
+                .children()
+                .find(|child| child.tag_name().name() == "Name")
+                .and_then(|child| child.text())
+                .map(str::to_owned),
+            transport_mode: node
+                .children()
+                .find(|child| child.tag_name().name() == "TransportMode")
+                .and_then(|child| child.text())
+                .map(str::to_owned),
+            ..ServiceJourneyPattern::default()
+        };
+        let mut waits = HashMap::<u64, u16>::new();
+        for sub_node in node.descendants() {
+            if sub_node.tag_name().name() == "LineRef" {
+                result.line =
+                    hash_id_with_seed(sub_node.attribute("ref").unwrap_or_default(), seed);
+            }
+            if sub_node.tag_name().name() == "RouteRef" {
+                result.route =
+                    hash_id_with_seed(sub_node.attribute("ref").unwrap_or_default(), seed);
+            }
+            if sub_node.tag_name().name() == "JourneyPatternRunTime" {
+                result.run_times.push(RunTime {
+                    timing_link: sub_node
+                        .children()
+                        .find(|child| child.tag_name().name() == "TimingLinkRef")
+                        .and_then(|child| child.attribute("ref"))
+                        .map(|link| hash_id_with_seed(link, seed)),
+                    minutes: Self::child_minutes(&sub_node, "RunTime"),
+                });
+            }
+            if sub_node.tag_name().name() == "JourneyPatternWaitTime" {
+                let stop = sub_node
+                    .descendants()
+                    .find(|child| {
+                        matches!(
+                            child.tag_name().name(),
+                            "StopPointInJourneyPatternRef" | "TimingPointInJourneyPatternRef"
+                        )
+                    })
+                    .and_then(|child| child.attribute("ref"));
+                if let Some(stop) = stop {
+                    waits.insert(
+                        hash_id_with_seed(stop, seed),
+                        Self::child_minutes(&sub_node, "WaitTime"),
+                    );
+                }
+            }
+            if sub_node.tag_name().name() != "StopPointInJourneyPattern" {
+                continue;
+            }
+            let mut stop = StopPointInJourneyPattern {
+                id: hash_id_with_seed(sub_node.attribute("id").unwrap_or_default(), seed),
+                order: sub_node
+                    .attribute("order")
+                    .and_then(|order| order.trim().parse().ok()),
+                ..StopPointInJourneyPattern::default()
+            };
+            stop.is_timing_point =
+                sub_node
+                    .descendants()
+                    .any(|child| match child.tag_name().name() {
+                        "TimingPointStatus" => child.text().is_some_and(|text| {
+                            matches!(text.trim(), "timingPoint" | "secondaryTimingPoint")
+                        }),
+                        "TimingPointRef" => true,
+                        _ => false,
+                    });
+            stop.scheduled_stop_point = hash_id_with_seed(
+                sub_node
+                    .descendants()
+                    .find(|child| child.tag_name().name() == "ScheduledStopPointRef")
+                    .map(|node| node.attribute("ref").unwrap_or_default())
+                    .unwrap_or_default(),
+                seed,
+            );
+            result.stops.push(stop);
+        }
+        for stop in &mut result.stops {
+            stop.wait_minutes = waits.get(&stop.id).copied().unwrap_or_default();
+        }
+        result
+    }
+
+    // an xsd duration child in whole minutes, 0 when absent
+    fn child_minutes(node: &roxmltree::Node, tag: &str) -> u16 {
+        node.children()
+            .find(|child| child.tag_name().name() == tag)
+            .and_then(|child| Self::parse_duration_seconds(child.text().unwrap_or_default()))
+            .and_then(|seconds| u16::try_from(seconds / 60).ok())
+            .unwrap_or_default()
+    }
+
+    fn parse_route_point(node: &roxmltree::Node, seed: u64) -> RoutePoint {
+        let stop = node
+            .descendants()
+            .find(|child| child.tag_name().name() == "ProjectToPointRef")
+            .map(|child| child.attribute("ref").unwrap_or_default());
+        let coordinate = |tag: &str| {
+            node.descendants()
+                .find(|child| child.tag_name().name() == tag)
+                .and_then(|child| child.text())
+                .and_then(|text| text.trim().parse::<f32>().ok())
+        };
+        RoutePoint {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            scheduled_stop_point: hash_id_with_seed(stop.unwrap_or_default(), seed),
+            is_via: stop.is_none(),
+            location: coordinate("Longitude").zip(coordinate("Latitude")),
+        }
+    }
+
+    fn parse_timing_link(node: &roxmltree::Node, seed: u64) -> TimingLink {
+        let point = |tag: &str| {
+            node.children()
+                .find(|child| child.tag_name().name() == tag)
+                .and_then(|child| child.attribute("ref"))
+                .map(|point| hash_id_with_seed(point, seed))
+                .unwrap_or_default()
+        };
+        TimingLink {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            from: point("FromPointRef"),
+            to: point("ToPointRef"),
+        }
+    }
+
+    fn parse_route(node: &roxmltree::Node, seed: u64) -> Route {
+        let mut points: Vec<(u32, u64)> = node
+            .descendants()
+            .filter(|child| child.tag_name().name() == "PointOnRoute")
+            .enumerate()
+            .map(|(idx, point)| {
+                let order = point
+                    .attribute("order")
+                    .and_then(|order| order.parse().ok())
+                    .unwrap_or(u32::try_from(idx).unwrap_or(u32::MAX));
+                let point_ref = point
+                    .descendants()
+                    .find(|child| child.tag_name().name() == "RoutePointRef")
+                    .map(|child| child.attribute("ref").unwrap_or_default())
+                    .unwrap_or_default();
+                (order, hash_id_with_seed(point_ref, seed))
+            })
+            .collect();
+        points.sort_by_key(|(order, _)| *order);
+        Route {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            points: points.into_iter().map(|(_, point)| point).collect(),
+        }
+    }
+
+    fn parse_service_journey(
+        node: &roxmltree::Node,
+        options: &ParseOptions,
+    ) -> Result<ServiceJourney, Box<dyn std::error::Error + Send + Sync>> {
+        let seed = options.id_seed;
+        let reference = |tag: &str| {
+            node.descendants()
+                .find(|node| node.tag_name().name() == tag)
+                .map(|node| node.attribute("ref").unwrap_or_default())
+                .ok_or_else(|| format!("missing {tag}"))
+        };
+        let day_type = reference("DayTypeRef")?;
+        // empty when the journey inherits the mode of its line
+        let transport_mode = node
+            .descendants()
+            .find(|node| node.tag_name().name() == "TransportMode")
+            .and_then(|node| node.text())
+            .unwrap_or_default();
+        let pattern_ref = reference("ServiceJourneyPatternRef")?;
+        let mut result = ServiceJourney {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            day_type: hash_id_with_seed(day_type, seed),
+            transport_mode: transport_mode.to_owned(),
+            pattern_ref: hash_id_with_seed(pattern_ref, seed),
+            ..ServiceJourney::default()
+        };
+        let mut departure_offset = 0_u16;
+        // the public code takes precedence over a private one
+        for child in node.children() {
+            match child.tag_name().name() {
+                "PublicCode" => result.public_code = child.text().map(str::to_owned),
+                "PrivateCode" if result.public_code.is_none() => {
+                    result.public_code = child.text().map(str::to_owned);
+                }
+                "DepartureTime" => {
+                    result.departure_time = child
+                        .text()
+                        .map(Self::parse_minutes)
+                        .transpose()?
+                        .map(DayMinute::from);
+                }
+                "DepartureDayOffset" => departure_offset = Self::parse_day_offset(&child),
+                _ => {}
+            }
+        }
+        if let Some(departure) = &mut result.departure_time {
+            *departure = departure.plus_days(departure_offset);
+        }
+        // journeys without passing times are timed by their pattern
+        for timetabled in node
+            .descendants()
+            .filter(|node| node.tag_name().name() == "passingTimes")
+            .flat_map(|node| node.descendants())
+            .filter(|node| node.tag_name().name() == "TimetabledPassingTime")
+        {
+            let mut timetabled_passing_time = TimetabledPassingTime::default();
+            // days after the operating day, folded into the minutes once all children are read
+            let (mut arrival_offset, mut departure_offset) = (0_u16, 0_u16);
+            for child in timetabled.descendants() {
                 match child.tag_name().name() {
                     "StopPointInJourneyPatternRef" => {
                         timetabled_passing_time.stop_point_in_journey_pattern =
-                            xxh3_64(child.attribute("ref").unwrap_or_default().as_bytes());
+                            hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
                     }
                     "ArrivalTime" => {
-                        timetabled_passing_time.arrival =
-                            Self::parse_minutes(child.text().unwrap_or_default());
+                        let text = child.text().unwrap_or_default();
+                        timetabled_passing_time.arrival = Some(Self::parse_minutes(text)?.into());
+                        if options.keep_raw_times {
+                            timetabled_passing_time.arrival_raw = Some(text.to_owned());
+                        }
                     }
                     "DepartureTime" => {
-                        timetabled_passing_time.departure =
-                            Self::parse_minutes(child.text().unwrap_or_default());
+                        let text = child.text().unwrap_or_default();
+                        timetabled_passing_time.departure = Some(Self::parse_minutes(text)?.into());
+                        if options.keep_raw_times {
+                            timetabled_passing_time.departure_raw = Some(text.to_owned());
+                        }
                     }
+                    "ArrivalDayOffset" => arrival_offset = Self::parse_day_offset(&child),
+                    "DepartureDayOffset" => departure_offset = Self::parse_day_offset(&child),
                     _ => {}
                 }
             }
+            if let Some(arrival) = &mut timetabled_passing_time.arrival {
+                *arrival = arrival.plus_days(arrival_offset);
+            }
+            if let Some(departure) = &mut timetabled_passing_time.departure {
+                *departure = departure.plus_days(departure_offset);
+            }
             result.passing_times.push(timetabled_passing_time);
         }
-        result
+        Ok(result)
     }
 
-    fn parse_operating_period(node: &roxmltree::Node) -> UicOperatingPeriod {
+    fn parse_operating_period(
+        node: &roxmltree::Node,
+        seed: u64,
+    ) -> Result<UicOperatingPeriod, Box<dyn std::error::Error + Send + Sync>> {
+        let id = node.attribute("id").unwrap_or_default();
         let mut result = UicOperatingPeriod {
-            id: xxh3_64(node.attribute("id").unwrap_or_default().as_bytes()),
+            id: hash_id_with_seed(id, seed),
+            id_ref: id.to_owned(),
             ..UicOperatingPeriod::default()
         };
+        let mut has_bits = false;
+        // explicitly listed dates, e.g. below IncludedDays
+        let mut dates = Vec::<u16>::new();
         for child in node.descendants() {
             match child.tag_name().name() {
-                "FromDate" => result.from = Self::parse_date(child.text().unwrap_or_default()),
-                "ToDate" => result.to = Self::parse_date(child.text().unwrap_or_default()),
+                "FromDate" => result.from = Self::parse_date(child.text().unwrap_or_default())?,
+                "ToDate" => result.to = Self::parse_date(child.text().unwrap_or_default())?,
                 "ValidDayBits" => {
-                    result.valid_day_bits =
-                        Self::parse_day_bits(child.text().unwrap_or_default().to_owned());
+                    has_bits = true;
+                    result.valid_day_bits = Self::parse_day_bits(child.text().unwrap_or_default());
+                }
+                "Date" => dates.push(Self::parse_date(child.text().unwrap_or_default())?),
+                _ => {}
+            }
+        }
+        if !has_bits && !dates.is_empty() {
+            let by_day = |date: &u16| crate::graph::days_since_epoch(*date);
+            if result.from == 0 {
+                result.from = *dates.iter().min_by_key(|date| by_day(date)).unwrap();
+            }
+            if result.to == 0 {
+                result.to = *dates.iter().max_by_key(|date| by_day(date)).unwrap();
+            }
+            for date in dates {
+                result.set_day(date, true);
+            }
+        } else if !has_bits && node.tag_name().name() == "OperatingPeriod" {
+            // a plain operating period covers every day between its dates
+            let span = crate::graph::days_since_epoch(result.to)
+                - crate::graph::days_since_epoch(result.from)
+                + 1;
+            let span = usize::try_from(span).unwrap_or_default();
+            result.valid_day_bits = vec![0; span.div_ceil(8)];
+            for day in 0..span {
+                result.valid_day_bits[day / 8] |= 1 << (day % 8);
+            }
+        }
+        Ok(result)
+    }
+
+    fn parse_day_type(node: &roxmltree::Node, seed: u64) -> DayType {
+        DayType {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            name: node
+                .children()
+                .find(|child| child.tag_name().name() == "Name")
+                .and_then(|child| child.text())
+                .map(str::to_owned),
+        }
+    }
+
+    fn parse_codespace(node: &roxmltree::Node, seed: u64) -> Codespace {
+        let mut result = Codespace {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            ..Codespace::default()
+        };
+        for child in node.children() {
+            match child.tag_name().name() {
+                "Xmlns" => child
+                    .text()
+                    .unwrap_or_default()
+                    .trim()
+                    .clone_into(&mut result.xmlns),
+                "XmlnsUrl" => result.url = child.text().map(|text| text.trim().to_owned()),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    // the name is either a direct child or part of the descriptor
+    fn parse_topographic_place(node: &roxmltree::Node, seed: u64) -> TopographicPlace {
+        TopographicPlace {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            name: node
+                .children()
+                .flat_map(|child| match child.tag_name().name() {
+                    "Descriptor" => child.children().collect(),
+                    _ => vec![child],
+                })
+                .find(|child| child.tag_name().name() == "Name")
+                .and_then(|child| child.text())
+                .map(str::to_owned),
+        }
+    }
+
+    fn parse_operating_day(
+        node: &roxmltree::Node,
+        seed: u64,
+    ) -> Result<OperatingDay, Box<dyn std::error::Error + Send + Sync>> {
+        let mut result = OperatingDay {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            ..OperatingDay::default()
+        };
+        if let Some(date) = node
+            .children()
+            .find(|child| child.tag_name().name() == "CalendarDate")
+            .and_then(|child| child.text())
+        {
+            result.date = Self::parse_date(date)?;
+        }
+        Ok(result)
+    }
+
+    fn parse_dated_service_journey(node: &roxmltree::Node, seed: u64) -> DatedServiceJourney {
+        let mut result = DatedServiceJourney {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            ..DatedServiceJourney::default()
+        };
+        for child in node.children() {
+            match child.tag_name().name() {
+                "ServiceJourneyRef" => {
+                    result.service_journey =
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
+                }
+                "OperatingDayRef" => {
+                    result.operating_day =
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
+                }
+                "ServiceAlteration" => {
+                    result.cancelled = child.text().map(str::trim) == Some("cancellation");
                 }
                 _ => {}
             }
@@ -275,17 +2465,23 @@ impl NetexData {
 
     fn parse_day_type_assignment(
         node: &roxmltree::Node,
-    ) -> Result<DayTypeAssignment, Box<dyn std::error::Error>> {
-        let mut assignment = DayTypeAssignment::default();
+        seed: u64,
+    ) -> Result<DayTypeAssignment, Box<dyn std::error::Error + Send + Sync>> {
+        // netex defaults isAvailable to true
+        let mut assignment = DayTypeAssignment {
+            is_available: true,
+            ..DayTypeAssignment::default()
+        };
         for child in node.descendants() {
             match child.tag_name().name() {
                 "OperatingPeriodRef" => {
                     assignment.operating_period =
-                        xxh3_64(child.attribute("ref").unwrap_or_default().as_bytes());
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
                 }
                 "DayTypeRef" => {
-                    assignment.day_type =
-                        xxh3_64(child.attribute("ref").unwrap_or_default().as_bytes());
+                    let day_type_ref = child.attribute("ref").unwrap_or_default();
+                    assignment.day_type = hash_id_with_seed(day_type_ref, seed);
+                    day_type_ref.clone_into(&mut assignment.day_type_ref);
                 }
                 "isAvailable" => {
                     assignment.is_available = child.text().unwrap_or_default().parse()?;
@@ -296,19 +2492,43 @@ impl NetexData {
         Ok(assignment)
     }
 
-    fn parse_line(node: &roxmltree::Node) -> Line {
+    fn parse_line(node: &roxmltree::Node, seed: u64) -> Line {
         let mut result = Line {
-            id: xxh3_64(node.attribute("id").unwrap_or_default().as_bytes()),
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
             ..Line::default()
         };
         for child in node.descendants() {
             match child.tag_name().name() {
                 "ShortName" => {
-                    child.text().unwrap_or_default().clone_into(&mut result.short_name);
+                    child
+                        .text()
+                        .unwrap_or_default()
+                        .clone_into(&mut result.short_name);
                 }
                 "AuthorityRef" => {
                     result.authority =
-                        xxh3_64(child.attribute("ref").unwrap_or_default().as_bytes());
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
+                }
+                "Monitored" => {
+                    result.monitored = child.text().is_some_and(|text| text.trim() == "true");
+                }
+                "TransportMode" => result.transport_mode = child.text().map(str::to_owned),
+                "Url" | "InfoLink" if result.url.is_none() => {
+                    result.url = child
+                        .text()
+                        .map(str::trim)
+                        .filter(|text| !text.is_empty())
+                        .or_else(|| child.attribute("uri"))
+                        .map(str::to_owned);
+                }
+                "Colour" => result.color = child.text().map(|text| text.trim().to_owned()),
+                "TextColour" => {
+                    result.text_color = child.text().map(|text| text.trim().to_owned());
+                }
+                "RepresentedByGroupRef" => {
+                    result.group = child
+                        .attribute("ref")
+                        .map(|group| hash_id_with_seed(group, seed));
                 }
                 _ => {}
             }
@@ -316,58 +2536,434 @@ impl NetexData {
         result
     }
 
-    fn parse_authority(node: &roxmltree::Node) -> Authority {
+    fn parse_authority(node: &roxmltree::Node, seed: u64) -> Authority {
         let mut result = Authority {
-            id: xxh3_64(node.attribute("id").unwrap_or_default().as_bytes()),
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
             ..Authority::default()
         };
-        for child in node.descendants() {
-            if child.tag_name().name() == "ShortName" {
-                child.text().unwrap_or_default().clone_into(&mut result.short_name);
-            }
+        // customer service details take priority, field by field
+        let (mut customer_service, mut general) = (None, None);
+        for child in node.descendants() {
+            match child.tag_name().name() {
+                "ShortName" => {
+                    child
+                        .text()
+                        .unwrap_or_default()
+                        .clone_into(&mut result.short_name);
+                }
+                "ParentOrganisationRef" => {
+                    result.parent = Some(hash_id_with_seed(
+                        child.attribute("ref").unwrap_or_default(),
+                        seed,
+                    ));
+                }
+                "CustomerServiceContactDetails" => {
+                    customer_service = Some(Self::parse_contact_details(&child));
+                }
+                "ContactDetails" => general = Some(Self::parse_contact_details(&child)),
+                _ => {}
+            }
+        }
+        result.contact = match (customer_service, general) {
+            (Some(mut contact), Some(general)) => {
+                contact.fill_missing(general);
+                Some(contact)
+            }
+            (contact, general) => contact.or(general),
+        };
+        result
+    }
+
+    fn parse_contact_details(node: &roxmltree::Node) -> ContactDetails {
+        let field = |name: &str| {
+            node.children()
+                .find(|child| child.tag_name().name() == name)
+                .and_then(|child| child.text())
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_owned)
+        };
+        ContactDetails {
+            phone: field("Phone"),
+            url: field("Url"),
+            email: field("Email"),
+        }
+    }
+
+    fn parse_group_of_lines(node: &roxmltree::Node, seed: u64) -> GroupOfLines {
+        let mut result = GroupOfLines {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            network: node.tag_name().name() == "Network",
+            ..GroupOfLines::default()
+        };
+        for child in node.children() {
+            match child.tag_name().name() {
+                "Name" => result.name = child.text().map(str::to_owned),
+                "members" => {
+                    // groups nested into a network list their own members
+                    result.lines = child
+                        .children()
+                        .filter(|member| member.tag_name().name() == "LineRef")
+                        .filter_map(|member| member.attribute("ref"))
+                        .map(|line| hash_id_with_seed(line, seed))
+                        .collect();
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+
+    fn parse_notice(node: &roxmltree::Node, seed: u64) -> Notice {
+        let mut result = Notice {
+            id: hash_id_with_seed(node.attribute("id").unwrap_or_default(), seed),
+            ..Notice::default()
+        };
+        for child in node.children() {
+            match child.tag_name().name() {
+                "Text" => {
+                    child
+                        .text()
+                        .unwrap_or_default()
+                        .trim()
+                        .clone_into(&mut result.text);
+                    result.lang = child
+                        .attribute(("http://www.w3.org/XML/1998/namespace", "lang"))
+                        .or_else(|| child.attribute("lang"))
+                        .map(str::to_owned);
+                }
+                "PublicCode" => result.public_code = child.text().map(str::to_owned),
+                _ => {}
+            }
+        }
+        result
+    }
+
+    // Assignments nested in the noticed object may omit NoticedObjectRef,
+    // the closest enclosing element with an id is used then.
+    fn parse_notice_assignment(node: &roxmltree::Node, seed: u64) -> NoticeAssignment {
+        let mut result = NoticeAssignment::default();
+        let mut noticed_object = node
+            .ancestors()
+            .skip(1)
+            .find_map(|ancestor| ancestor.attribute("id"));
+        for child in node.children() {
+            match child.tag_name().name() {
+                "NoticeRef" => {
+                    result.notice =
+                        hash_id_with_seed(child.attribute("ref").unwrap_or_default(), seed);
+                }
+                "NoticedObjectRef" => noticed_object = child.attribute("ref"),
+                _ => {}
+            }
+        }
+        result.noticed_object = hash_id_with_seed(noticed_object.unwrap_or_default(), seed);
+        result
+    }
+
+    // Maps operating period ids to the refs of the day types assigned to them
+    pub fn period_day_types(&self) -> HashMap<u64, Vec<&str>> {
+        let mut result = HashMap::<u64, Vec<&str>>::new();
+        for assignment in &self.day_type_assignments {
+            let day_types = result.entry(assignment.operating_period).or_default();
+            if !day_types.contains(&assignment.day_type_ref.as_str()) {
+                day_types.push(&assignment.day_type_ref);
+            }
+        }
+        result
+    }
+
+    pub fn day_type_name(&self, day_type: u64) -> Option<&str> {
+        self.day_types
+            .iter()
+            .find(|candidate| candidate.id == day_type)
+            .and_then(|candidate| candidate.name.as_deref())
+    }
+
+    // Refs of the day types assigned to one operating period, in a single scan
+    // of the assignments. Build period_day_types once to look up many periods.
+    pub fn day_types_for_period(&self, period_id: u64) -> Vec<&str> {
+        let mut result = Vec::<&str>::new();
+        for assignment in &self.day_type_assignments {
+            if assignment.operating_period == period_id
+                && !result.contains(&assignment.day_type_ref.as_str())
+            {
+                result.push(&assignment.day_type_ref);
+            }
+        }
+        result
+    }
+
+    // Checks that the stops of every journey pattern follow the order of its route
+    pub fn validate_stop_order(&self) -> Vec<StopOrderDiscrepancy> {
+        let stop_to_route_point: HashMap<u64, u64> = self
+            .route_points
+            .iter()
+            .filter(|point| !point.is_via)
+            .map(|point| (point.scheduled_stop_point, point.id))
+            .collect();
+        let routes: HashMap<u64, &Route> =
+            self.routes.iter().map(|route| (route.id, route)).collect();
+        let mut result = Vec::new();
+        for pattern in &self.service_journey_patterns {
+            let Some(route) = routes.get(&pattern.route) else {
+                continue;
+            };
+            let positions: HashMap<u64, usize> = route
+                .points
+                .iter()
+                .enumerate()
+                .map(|(idx, point)| (*point, idx))
+                .collect();
+            let mut last_position = 0;
+            for stop in &pattern.stops {
+                let Some(position) = stop_to_route_point
+                    .get(&stop.scheduled_stop_point)
+                    .and_then(|point| positions.get(point))
+                else {
+                    continue;
+                };
+                if *position < last_position {
+                    result.push(StopOrderDiscrepancy {
+                        pattern: pattern.id,
+                        route: route.id,
+                        scheduled_stop_point: stop.scheduled_stop_point,
+                    });
+                }
+                last_position = last_position.max(*position);
+            }
+        }
+        result
+    }
+
+    // days a journey runs on by its dated journeys, cancelled ones excluded
+    fn dated_operating_days(&self) -> HashMap<u64, Vec<&OperatingDay>> {
+        let days: HashMap<u64, &OperatingDay> = self
+            .operating_days
+            .iter()
+            .map(|day| (day.id, day))
+            .collect();
+        let mut dated = HashMap::<u64, Vec<&OperatingDay>>::new();
+        for journey in self.dated_service_journeys.iter().filter(|d| !d.cancelled) {
+            if let Some(day) = days.get(&journey.operating_day) {
+                dated.entry(journey.service_journey).or_default().push(day);
+            }
+        }
+        dated
+    }
+
+    // Follows every journey to its pattern, the pattern's route and line, and
+    // the operating periods of its day type or the days of its dated journeys.
+    pub fn resolve_chain(&self) -> Vec<ResolvedJourney<'_>> {
+        let patterns: HashMap<u64, &ServiceJourneyPattern> = self
+            .service_journey_patterns
+            .iter()
+            .map(|pattern| (pattern.id, pattern))
+            .collect();
+        let routes: HashMap<u64, &Route> =
+            self.routes.iter().map(|route| (route.id, route)).collect();
+        let lines: HashMap<u64, &Line> = self.lines.iter().map(|line| (line.id, line)).collect();
+        let periods: HashMap<u64, &UicOperatingPeriod> = self
+            .operating_periods
+            .iter()
+            .map(|period| (period.id, period))
+            .collect();
+        let mut assignments = HashMap::<u64, Vec<&DayTypeAssignment>>::new();
+        for dta in self
+            .day_type_assignments
+            .iter()
+            .filter(|dta| dta.is_available)
+        {
+            assignments.entry(dta.day_type).or_default().push(dta);
+        }
+        let dated = self.dated_operating_days();
+        self.service_journeys
+            .iter()
+            .map(|journey| {
+                let mut errors = Vec::new();
+                let pattern = patterns.get(&journey.pattern_ref).copied();
+                if pattern.is_none() {
+                    errors.push(ChainError::MissingPattern(journey.pattern_ref));
+                }
+                let route = pattern.and_then(|pattern| match routes.get(&pattern.route) {
+                    None if pattern.route != 0 => {
+                        errors.push(ChainError::MissingRoute(pattern.route));
+                        None
+                    }
+                    route => route.copied(),
+                });
+                let line = pattern.and_then(|pattern| {
+                    let line = lines.get(&pattern.line).copied();
+                    if line.is_none() {
+                        errors.push(ChainError::MissingLine(pattern.line));
+                    }
+                    line
+                });
+                let assigned = assignments
+                    .get(&journey.day_type)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let operating_days = dated.get(&journey.id).cloned().unwrap_or_default();
+                if assigned.is_empty() && operating_days.is_empty() {
+                    errors.push(ChainError::MissingCalendar(journey.day_type));
+                }
+                let periods = assigned
+                    .iter()
+                    .filter_map(|dta| {
+                        let period = periods.get(&dta.operating_period).copied();
+                        if period.is_none() {
+                            errors.push(ChainError::MissingPeriod(dta.operating_period));
+                        }
+                        period
+                    })
+                    .collect();
+                ResolvedJourney {
+                    journey,
+                    pattern,
+                    route,
+                    line,
+                    periods,
+                    operating_days,
+                    errors,
+                }
+            })
+            .collect()
+    }
+
+    // (long, lat) of the points of a route in travel order. Stops are placed at
+    // their scheduled stop point, via points at their own location.
+    // Points without a known position are skipped.
+    pub fn route_shape(&self, route: u64) -> Vec<(f32, f32)> {
+        let Some(route) = self.routes.iter().find(|candidate| candidate.id == route) else {
+            return Vec::new();
+        };
+        let points: HashMap<u64, &RoutePoint> = self
+            .route_points
+            .iter()
+            .map(|point| (point.id, point))
+            .collect();
+        let stops: HashMap<u64, (f32, f32)> = self
+            .scheduled_stop_points
+            .iter()
+            .map(|stop| (stop.id, (stop.long, stop.lat)))
+            .collect();
+        route
+            .points
+            .iter()
+            .filter_map(|id| points.get(id))
+            .filter_map(|point| {
+                if point.is_via {
+                    point.location
+                } else {
+                    stops.get(&point.scheduled_stop_point).copied()
+                }
+            })
+            .collect()
+    }
+
+    // In netex departure and arrival time are reqpresented as hh:mm:ss
+    // seconds are mostly 00 anyway, so we only care about the minute of day
+    fn parse_minutes(value: &str) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+        let invalid = || format!("invalid time {value:?}");
+        let mut parts = value.trim().split(':');
+        let (Some(hours), Some(minutes)) = (parts.next(), parts.next()) else {
+            return Err(invalid().into());
+        };
+        let hours: u16 = hours.parse().map_err(|_| invalid())?;
+        let minutes: u16 = minutes.parse().map_err(|_| invalid())?;
+        if hours > 99 || minutes > 59 {
+            return Err(invalid().into());
+        }
+        Ok(hours * 60 + minutes)
+    }
+
+    // Day offsets are capped at 30 days, far beyond any real timetable, so that
+    // the offset plus a time of at most 99:59 stays within the u16 minutes
+    fn parse_day_offset(node: &roxmltree::Node) -> u16 {
+        node.text()
+            .and_then(|text| text.trim().parse::<u16>().ok())
+            .unwrap_or_default()
+            .min(30)
+    }
+
+    // Parses xsd:duration values like "PT5M" or "PT300S" into seconds
+    // years and months have no fixed length, so they are rejected
+    fn parse_duration_seconds(value: &str) -> Option<u32> {
+        let value = value.trim().strip_prefix('P')?;
+        let mut result = 0_u32;
+        let mut number = 0_u32;
+        let mut in_time = false;
+        for c in value.chars() {
+            match c {
+                '0'..='9' => number = number.checked_mul(10)?.checked_add(c.to_digit(10)?)?,
+                'T' => in_time = true,
+                'D' if !in_time => result = result.checked_add(number.checked_mul(86400)?)?,
+                'H' if in_time => result = result.checked_add(number.checked_mul(3600)?)?,
+                'M' if in_time => result = result.checked_add(number.checked_mul(60)?)?,
+                'S' if in_time => result = result.checked_add(number)?,
+                _ => return None,
+            }
+            if !c.is_ascii_digit() {
+                number = 0;
+            }
+        }
+        Some(result)
+    }
+
+    // Parses "2022-06-13T00:00:00" into bits
+    // day 5 bit, month 4 bit, year 7 bit, the year is kept modulo 100
+    fn parse_date(value: &str) -> Result<u16, Box<dyn std::error::Error + Send + Sync>> {
+        let invalid = || format!("invalid date {value:?}");
+        let date = value.trim().get(..10).ok_or_else(invalid)?;
+        let mut parts = date.split('-');
+        let (Some(year), Some(month), Some(day), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid().into());
+        };
+        let year: u16 = year.parse().map_err(|_| invalid())?;
+        let month: u16 = month.parse().map_err(|_| invalid())?;
+        let day: u16 = day.parse().map_err(|_| invalid())?;
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return Err(invalid().into());
         }
-        result
+        Ok((year % 100) | (month << 7) | (day << 11))
     }
 
-    // In netex departure and arrival time are reqpresented as hh:mm:ss
-    // seconds are mostly 00 anyway, so we only care about the minute of day
-    // lets also assume times are represented as ascii chars
-    #[allow(clippy::cast_lossless)]
-    fn parse_minutes(value: &str) -> u16 {
-        const ASCII_ZERO: u16 = 48;
-        let bytes = value.as_bytes();
-        let mut result = 0_u16;
-        result += (bytes[0] as u16 - ASCII_ZERO) * 600;
-        result += (bytes[1] as u16 - ASCII_ZERO) * 60;
-        result += (bytes[3] as u16 - ASCII_ZERO) * 10;
-        result += bytes[4] as u16 - ASCII_ZERO;
-        result
+    // Seconds since 1970 of a timestamp like "2022-06-01T12:00:00.5+02:00",
+    // timestamps without offset are taken as UTC
+    fn parse_timestamp(value: &str) -> Option<i64> {
+        let value = value.trim();
+        let days = crate::graph::days_since_epoch(Self::parse_date(value).ok()?);
+        let time = value.get(10..)?.strip_prefix('T')?;
+        let (time, offset) = time.split_at(time.find(['Z', '+', '-']).unwrap_or(time.len()));
+        let mut parts = time.split(':');
+        let hours: i64 = parts.next()?.parse().ok()?;
+        let minutes: i64 = parts.next()?.parse().ok()?;
+        // fractions of a second are dropped
+        let seconds: i64 = match parts.next() {
+            Some(seconds) => seconds.split('.').next()?.parse().ok()?,
+            None => 0,
+        };
+        let offset_minutes = match offset {
+            "" | "Z" => 0,
+            _ => {
+                let digits = offset[1..].replace(':', "");
+                let hours: i64 = digits.get(..2)?.parse().ok()?;
+                let minutes: i64 = digits
+                    .get(2..)
+                    .filter(|m| !m.is_empty())
+                    .map_or(Some(0), |m| m.parse().ok())?;
+                let sign = if offset.starts_with('-') { -1 } else { 1 };
+                sign * (hours * 60 + minutes)
+            }
+        };
+        Some(days * 86400 + hours * 3600 + minutes * 60 + seconds - offset_minutes * 60)
     }
 
-    // Parses "2022-06-13T00:00:00" into bits
-    // day 5 bit, month 4 bit, year 7 bit
-    #[allow(clippy::cast_lossless)]
-    fn parse_date(value: &str) -> u16 {
-        const ASCII_ZERO: u16 = 48;
-        let bytes = value.as_bytes();
-        // year
-        let mut year: u16 = (bytes[2] as u16 - ASCII_ZERO) * 10;
-        assert!(year < 128);
-        year += bytes[3] as u16 - ASCII_ZERO;
-        // month
-        let mut month: u16 = (bytes[5] as u16 - ASCII_ZERO) * 10;
-        assert!(month < 16);
-        month += bytes[6] as u16 - ASCII_ZERO;
-        year |= month << 7;
-        // day
-        let mut day: u16 = (bytes[8] as u16 - ASCII_ZERO) * 10;
-        assert!(day < 32);
-        day += bytes[9] as u16 - ASCII_ZERO;
-        year | (day << 11)
-    }
-
-    // Parses "11001100"... as Vec<u8>
-    fn parse_day_bits(mut value: String) -> Vec<u8> {
+    // Parses "11001100"... as Vec<u8>, hex strings like "0xCC" are expanded first
+    fn parse_day_bits(value: &str) -> Vec<u8> {
+        let mut value = Self::day_bits_as_binary(value);
         let pad_len = 8 - (value.len() % 8);
         if pad_len != 8 {
             value.push_str(&"0".repeat(pad_len));
@@ -379,40 +2975,77 @@ impl NetexData {
         result
     }
 
-    // value should be at least 8 byte long
+    // Only 0x prefixed strings are hex, with four days per digit and the most
+    // significant first. A binary string like "1100" is a valid hex one as well.
+    fn day_bits_as_binary(value: &str) -> String {
+        let trimmed = value.trim();
+        let Some(hex) = trimmed
+            .strip_prefix("0x")
+            .or_else(|| trimmed.strip_prefix("0X"))
+        else {
+            return trimmed.to_owned();
+        };
+        hex.chars()
+            .filter_map(|c| c.to_digit(16))
+            .flat_map(|digit| {
+                (0..4)
+                    .rev()
+                    .map(move |bit| if digit >> bit & 1 == 1 { '1' } else { '0' })
+            })
+            .collect()
+    }
+
+    // value should be at least 8 byte long, anything but 1 is a day without service
     #[allow(clippy::needless_range_loop)]
     fn parse_day_bit_group(value: &[u8]) -> u8 {
-        const ASCII_ZERO: u8 = 48;
         let mut result = 0_u8;
         for i in 0..8 {
-            result |= (value[i] - ASCII_ZERO) << i;
+            result |= u8::from(value[i] == b'1') << i;
         }
         result
     }
 }
 
+#[cfg(test)]
 mod tests {
     #[test]
     fn parse_minutes() {
-        let result = super::NetexData::parse_minutes("12:34");
+        let result = super::NetexData::parse_minutes("12:34").unwrap();
         assert_eq!(result, 754);
     }
 
     #[test]
     fn parse_day_bits_group() {
-        let result = super::NetexData::parse_day_bits("1111111011".to_owned());
+        let result = super::NetexData::parse_day_bits("1111111011");
         assert_eq!(&result, &[127, 3]);
     }
 
+    #[test]
+    fn parse_day_bits_hex() {
+        // 1111 1000 0011
+        let result = super::NetexData::parse_day_bits("0xF83");
+        assert_eq!(&result, &[0b0001_1111, 0b0000_1100]);
+        // without prefix digits other than 1 are days without service
+        assert_eq!(super::NetexData::parse_day_bits("F83"), &[0]);
+        assert_eq!(super::NetexData::parse_day_bits("1100"), &[3]);
+        assert_eq!(
+            super::NetexData::parse_day_bits("0x10"),
+            super::NetexData::parse_day_bits("00010000")
+        );
+        assert_eq!(super::NetexData::parse_day_bits("10"), &[1]);
+        // stray characters count as days without service instead of underflowing
+        assert_eq!(super::NetexData::parse_day_bits("1x1-"), &[5]);
+    }
+
     #[test]
     fn parse_date() {
-        let result = super::NetexData::parse_date("2003-02-01T00:00:00");
+        let result = super::NetexData::parse_date("2003-02-01T00:00:00").unwrap();
         assert_eq!(result, 0b_0000_1001_0000_0011);
     }
 
     #[test]
     fn decode_date() {
-        let parsed = super::NetexData::parse_date("2045-11-23T00:00:00");
+        let parsed = super::NetexData::parse_date("2045-11-23T00:00:00").unwrap();
         let year = parsed & 0b_0000_0000_0111_1111;
         let month = (parsed >> 7) & 0b_0000_0000_0000_1111;
         let day = (parsed >> 11) & 0b_0000_0000_0001_1111;
@@ -420,4 +3053,1526 @@ mod tests {
         assert_eq!(month, 11_u16);
         assert_eq!(day, 23_u16);
     }
+
+    #[test]
+    fn parse_duration_seconds() {
+        assert_eq!(super::NetexData::parse_duration_seconds("PT5M"), Some(300));
+        assert_eq!(
+            super::NetexData::parse_duration_seconds("PT1H2M3S"),
+            Some(3723)
+        );
+        assert_eq!(super::NetexData::parse_duration_seconds("P1Y"), None);
+        // durations beyond u32 seconds are rejected instead of wrapping
+        assert_eq!(super::NetexData::parse_duration_seconds("P50000D"), None);
+        assert_eq!(
+            super::NetexData::parse_duration_seconds("PT99999999999S"),
+            None
+        );
+    }
+
+    #[test]
+    fn single_pass_matches_multi_pass() {
+        fn keys<T, K>(items: &[T], key: impl Fn(&T) -> K) -> Vec<K> {
+            items.iter().map(key).collect()
+        }
+        let xml = crate::fixtures::DOCUMENT;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        // the multi-pass parse scans the document once per element kind
+        let document = roxmltree::Document::parse(xml).unwrap();
+        let options = super::ParseOptions::default();
+
+        let stop = |e: &super::ScheduledStopPoint| (e.id, e.short_name.clone(), e.long, e.lat);
+        let stops = super::parse_stops(&document, &options).unwrap();
+        assert_eq!(keys(&data.scheduled_stop_points, stop), keys(&stops, stop));
+        let place = |e: &super::StopPlace| (e.id, e.min_transfer_seconds, e.name.clone());
+        let places = super::parse_stop_places(&document, &options);
+        assert_eq!(keys(&data.stop_places, place), keys(&places, place));
+        let psa = |e: &super::PassengerStopAssignment| (e.scheduled_stop_point, e.stop_place);
+        let psas = super::parse_passenger_stop_assignments(&document, &options);
+        assert_eq!(
+            keys(&data.passenger_stop_assignments, psa),
+            keys(&psas, psa)
+        );
+        let pattern = |e: &super::ServiceJourneyPattern| {
+            let stops = keys(&e.stops, |stop| stop.scheduled_stop_point);
+            (e.id, e.line, e.route, e.name.clone(), stops)
+        };
+        let patterns = super::parse_journey_patterns(&document, &options);
+        assert_eq!(
+            keys(&data.service_journey_patterns, pattern),
+            keys(&patterns, pattern)
+        );
+        let journey = |e: &super::ServiceJourney| {
+            let times = keys(&e.passing_times, |time| {
+                (
+                    time.stop_point_in_journey_pattern,
+                    time.arrival,
+                    time.departure,
+                )
+            });
+            (
+                e.id,
+                e.day_type,
+                e.pattern_ref,
+                e.transport_mode.clone(),
+                times,
+            )
+        };
+        let journeys = super::parse_journeys(&document, &options).unwrap();
+        assert_eq!(
+            keys(&data.service_journeys, journey),
+            keys(&journeys, journey)
+        );
+        let period = |e: &super::UicOperatingPeriod| (e.id, e.from, e.to, e.valid_day_bits.clone());
+        let periods = super::parse_periods(&document, &options).unwrap();
+        assert_eq!(
+            keys(&data.operating_periods, period),
+            keys(&periods, period)
+        );
+        let assignment = |e: &super::DayTypeAssignment| {
+            (
+                e.operating_period,
+                e.day_type,
+                e.day_type_ref.clone(),
+                e.is_available,
+            )
+        };
+        let assignments = super::parse_day_type_assignments(&document, &options).unwrap();
+        assert_eq!(
+            keys(&data.day_type_assignments, assignment),
+            keys(&assignments, assignment)
+        );
+        let line = |e: &super::Line| (e.id, e.short_name.clone(), e.authority);
+        let lines = super::parse_lines(&document, &options);
+        assert_eq!(keys(&data.lines, line), keys(&lines, line));
+        let authority = |e: &super::Authority| (e.id, e.short_name.clone(), e.parent);
+        let authorities = super::parse_authorities(&document, &options);
+        assert_eq!(
+            keys(&data.authorities, authority),
+            keys(&authorities, authority)
+        );
+    }
+
+    #[test]
+    fn parse_stops() {
+        let document = roxmltree::Document::parse(crate::fixtures::DOCUMENT).unwrap();
+        let stops = super::parse_stops(&document, &super::ParseOptions::default()).unwrap();
+        let names: Vec<&str> = stops.iter().map(|s| s.short_name.as_str()).collect();
+        assert_eq!(names, vec!["Köln Hbf", "Köln Messe/Deutz", "Köln-Mülheim"]);
+        assert_eq!(stops[0].id, super::hash_id("ssp:a"));
+        assert!((stops[0].long - 6.958).abs() < 1e-5);
+
+        let data = super::NetexData::from_document(&document).unwrap();
+        let expected = crate::fixtures::data();
+        assert_eq!(data.scheduled_stop_points.len(), 3);
+        assert_eq!(data.lines[0].short_name, expected.lines[0].short_name);
+        assert_eq!(
+            data.service_journeys[0].passing_times.len(),
+            expected.service_journeys[0].passing_times.len()
+        );
+        assert_eq!(
+            data.operating_periods[0].valid_day_bits,
+            expected.operating_periods[0].valid_day_bits
+        );
+
+        let options = super::ParseOptions {
+            id_seed: 1,
+            ..super::ParseOptions::default()
+        };
+        let seeded = super::hash_id_with_seed("ssp:a", 1);
+        assert_eq!(
+            super::parse_stops(&document, &options).unwrap()[0].id,
+            seeded
+        );
+        let lines = super::parse_lines(&document, &options);
+        assert_eq!(lines[0].id, super::hash_id_with_seed("line:1", 1));
+        let data = super::NetexData::from_document_with_options(&document, &options).unwrap();
+        assert_eq!(data.scheduled_stop_points[0].id, seeded);
+    }
+
+    #[test]
+    fn from_zip_reader() {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::<u8>::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("line.xml", options).unwrap();
+        zip.write_all(crate::fixtures::DOCUMENT.as_bytes()).unwrap();
+        zip.start_file("readme.txt", options).unwrap();
+        zip.write_all(b"not netex").unwrap();
+        let cursor = zip.finish().unwrap();
+        let data = super::NetexData::from_zip_reader(cursor, ".xml").unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].scheduled_stop_points.len(), 3);
+        assert_eq!(data[0].service_journeys[0].source, Some(0));
+    }
+
+    #[test]
+    fn from_zip_reader_errors() {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::<u8>::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("line.xml", options).unwrap();
+        zip.write_all(crate::fixtures::DOCUMENT.as_bytes()).unwrap();
+        zip.start_file("broken.xml", options).unwrap();
+        zip.write_all(b"<PublicationDelivery>").unwrap();
+        let cursor = zip.finish().unwrap();
+        let err = super::NetexData::from_zip_reader(cursor, ".xml")
+            .err()
+            .unwrap();
+        let err = err.downcast::<super::DocumentError>().unwrap();
+        assert_eq!(err.document, "broken.xml");
+        assert!(err.source.downcast_ref::<roxmltree::Error>().is_some());
+    }
+
+    #[test]
+    fn from_zip_skipping_errors() {
+        use std::io::Write;
+        let path =
+            std::env::temp_dir().join(format!("netex-parse-skip-{}.zip", std::process::id()));
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("broken.xml", options).unwrap();
+        zip.write_all(b"<PublicationDelivery>").unwrap();
+        zip.start_file("line.xml", options).unwrap();
+        zip.write_all(crate::fixtures::DOCUMENT.as_bytes()).unwrap();
+        zip.finish().unwrap();
+        let result = super::NetexData::from_zip_skipping_errors(
+            &path,
+            ".xml",
+            &super::ParseOptions::default(),
+        );
+        std::fs::remove_file(&path).unwrap();
+        let (data, errors) = result.unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].scheduled_stop_points.len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].document, "broken.xml");
+    }
+
+    #[test]
+    fn unselected_elements_are_not_parsed() {
+        // every hook follows one element parser, which scans the element's descendants
+        #[derive(Default)]
+        struct Parsed(usize);
+        impl super::NetexVisitor for Parsed {
+            fn on_stop(&mut self, _stop: super::ScheduledStopPoint) {
+                self.0 += 1;
+            }
+            fn on_journey_pattern(&mut self, _pattern: super::ServiceJourneyPattern) {
+                self.0 += 1;
+            }
+            fn on_journey(&mut self, _journey: super::ServiceJourney) {
+                self.0 += 1;
+            }
+            fn on_line(&mut self, _line: super::Line) {
+                self.0 += 1;
+            }
+            fn on_period(&mut self, _period: super::UicOperatingPeriod) {
+                self.0 += 1;
+            }
+            fn on_day_type_assignment(&mut self, _assignment: super::DayTypeAssignment) {
+                self.0 += 1;
+            }
+        }
+        let parsed = |elements| {
+            let xml = crate::fixtures::DOCUMENT;
+            let options = super::ParseOptions {
+                elements,
+                ..super::ParseOptions::default()
+            };
+            let mut visitor = Parsed::default();
+            super::NetexData::visit_xml(xml.as_bytes(), xml.len(), &options, &mut visitor, None)
+                .unwrap();
+            visitor.0
+        };
+        // three stops and one of each other element
+        assert_eq!(parsed(super::Elements::ALL), 8);
+        assert_eq!(parsed(super::Elements::STOPS), 3);
+        assert_eq!(parsed(super::Elements::PERIODS), 1);
+    }
+
+    #[test]
+    fn parse_selected_elements() {
+        let xml = crate::fixtures::DOCUMENT;
+        let options = super::ParseOptions {
+            elements: super::Elements::STOPS,
+            ..super::ParseOptions::default()
+        };
+        let data =
+            super::NetexData::from_xml_with_options(xml.as_bytes(), xml.len(), &options).unwrap();
+        assert_eq!(data.scheduled_stop_points.len(), 3);
+        assert!(data.service_journeys.is_empty());
+        assert!(data.service_journey_patterns.is_empty());
+        assert!(data.operating_periods.is_empty());
+        assert!(data.day_type_assignments.is_empty());
+        assert!(data.lines.is_empty());
+
+        let options = super::ParseOptions {
+            elements: super::Elements::JOURNEYS | super::Elements::PERIODS,
+            ..super::ParseOptions::default()
+        };
+        let data =
+            super::NetexData::from_xml_with_options(xml.as_bytes(), xml.len(), &options).unwrap();
+        assert!(data.scheduled_stop_points.is_empty());
+        assert_eq!(data.service_journeys.len(), 1);
+        assert_eq!(data.operating_periods.len(), 1);
+        assert!(data.day_type_assignments.is_empty());
+    }
+
+    #[test]
+    fn visit() {
+        #[derive(Default)]
+        struct Counter {
+            stops: usize,
+            journeys: usize,
+            periods: usize,
+        }
+        impl super::NetexVisitor for Counter {
+            fn on_stop(&mut self, _stop: super::ScheduledStopPoint) {
+                self.stops += 1;
+            }
+            fn on_journey(&mut self, _journey: super::ServiceJourney) {
+                self.journeys += 1;
+            }
+            fn on_period(&mut self, _period: super::UicOperatingPeriod) {
+                self.periods += 1;
+            }
+        }
+        let xml = crate::fixtures::document_with_trips(&["07", "08"]);
+        let mut counter = Counter::default();
+        super::NetexData::visit(xml.as_bytes(), xml.len(), &mut counter).unwrap();
+        assert_eq!(counter.stops, 3);
+        assert_eq!(counter.journeys, 2);
+        assert_eq!(counter.periods, 1);
+    }
+
+    #[test]
+    fn from_xml_lenient() {
+        let xml = r#"<PublicationDelivery>
+            <ScheduledStopPoint id="ssp:1">
+                <Name>Köln Hbf</Name>
+                <Longitude>6.958</Longitude>
+                <Latitude>50.943</Latitude>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:2">
+                <Name>Köln Messe/Deutz</Name>
+                <Longitude>six</Longitude>
+                <Latitude>50.940</Latitude>
+            </ScheduledStopPoint>
+        </PublicationDelivery>"#;
+        assert!(super::NetexData::from_xml(xml.as_bytes(), xml.len()).is_err());
+        let (data, errors) = super::NetexData::from_xml_lenient(xml.as_bytes(), xml.len());
+        assert_eq!(data.scheduled_stop_points.len(), 1);
+        assert_eq!(data.scheduled_stop_points[0].short_name, "Köln Hbf");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].element, "ScheduledStopPoint");
+        assert_eq!(errors[0].id.as_deref(), Some("ssp:2"));
+        assert!(errors[0]
+            .to_string()
+            .starts_with("ScheduledStopPoint ssp:2: "));
+    }
+
+    #[test]
+    fn from_xml_lenient_journeys_and_calendars() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "</PublicationDelivery>",
+            r#"<ServiceJourney id="sj:2"><dayTypes><DayTypeRef ref="dt:1"/></dayTypes></ServiceJourney>
+            <ServiceJourney id="sj:3">
+                <dayTypes><DayTypeRef ref="dt:1"/></dayTypes>
+                <ServiceJourneyPatternRef ref="sjp:1"/>
+                <passingTimes>
+                    <TimetabledPassingTime><DepartureTime>8</DepartureTime></TimetabledPassingTime>
+                </passingTimes>
+            </ServiceJourney>
+            <UicOperatingPeriod id="op:2"><FromDate>2022-13-01</FromDate></UicOperatingPeriod>
+            <OperatingDay id="od:1"><CalendarDate>22-6-1</CalendarDate></OperatingDay>
+            </PublicationDelivery>"#,
+        );
+        assert!(super::NetexData::from_xml(xml.as_bytes(), xml.len()).is_err());
+        let (data, errors) = super::NetexData::from_xml_lenient(xml.as_bytes(), xml.len());
+        assert_eq!(data.service_journeys.len(), 1);
+        assert_eq!(data.operating_periods.len(), 1);
+        assert!(data.operating_days.is_empty());
+        let failed: Vec<(&str, &str)> = errors
+            .iter()
+            .map(|err| (err.element.as_str(), err.id.as_deref().unwrap_or_default()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                ("ServiceJourney", "sj:2"),
+                ("ServiceJourney", "sj:3"),
+                ("UicOperatingPeriod", "op:2"),
+                ("OperatingDay", "od:1"),
+            ]
+        );
+        assert!(errors[0].message.contains("ServiceJourneyPatternRef"));
+    }
+
+    #[test]
+    fn parse_invalid_times_and_dates() {
+        assert!(super::NetexData::parse_minutes("").is_err());
+        assert!(super::NetexData::parse_minutes("8:6x").is_err());
+        assert!(super::NetexData::parse_minutes("08:60:00").is_err());
+        assert_eq!(super::NetexData::parse_minutes(" 8:06 ").unwrap(), 486);
+        assert!(super::NetexData::parse_date("2022").is_err());
+        assert!(super::NetexData::parse_date("2022-00-13").is_err());
+        assert!(super::NetexData::parse_date("2022-06-1x").is_err());
+        assert_eq!(
+            super::NetexData::parse_date(" 2022-06-13 ").unwrap(),
+            22 + (6 << 7) + (13 << 11)
+        );
+    }
+
+    #[test]
+    fn parse_day_offset() {
+        let xml = r#"<PublicationDelivery>
+            <ServiceJourney id="sj:1">
+                <dayTypes><DayTypeRef ref="dt:1"/></dayTypes>
+                <ServiceJourneyPatternRef ref="sjp:1"/>
+                <passingTimes>
+                    <TimetabledPassingTime>
+                        <DepartureTime>23:50:00</DepartureTime>
+                    </TimetabledPassingTime>
+                    <TimetabledPassingTime>
+                        <ArrivalTime>00:10:00</ArrivalTime>
+                        <ArrivalDayOffset>1</ArrivalDayOffset>
+                        <DepartureTime>00:12:00</DepartureTime>
+                        <DepartureDayOffset>1</DepartureDayOffset>
+                    </TimetabledPassingTime>
+                </passingTimes>
+            </ServiceJourney>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let times = &data.service_journeys[0].passing_times;
+        assert_eq!(times[0].departure.unwrap().minute_of_day(), 1430);
+        assert_eq!(times[1].arrival.unwrap().minute_of_day(), 1450);
+        assert_eq!(times[1].departure.unwrap().minute_of_day(), 1452);
+        assert_eq!(times[1].arrival.unwrap().to_string(), "00:10");
+
+        // offsets beyond 30 days are capped
+        let xml = xml.replace(
+            "<ArrivalDayOffset>1</ArrivalDayOffset>",
+            "<ArrivalDayOffset>1000</ArrivalDayOffset>",
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let arrival = data.service_journeys[0].passing_times[1].arrival.unwrap();
+        assert_eq!(arrival.minute_of_day(), 30 * 24 * 60 + 10);
+    }
+
+    #[test]
+    fn parse_notice() {
+        let xml = r#"<PublicationDelivery xmlns="http://www.netex.org.uk/netex">
+            <Notice id="n:1">
+                <Text xml:lang="de">Nur an Schultagen</Text>
+                <PublicCode>S</PublicCode>
+            </Notice>
+            <NoticeAssignment id="na:1">
+                <NoticeRef ref="n:1"/>
+                <NoticedObjectRef ref="sj:1"/>
+            </NoticeAssignment>
+            <ServiceJourney id="sj:2">
+                <TransportMode>bus</TransportMode>
+                <DayTypeRef ref="dt:1"/>
+                <ServiceJourneyPatternRef ref="sjp:1"/>
+                <passingTimes/>
+                <noticeAssignments>
+                    <NoticeAssignment id="na:2"><NoticeRef ref="n:1"/></NoticeAssignment>
+                </noticeAssignments>
+            </ServiceJourney>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.notices,
+            vec![super::Notice {
+                id: super::hash_id("n:1"),
+                text: "Nur an Schultagen".to_owned(),
+                lang: Some("de".to_owned()),
+                public_code: Some("S".to_owned()),
+            }]
+        );
+        let objects: Vec<u64> = data
+            .notice_assignments
+            .iter()
+            .map(|assignment| assignment.noticed_object)
+            .collect();
+        assert_eq!(
+            objects,
+            vec![super::hash_id("sj:1"), super::hash_id("sj:2")]
+        );
+        assert!(data
+            .notice_assignments
+            .iter()
+            .all(|assignment| assignment.notice == super::hash_id("n:1")));
+    }
+
+    #[test]
+    fn parse_projected_location() {
+        let xml = r#"<PublicationDelivery>
+            <ScheduledStopPoint id="ssp:1">
+                <Location srsName="EPSG:25832"><pos>356544.42 5645471.74</pos></Location>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:2">
+                <Location srsName="urn:ogc:def:crs:EPSG::32633">
+                    <pos>392082.81 5820158.15</pos>
+                </Location>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:3">
+                <Location><Longitude>6.958</Longitude><Latitude>50.943</Latitude></Location>
+            </ScheduledStopPoint>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let coords: Vec<(f32, f32)> = data
+            .scheduled_stop_points
+            .iter()
+            .map(|stop| (stop.lat, stop.long))
+            .collect();
+        for ((lat, long), (expected_lat, expected_long)) in
+            coords
+                .into_iter()
+                .zip([(50.943, 6.958), (52.520_833, 13.409_444), (50.943, 6.958)])
+        {
+            assert!((lat - expected_lat).abs() < 1e-5);
+            assert!((long - expected_long).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn prefer_wgs84_location() {
+        let xml = r#"<PublicationDelivery xmlns:gml="http://www.opengis.net/gml/3.2">
+            <ScheduledStopPoint id="ssp:1">
+                <Location>
+                    <Longitude>6.958</Longitude><Latitude>50.943</Latitude>
+                    <gml:pos srsName="EPSG:25832">356000.00 5645000.00</gml:pos>
+                </Location>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:2">
+                <Location srsName="EPSG:25832">
+                    <gml:pos>356000.00 5645000.00</gml:pos>
+                    <gml:pos srsName="EPSG:4326">50.943 6.958</gml:pos>
+                </Location>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:3">
+                <Location srsName="EPSG:25832">
+                    <gml:pos>356000.00 5645000.00</gml:pos>
+                    <Longitude>6.958</Longitude><Latitude>50.943</Latitude>
+                </Location>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:4">
+                <Location srsName="EPSG:25832">
+                    <Longitude>6.958</Longitude><Latitude>50.943</Latitude>
+                </Location>
+            </ScheduledStopPoint>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.scheduled_stop_points.len(), 4);
+        for stop in &data.scheduled_stop_points {
+            assert!((stop.lat - 50.943).abs() < 1e-5);
+            assert!((stop.long - 6.958).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn parse_type_of_place() {
+        let xml = r#"<PublicationDelivery>
+            <ScheduledStopPoint id="ssp:1"><StopType>onstreetBus</StopType></ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:2"><TypeOfPlaceRef ref="top:railStation"/></ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:3"/>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let types: Vec<_> = data
+            .scheduled_stop_points
+            .iter()
+            .map(|stop| stop.type_of_place)
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                Some(super::TypeOfPlace::OnStreet),
+                Some(super::TypeOfPlace::Station),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_stop_place_transfer_time() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="sp:1">
+                <TransferDuration><DefaultDuration>PT300S</DefaultDuration></TransferDuration>
+            </StopPlace>
+            <PassengerStopAssignment id="psa:1">
+                <ScheduledStopPointRef ref="ssp:1"/>
+                <StopPlaceRef ref="sp:1"/>
+            </PassengerStopAssignment>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.stop_places.len(), 1);
+        assert_eq!(data.stop_places[0].min_transfer_seconds, Some(300));
+        assert_eq!(
+            data.passenger_stop_assignments[0].stop_place,
+            data.stop_places[0].id
+        );
+    }
+
+    #[test]
+    fn parse_stop_place_type() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="sp:1"><StopPlaceType>railStation</StopPlaceType></StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.stop_places[0].stop_place_type.as_deref(),
+            Some("railStation")
+        );
+        assert_eq!(data.stop_places[0].weighting, None);
+    }
+
+    #[test]
+    fn parse_authority_parent() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ShortName>KVB</ShortName>",
+            r#"<ShortName>KVB</ShortName><ParentOrganisationRef ref="auth:0"/>"#,
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.authorities[0].parent, Some(super::hash_id("auth:0")));
+        // a plain OrganisationRef points at a related organisation, not the parent
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ShortName>KVB</ShortName>",
+            r#"<ShortName>KVB</ShortName><parts><OrganisationPart id="part:1"><OrganisationRef ref="auth:2"/></OrganisationPart></parts>"#,
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.authorities[0].parent, None);
+    }
+
+    #[test]
+    fn parse_stop_place_parent() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="hub:1"><Name>Köln Hbf</Name></StopPlace>
+            <StopPlace id="sp:1"><ParentSiteRef ref="hub:1"/></StopPlace>
+            <StopPlace id="hub:2">
+                <children><StopPlace id="sp:2"/></children>
+            </StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let parents: Vec<Option<u64>> = data.stop_places.iter().map(|p| p.parent).collect();
+        assert_eq!(
+            parents,
+            vec![
+                None,
+                Some(super::hash_id("hub:1")),
+                None,
+                Some(super::hash_id("hub:2"))
+            ]
+        );
+        assert_eq!(data.stop_places[0].name.as_deref(), Some("Köln Hbf"));
+    }
+
+    #[test]
+    fn parse_stop_place_weighting() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="sp:1">
+                <Weighting>preferredInterchange</Weighting>
+            </StopPlace>
+            <StopPlace id="sp:2">
+                <Weighting>somethingElse</Weighting>
+            </StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.stop_places[0].weighting,
+            Some(super::InterchangeWeighting::PreferredInterchange)
+        );
+        assert_eq!(data.stop_places[1].weighting, None);
+    }
+
+    #[test]
+    fn parse_stop_place_ignores_nested_values() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="hub:1">
+                <quays><Quay id="q:1">
+                    <Weighting>preferredInterchange</Weighting>
+                    <TransferDuration><DefaultDuration>PT60S</DefaultDuration></TransferDuration>
+                </Quay></quays>
+                <children><StopPlace id="sp:1">
+                    <Weighting>noInterchange</Weighting>
+                    <TransferDuration><DefaultDuration>PT600S</DefaultDuration></TransferDuration>
+                </StopPlace></children>
+            </StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.stop_places[0].weighting, None);
+        assert_eq!(data.stop_places[0].min_transfer_seconds, None);
+        assert_eq!(
+            data.stop_places[1].weighting,
+            Some(super::InterchangeWeighting::NoInterchange)
+        );
+        assert_eq!(data.stop_places[1].min_transfer_seconds, Some(600));
+    }
+
+    #[test]
+    fn parse_stop_place_compass_bearing() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="sp:1">
+                <quays><Quay id="q:1"><CompassBearing>270</CompassBearing></Quay></quays>
+            </StopPlace>
+            <StopPlace id="sp:2">
+                <quays><Quay id="q:2"><CompassBearing>400</CompassBearing></Quay></quays>
+            </StopPlace>
+            <StopPlace id="sp:3">
+                <quays><Quay id="q:3"><CompassBearing>360</CompassBearing></Quay></quays>
+            </StopPlace>
+            <StopPlace id="sp:4">
+                <children>
+                    <StopPlace id="sp:5">
+                        <quays><Quay id="q:5"><CompassBearing>90</CompassBearing></Quay></quays>
+                    </StopPlace>
+                </children>
+            </StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let bearings: Vec<Option<f32>> = data
+            .stop_places
+            .iter()
+            .map(|place| place.compass_bearing)
+            .collect();
+        assert_eq!(
+            bearings,
+            vec![Some(270.0), None, Some(0.0), None, Some(90.0)]
+        );
+    }
+
+    #[test]
+    fn parse_stop_place_accessibility() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="sp:1">
+                <AccessibilityAssessment>
+                    <limitations>
+                        <AccessibilityLimitation>
+                            <StepFreeAccess>true</StepFreeAccess>
+                            <AudibleSignalsAvailable>false</AudibleSignalsAvailable>
+                            <VisualSignsAvailable>partial</VisualSignsAvailable>
+                        </AccessibilityLimitation>
+                    </limitations>
+                </AccessibilityAssessment>
+            </StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.stop_places[0].accessibility,
+            super::AccessibilityLimitation {
+                wheelchair_access: super::LimitationStatus::Unknown,
+                step_free_access: super::LimitationStatus::True,
+                audible_signals_available: super::LimitationStatus::False,
+                visual_signs_available: super::LimitationStatus::Partial,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_quay_accessibility() {
+        let xml = r#"<PublicationDelivery>
+            <StopPlace id="sp:1">
+                <AccessibilityAssessment><limitations><AccessibilityLimitation>
+                    <StepFreeAccess>true</StepFreeAccess>
+                </AccessibilityLimitation></limitations></AccessibilityAssessment>
+                <quays>
+                    <Quay id="q:4"><PublicCode>4</PublicCode></Quay>
+                    <Quay id="q:5">
+                        <PublicCode>5</PublicCode>
+                        <AccessibilityAssessment><limitations><AccessibilityLimitation>
+                            <StepFreeAccess>false</StepFreeAccess>
+                        </AccessibilityLimitation></limitations></AccessibilityAssessment>
+                    </Quay>
+                </quays>
+            </StopPlace>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let place = &data.stop_places[0];
+        assert_eq!(
+            place.accessibility.step_free_access,
+            super::LimitationStatus::True
+        );
+        assert_eq!(
+            place.quays[0].accessibility.step_free_access,
+            super::LimitationStatus::Unknown
+        );
+        assert_eq!(
+            place.quays[1].accessibility.step_free_access,
+            super::LimitationStatus::False
+        );
+    }
+
+    #[test]
+    fn parse_stop_name_preference() {
+        let xml = r#"<PublicationDelivery>
+            <ScheduledStopPoint id="ssp:1">
+                <ShortName>Köln Hbf</ShortName>
+                <Name>Köln Hauptbahnhof</Name>
+            </ScheduledStopPoint>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.scheduled_stop_points[0].short_name, "Köln Hbf");
+        let options = super::ParseOptions {
+            name_preference: vec!["Name".to_owned(), "ShortName".to_owned()],
+            ..super::ParseOptions::default()
+        };
+        let data =
+            super::NetexData::from_xml_with_options(xml.as_bytes(), xml.len(), &options).unwrap();
+        assert_eq!(
+            data.scheduled_stop_points[0].short_name,
+            "Köln Hauptbahnhof"
+        );
+    }
+
+    #[test]
+    fn resolve_chain() {
+        let data = crate::fixtures::data();
+        let resolved = data.resolve_chain();
+        assert_eq!(resolved.len(), 1);
+        let chain = &resolved[0];
+        assert!(chain.errors.is_empty());
+        assert_eq!(chain.pattern.unwrap().id, super::hash_id("sjp:1"));
+        assert!(chain.route.is_none());
+        assert_eq!(chain.line.unwrap().short_name, "S1");
+        let periods: Vec<u64> = chain.periods.iter().map(|period| period.id).collect();
+        assert_eq!(periods, vec![super::hash_id("op:1")]);
+    }
+
+    #[test]
+    fn resolve_chain_missing_pattern() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            r#"<ServiceJourneyPatternRef ref="sjp:1"/>"#,
+            r#"<ServiceJourneyPatternRef ref="sjp:2"/>"#,
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let resolved = data.resolve_chain();
+        let chain = &resolved[0];
+        assert!(chain.pattern.is_none());
+        assert!(chain.line.is_none());
+        assert_eq!(chain.periods.len(), 1);
+        assert_eq!(
+            chain.errors,
+            vec![super::ChainError::MissingPattern(super::hash_id("sjp:2"))]
+        );
+    }
+
+    #[test]
+    fn resolve_chain_dated_journey() {
+        // the day type is never assigned, the journey runs on a single date instead
+        let xml = crate::fixtures::DOCUMENT
+            .replace(
+                r#"<dayTypes><DayTypeRef ref="dt:1"/></dayTypes>"#,
+                r#"<dayTypes><DayTypeRef ref="dt:2"/></dayTypes>"#,
+            )
+            .replace(
+                "</PublicationDelivery>",
+                r#"<OperatingDay id="od:1"><CalendarDate>2022-06-25</CalendarDate></OperatingDay>
+                <DatedServiceJourney id="dsj:1">
+                    <ServiceJourneyRef ref="sj:1"/>
+                    <OperatingDayRef ref="od:1"/>
+                </DatedServiceJourney>
+                </PublicationDelivery>"#,
+            );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let resolved = data.resolve_chain();
+        let chain = &resolved[0];
+        assert!(chain.errors.is_empty());
+        assert!(chain.periods.is_empty());
+        let days: Vec<u64> = chain.operating_days.iter().map(|day| day.id).collect();
+        assert_eq!(days, vec![super::hash_id("od:1")]);
+
+        let xml = xml.replace(
+            "<OperatingDayRef",
+            "<ServiceAlteration>cancellation</ServiceAlteration><OperatingDayRef",
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.resolve_chain()[0].errors,
+            vec![super::ChainError::MissingCalendar(super::hash_id("dt:2"))]
+        );
+    }
+
+    #[test]
+    fn validate_stop_order() {
+        let xml = r#"<PublicationDelivery>
+            <RoutePoint id="rp:a"><projections><PointProjection><ProjectToPointRef ref="ssp:a"/></PointProjection></projections></RoutePoint>
+            <RoutePoint id="rp:b"><projections><PointProjection><ProjectToPointRef ref="ssp:b"/></PointProjection></projections></RoutePoint>
+            <RoutePoint id="rp:c"><projections><PointProjection><ProjectToPointRef ref="ssp:c"/></PointProjection></projections></RoutePoint>
+            <Route id="r:1">
+                <pointsInSequence>
+                    <PointOnRoute order="1"><RoutePointRef ref="rp:a"/></PointOnRoute>
+                    <PointOnRoute order="2"><RoutePointRef ref="rp:b"/></PointOnRoute>
+                    <PointOnRoute order="3"><RoutePointRef ref="rp:c"/></PointOnRoute>
+                </pointsInSequence>
+            </Route>
+            <ServiceJourneyPattern id="sjp:ok">
+                <RouteRef ref="r:1"/>
+                <pointsInSequence>
+                    <StopPointInJourneyPattern id="sp:1"><ScheduledStopPointRef ref="ssp:a"/></StopPointInJourneyPattern>
+                    <StopPointInJourneyPattern id="sp:2"><ScheduledStopPointRef ref="ssp:b"/></StopPointInJourneyPattern>
+                    <StopPointInJourneyPattern id="sp:3"><ScheduledStopPointRef ref="ssp:c"/></StopPointInJourneyPattern>
+                </pointsInSequence>
+            </ServiceJourneyPattern>
+            <ServiceJourneyPattern id="sjp:swapped">
+                <RouteRef ref="r:1"/>
+                <pointsInSequence>
+                    <StopPointInJourneyPattern id="sp:4"><ScheduledStopPointRef ref="ssp:a"/></StopPointInJourneyPattern>
+                    <StopPointInJourneyPattern id="sp:5"><ScheduledStopPointRef ref="ssp:c"/></StopPointInJourneyPattern>
+                    <StopPointInJourneyPattern id="sp:6"><ScheduledStopPointRef ref="ssp:b"/></StopPointInJourneyPattern>
+                </pointsInSequence>
+            </ServiceJourneyPattern>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let discrepancies = data.validate_stop_order();
+        assert_eq!(
+            discrepancies,
+            vec![super::StopOrderDiscrepancy {
+                pattern: super::hash_id("sjp:swapped"),
+                route: super::hash_id("r:1"),
+                scheduled_stop_point: super::hash_id("ssp:b"),
+            }]
+        );
+    }
+
+    #[test]
+    fn from_paths_source() {
+        let dir = std::env::temp_dir().join(format!("netex-parse-paths-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = [dir.join("a.xml"), dir.join("b.xml")];
+        for (idx, path) in paths.iter().enumerate() {
+            let xml = format!(
+                r#"<PublicationDelivery>
+                    <ScheduledStopPoint id="ssp:{idx}"><Name>Stop {idx}</Name></ScheduledStopPoint>
+                </PublicationDelivery>"#
+            );
+            std::fs::write(path, xml).unwrap();
+        }
+        let data = super::NetexData::from_paths(&paths).unwrap();
+        let options = super::ParseOptions {
+            id_seed: 1,
+            ..super::ParseOptions::default()
+        };
+        let seeded = super::NetexData::from_paths_with_options(&paths, &options).unwrap();
+        let missing = super::NetexData::from_paths(&[dir.join("c.xml")])
+            .err()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            seeded[0].scheduled_stop_points[0].id,
+            super::hash_id_with_seed("ssp:0", 1)
+        );
+        let missing = missing.downcast::<super::DocumentError>().unwrap();
+        assert!(missing.document.ends_with("c.xml"));
+        assert!(missing.source.downcast_ref::<std::io::Error>().is_some());
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].scheduled_stop_points[0].short_name, "Stop 0");
+        assert_eq!(data[0].scheduled_stop_points[0].source, Some(0));
+        assert_eq!(data[1].scheduled_stop_points[0].source, Some(1));
+    }
+
+    #[test]
+    fn codespace() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<PublicationDelivery>",
+            r#"<PublicationDelivery>
+            <codespaces>
+                <Codespace id="de">
+                    <Xmlns>DE</Xmlns>
+                    <XmlnsUrl>http://www.delfi.de/</XmlnsUrl>
+                </Codespace>
+            </codespaces>"#,
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.codespace(), Some("DE"));
+        assert_eq!(
+            data.codespaces[0].url.as_deref(),
+            Some("http://www.delfi.de/")
+        );
+        let plain = data.scheduled_stop_points[0].id;
+        assert_eq!(plain, super::hash_id("ssp:a"));
+
+        let options = super::ParseOptions {
+            codespace_ids: true,
+            ..super::ParseOptions::default()
+        };
+        let tagged =
+            super::NetexData::from_xml_with_options(xml.as_bytes(), xml.len(), &options).unwrap();
+        let seed = super::hash_id("DE");
+        assert_eq!(
+            tagged.scheduled_stop_points[0].id,
+            super::hash_id_with_seed("ssp:a", seed)
+        );
+        assert_ne!(tagged.scheduled_stop_points[0].id, plain);
+        // every entry point seeds the same way
+        let document = roxmltree::Document::parse(&xml).unwrap();
+        let tagged_document =
+            super::NetexData::from_document_with_options(&document, &options).unwrap();
+        assert_eq!(
+            tagged_document.scheduled_stop_points[0].id,
+            tagged.scheduled_stop_points[0].id
+        );
+        let stops = super::parse_stops(&document, &options).unwrap();
+        assert_eq!(stops[0].id, tagged.scheduled_stop_points[0].id);
+        let journeys = super::parse_journeys(&document, &options).unwrap();
+        assert_eq!(journeys[0].id, tagged.service_journeys[0].id);
+    }
+
+    #[test]
+    fn merge_prefers_newer() {
+        let feed = |published: &str, name: &str| {
+            let xml = format!(
+                r#"<PublicationDelivery>
+                    <PublicationTimestamp>{published}</PublicationTimestamp>
+                    <ScheduledStopPoint id="ssp:1"><Name>{name}</Name></ScheduledStopPoint>
+                    <ScheduledStopPoint id="ssp:{name}"><Name>{name}</Name></ScheduledStopPoint>
+                </PublicationDelivery>"#
+            );
+            super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap()
+        };
+        let older = feed("2022-05-01T00:00:00", "old");
+        assert_eq!(older.published.as_deref(), Some("2022-05-01T00:00:00"));
+        let newer = feed("2022-06-01T00:00:00", "new");
+        let merged = super::NetexData::merge(vec![older, newer]);
+        assert_eq!(merged.published.as_deref(), Some("2022-06-01T00:00:00"));
+        let names: Vec<&str> = merged
+            .scheduled_stop_points
+            .iter()
+            .map(|stop| stop.short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["new", "new", "old"]);
+        assert_eq!(merged.scheduled_stop_points[0].id, super::hash_id("ssp:1"));
+
+        // 10:00 UTC is older than 11:00 UTC, although it sorts after it as text
+        let older = feed("2022-06-01T12:00:00+02:00", "old");
+        let newer = feed("2022-06-01T11:00:00Z", "new");
+        let merged = super::NetexData::merge(vec![older, newer]);
+        assert_eq!(merged.published.as_deref(), Some("2022-06-01T11:00:00Z"));
+        assert_eq!(merged.scheduled_stop_points[0].short_name, "new");
+    }
+
+    #[test]
+    fn merge_conflicting_assignments() {
+        let feed = |published: &str, period: &str| {
+            let xml = format!(
+                r#"<PublicationDelivery>
+                    <PublicationTimestamp>{published}</PublicationTimestamp>
+                    <DayTypeAssignment id="dta:{period}">
+                        <OperatingPeriodRef ref="{period}"/><DayTypeRef ref="dt:1"/>
+                    </DayTypeAssignment>
+                    <DayTypeAssignment id="dta:{period}:2">
+                        <OperatingPeriodRef ref="{period}:2"/><DayTypeRef ref="dt:1"/>
+                    </DayTypeAssignment>
+                    <NoticeAssignment id="na:1">
+                        <NoticeRef ref="n:1"/><NoticedObjectRef ref="sj:1"/>
+                    </NoticeAssignment>
+                </PublicationDelivery>"#
+            );
+            super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap()
+        };
+        let older = feed("2022-05-01T00:00:00", "op:old");
+        let newer = feed("2022-06-01T00:00:00", "op:new");
+        let merged = super::NetexData::merge(vec![older, newer]);
+        // both assignments of the newer feed remain, none of the older one
+        let periods: Vec<u64> = merged
+            .day_type_assignments
+            .iter()
+            .map(|assignment| assignment.operating_period)
+            .collect();
+        assert_eq!(
+            periods,
+            vec![super::hash_id("op:new"), super::hash_id("op:new:2")]
+        );
+        assert_eq!(merged.notice_assignments.len(), 1);
+    }
+
+    #[test]
+    fn parse_timestamp() {
+        let utc = super::NetexData::parse_timestamp("2022-06-01T10:00:00Z").unwrap();
+        assert_eq!(utc % 86400, 10 * 3600);
+        for local in [
+            "2022-06-01T12:00:00+02:00",
+            "2022-06-01T12:00:00.250+0200",
+            "2022-06-01T05:30:00-04:30",
+            "2022-06-01T10:00:00",
+            "2022-06-01T10:00",
+        ] {
+            assert_eq!(
+                super::NetexData::parse_timestamp(local),
+                Some(utc),
+                "{local}"
+            );
+        }
+        assert_eq!(super::NetexData::parse_timestamp("2022-06-01"), None);
+        assert_eq!(super::NetexData::parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn from_paths_merged() {
+        let dir = std::env::temp_dir().join(format!("netex-parse-merged-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = [dir.join("new.xml"), dir.join("old.xml")];
+        for (path, (published, name)) in paths.iter().zip([
+            ("2022-06-01T00:00:00", "new"),
+            ("2022-05-01T00:00:00", "old"),
+        ]) {
+            let xml = format!(
+                r#"<PublicationDelivery>
+                    <PublicationTimestamp>{published}</PublicationTimestamp>
+                    <ScheduledStopPoint id="ssp:1"><Name>{name}</Name></ScheduledStopPoint>
+                </PublicationDelivery>"#
+            );
+            std::fs::write(path, xml).unwrap();
+        }
+        let merged =
+            super::NetexData::from_paths_merged(&paths, &super::ParseOptions::default()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(merged.scheduled_stop_points.len(), 1);
+        assert_eq!(merged.scheduled_stop_points[0].short_name, "new");
+    }
+
+    #[test]
+    fn line_contrast_ratio() {
+        let xml = r#"<PublicationDelivery>
+            <Line id="line:1">
+                <Presentation><Colour>FFFF00</Colour><TextColour>000000</TextColour></Presentation>
+            </Line>
+            <Line id="line:2"><Presentation><Colour>FFFF00</Colour></Presentation></Line>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.lines[0].color.as_deref(), Some("FFFF00"));
+        let ratio = data.lines[0].contrast_ratio().unwrap();
+        assert!((ratio - 19.56).abs() < 0.01);
+        assert_eq!(data.lines[1].contrast_ratio(), None);
+    }
+
+    #[test]
+    fn journey_terminals() {
+        let mut data = crate::fixtures::data();
+        data.service_journeys[0].passing_times.reverse();
+        let journey = &data.service_journeys[0];
+        assert_eq!(journey.terminals(&data), Some(("Köln Hbf", "Köln-Mülheim")));
+        let empty = super::NetexData::default();
+        assert_eq!(journey.terminals(&empty), None);
+    }
+
+    #[test]
+    fn synthesize_passing_times() {
+        let xml = r#"<PublicationDelivery>
+            <ServiceJourneyPattern id="sjp:1">
+                <pointsInSequence>
+                    <StopPointInJourneyPattern id="sp:1"/>
+                    <StopPointInJourneyPattern id="sp:2"/>
+                    <StopPointInJourneyPattern id="sp:3"/>
+                </pointsInSequence>
+                <runTimes>
+                    <JourneyPatternRunTime id="rt:1"><RunTime>PT5M</RunTime></JourneyPatternRunTime>
+                    <JourneyPatternRunTime id="rt:2"><RunTime>PT6M</RunTime></JourneyPatternRunTime>
+                </runTimes>
+                <waitTimes>
+                    <JourneyPatternWaitTime id="wt:1">
+                        <StopPointInJourneyPatternRef ref="sp:2"/><WaitTime>PT1M</WaitTime>
+                    </JourneyPatternWaitTime>
+                </waitTimes>
+            </ServiceJourneyPattern>
+            <ServiceJourney id="sj:1">
+                <DepartureTime>08:00:00</DepartureTime>
+                <TransportMode>rail</TransportMode>
+                <dayTypes><DayTypeRef ref="dt:1"/></dayTypes>
+                <ServiceJourneyPatternRef ref="sjp:1"/>
+            </ServiceJourney>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let times: Vec<(u64, u16, u16)> = data.service_journeys[0]
+            .passing_times
+            .iter()
+            .map(|time| {
+                (
+                    time.stop_point_in_journey_pattern,
+                    time.arrival.unwrap().minute_of_day(),
+                    time.departure.unwrap().minute_of_day(),
+                )
+            })
+            .collect();
+        let stop = super::hash_id;
+        assert_eq!(
+            times,
+            vec![
+                (stop("sp:1"), 480, 480),
+                (stop("sp:2"), 485, 486),
+                (stop("sp:3"), 492, 492)
+            ]
+        );
+    }
+
+    // departure and arrival minutes of the synthesized passing times
+    fn passing_minutes(journey: &super::ServiceJourney) -> Vec<(u16, u16)> {
+        journey
+            .passing_times
+            .iter()
+            .map(|time| {
+                (
+                    time.arrival.unwrap().minute_of_day(),
+                    time.departure.unwrap().minute_of_day(),
+                )
+            })
+            .collect()
+    }
+
+    const TIMED_PATTERN: &str = r#"<ServiceJourneyPattern id="sjp:1">
+            <pointsInSequence>
+                <StopPointInJourneyPattern id="sp:1"><ScheduledStopPointRef ref="ssp:a"/></StopPointInJourneyPattern>
+                <StopPointInJourneyPattern id="sp:2"><ScheduledStopPointRef ref="ssp:b"/></StopPointInJourneyPattern>
+                <StopPointInJourneyPattern id="sp:3"><ScheduledStopPointRef ref="ssp:c"/></StopPointInJourneyPattern>
+            </pointsInSequence>
+            <runTimes>
+                <JourneyPatternRunTime id="rt:2"><TimingLinkRef ref="tl:bc"/><RunTime>PT6M</RunTime></JourneyPatternRunTime>
+                <JourneyPatternRunTime id="rt:1"><TimingLinkRef ref="tl:ab"/><RunTime>PT5M</RunTime></JourneyPatternRunTime>
+            </runTimes>
+        </ServiceJourneyPattern>
+        <TimingLink id="tl:ab"><FromPointRef ref="ssp:a"/><ToPointRef ref="ssp:b"/></TimingLink>
+        <TimingLink id="tl:bc"><FromPointRef ref="ssp:b"/><ToPointRef ref="ssp:c"/></TimingLink>"#;
+
+    const UNTIMED_JOURNEY: &str = r#"<ServiceJourney id="sj:1">
+            <DepartureTime>08:00:00</DepartureTime>
+            <dayTypes><DayTypeRef ref="dt:1"/></dayTypes>
+            <ServiceJourneyPatternRef ref="sjp:1"/>
+        </ServiceJourney>"#;
+
+    #[test]
+    fn synthesize_passing_times_by_timing_link() {
+        #[derive(Default)]
+        struct Journeys(Vec<super::ServiceJourney>);
+        impl super::NetexVisitor for Journeys {
+            fn on_journey(&mut self, journey: super::ServiceJourney) {
+                self.0.push(journey);
+            }
+        }
+        // run times are listed out of order and placed by their timing links
+        let xml =
+            format!("<PublicationDelivery>{UNTIMED_JOURNEY}{TIMED_PATTERN}</PublicationDelivery>");
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.timing_links.len(), 2);
+        assert_eq!(
+            passing_minutes(&data.service_journeys[0]),
+            vec![(480, 480), (485, 485), (491, 491)]
+        );
+
+        // visitors receive the synthesized times as well
+        let mut journeys = Journeys::default();
+        super::NetexData::visit(xml.as_bytes(), xml.len(), &mut journeys).unwrap();
+        assert_eq!(
+            passing_minutes(&journeys.0[0]),
+            passing_minutes(&data.service_journeys[0])
+        );
+    }
+
+    #[test]
+    fn synthesize_passing_times_across_documents() {
+        let dir = std::env::temp_dir().join(format!("netex-parse-shared-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths = [dir.join("shared.xml"), dir.join("line.xml")];
+        for (path, content) in paths.iter().zip([TIMED_PATTERN, UNTIMED_JOURNEY]) {
+            std::fs::write(
+                path,
+                format!("<PublicationDelivery>{content}</PublicationDelivery>"),
+            )
+            .unwrap();
+        }
+        let data = super::NetexData::from_paths(&paths).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            passing_minutes(&data[1].service_journeys[0]),
+            vec![(480, 480), (485, 485), (491, 491)]
+        );
+    }
+
+    #[test]
+    fn synthesize_passing_times_overflow() {
+        let xml = format!(
+            "<PublicationDelivery>{UNTIMED_JOURNEY}{}</PublicationDelivery>",
+            TIMED_PATTERN
+                .replace("PT5M", "PT1000H")
+                .replace("PT6M", "PT1000H")
+        );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert!(data.service_journeys[0].passing_times.is_empty());
+    }
+
+    #[test]
+    fn parse_stop_order() {
+        let xml = crate::fixtures::DOCUMENT
+            .replace(r#"id="sp:1">"#, r#"id="sp:1" order="1">"#)
+            .replace(r#"id="sp:2">"#, r#"id="sp:2" order="2">"#)
+            .replace(r#"id="sp:3">"#, r#"id="sp:3" order="4">"#);
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let orders: Vec<Option<u32>> = data.service_journey_patterns[0]
+            .stops
+            .iter()
+            .map(|stop| stop.order)
+            .collect();
+        assert_eq!(orders, vec![Some(1), Some(2), Some(4)]);
+        let data = crate::fixtures::data();
+        assert_eq!(data.service_journey_patterns[0].stops[0].order, None);
+    }
+
+    #[test]
+    fn parse_timing_points() {
+        let xml = crate::fixtures::DOCUMENT
+            .replace(
+                r#"<StopPointInJourneyPattern id="sp:1">"#,
+                r#"<StopPointInJourneyPattern id="sp:1"><TimingPointStatus>timingPoint</TimingPointStatus>"#,
+            )
+            .replace(
+                r#"<StopPointInJourneyPattern id="sp:2">"#,
+                r#"<StopPointInJourneyPattern id="sp:2"><TimingPointStatus>notTimingPoint</TimingPointStatus>"#,
+            )
+            .replace(
+                r#"<StopPointInJourneyPattern id="sp:3">"#,
+                r#"<StopPointInJourneyPattern id="sp:3"><TimingPointRef ref="tp:3"/>"#,
+            );
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let timing: Vec<bool> = data.service_journey_patterns[0]
+            .stops
+            .iter()
+            .map(|stop| stop.is_timing_point)
+            .collect();
+        assert_eq!(timing, vec![true, false, true]);
+        let times = &data.service_journeys[0].passing_times;
+        assert!(times[0].at_timing_point(&data));
+        assert!(!times[1].at_timing_point(&data));
+        let data = crate::fixtures::data();
+        assert!(!data.service_journeys[0].passing_times[0].at_timing_point(&data));
+    }
+
+    #[test]
+    fn parse_service_journey_pattern_name() {
+        let xml = r#"<PublicationDelivery>
+            <ServiceJourneyPattern id="sjp:1">
+                <Name>Köln Hbf - Bonn Hbf</Name>
+                <pointsInSequence>
+                    <StopPointInJourneyPattern id="sp:1"><Name>Köln Hbf</Name></StopPointInJourneyPattern>
+                </pointsInSequence>
+            </ServiceJourneyPattern>
+            <ServiceJourneyPattern id="sjp:2"/>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let patterns = &data.service_journey_patterns;
+        assert_eq!(patterns[0].name.as_deref(), Some("Köln Hbf - Bonn Hbf"));
+        assert_eq!(patterns[1].name, None);
+    }
+
+    #[test]
+    fn hash_id_seeds() {
+        assert_eq!(
+            super::hash_id("ssp:1"),
+            super::hash_id_with_seed("ssp:1", 0)
+        );
+        assert_ne!(
+            super::hash_id_with_seed("ssp:1", 0),
+            super::hash_id_with_seed("ssp:1", 1)
+        );
+        // a deliberately weak hash to surface a collision
+        let ids = ["ssp:1", "ssp:2", "line:1", "ssp:1"];
+        let collisions = super::collisions_by(ids.into_iter(), |id| id.len() as u64);
+        assert_eq!(collisions, vec![("ssp:1".to_owned(), "ssp:2".to_owned())]);
+        let xml =
+            r#"<PublicationDelivery><Line id="line:1"/><Line id="line:2"/></PublicationDelivery>"#;
+        assert!(super::find_id_collisions(xml.as_bytes(), xml.len(), 7)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn day_types_for_period() {
+        let xml = r#"<PublicationDelivery>
+            <DayTypeAssignment id="dta:1">
+                <OperatingPeriodRef ref="op:1"/><DayTypeRef ref="dt:weekday"/>
+            </DayTypeAssignment>
+            <DayTypeAssignment id="dta:2">
+                <OperatingPeriodRef ref="op:1"/><DayTypeRef ref="dt:schoolday"/>
+            </DayTypeAssignment>
+            <DayTypeAssignment id="dta:3">
+                <OperatingPeriodRef ref="op:2"/><DayTypeRef ref="dt:sunday"/>
+            </DayTypeAssignment>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let result = data.day_types_for_period(super::hash_id("op:1"));
+        assert_eq!(result, vec!["dt:weekday", "dt:schoolday"]);
+        assert!(data.day_types_for_period(super::hash_id("op:3")).is_empty());
+        let index = data.period_day_types();
+        for period in ["op:1", "op:2"] {
+            let period = super::hash_id(period);
+            assert_eq!(data.day_types_for_period(period), index[&period]);
+        }
+    }
+
+    #[test]
+    fn day_type_name() {
+        let xml = r#"<PublicationDelivery>
+            <DayType id="dt:schoolday">
+                <Name>Schooldays</Name>
+                <properties><PropertyOfDay><Name>ignored</Name></PropertyOfDay></properties>
+            </DayType>
+            <DayType id="dt:unnamed"/>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.day_type_name(super::hash_id("dt:schoolday")),
+            Some("Schooldays")
+        );
+        assert_eq!(data.day_type_name(super::hash_id("dt:unnamed")), None);
+        assert_eq!(data.day_type_name(super::hash_id("dt:missing")), None);
+    }
+
+    #[test]
+    fn keep_raw_times() {
+        let xml = crate::fixtures::DOCUMENT.replace("08:05:00", "08:05:30");
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(data.service_journeys[0].passing_times[1].arrival_raw, None);
+        let options = super::ParseOptions {
+            keep_raw_times: true,
+            ..super::ParseOptions::default()
+        };
+        let data =
+            super::NetexData::from_xml_with_options(xml.as_bytes(), xml.len(), &options).unwrap();
+        let time = &data.service_journeys[0].passing_times[1];
+        assert_eq!(time.arrival.unwrap().minute_of_day(), 485);
+        assert_eq!(time.arrival_raw.as_deref(), Some("08:05:30"));
+        assert_eq!(time.departure_raw.as_deref(), Some("08:06:00"));
+    }
+
+    #[test]
+    fn subtract_operating_period() {
+        let mut period = super::UicOperatingPeriod {
+            from: super::NetexData::parse_date("2022-06-13T00:00:00").unwrap(),
+            valid_day_bits: super::NetexData::parse_day_bits("1111111"),
+            ..super::UicOperatingPeriod::default()
+        };
+        let excluded = super::UicOperatingPeriod {
+            from: super::NetexData::parse_date("2022-06-15T00:00:00").unwrap(),
+            valid_day_bits: super::NetexData::parse_day_bits("101"),
+            ..super::UicOperatingPeriod::default()
+        };
+        period.subtract(&excluded);
+        assert_eq!(period.valid_day_bits, vec![0b0110_1011]);
+    }
+
+    #[test]
+    fn parse_operating_period_dates() {
+        let xml = r#"<PublicationDelivery>
+            <UicOperatingPeriod id="op:1">
+                <IncludedDays>
+                    <Date>2022-06-15</Date>
+                    <Date>2022-06-13</Date>
+                    <Date>2022-06-20</Date>
+                </IncludedDays>
+            </UicOperatingPeriod>
+            <OperatingPeriod id="op:2">
+                <FromDate>2022-06-13T00:00:00</FromDate>
+                <ToDate>2022-06-15T00:00:00</ToDate>
+            </OperatingPeriod>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let period = &data.operating_periods[0];
+        assert_eq!(
+            period.from,
+            super::NetexData::parse_date("2022-06-13").unwrap()
+        );
+        assert_eq!(
+            period.to,
+            super::NetexData::parse_date("2022-06-20").unwrap()
+        );
+        assert_eq!(
+            period.valid_day_bits,
+            super::NetexData::parse_day_bits("10100001")
+        );
+        assert_eq!(
+            data.operating_periods[1].valid_day_bits,
+            super::NetexData::parse_day_bits("111")
+        );
+    }
+
+    #[test]
+    fn set_operating_period_day() {
+        let mut period = super::UicOperatingPeriod {
+            from: super::NetexData::parse_date("2022-06-13T00:00:00").unwrap(),
+            to: super::NetexData::parse_date("2022-06-21T00:00:00").unwrap(),
+            valid_day_bits: super::NetexData::parse_day_bits("1111100"),
+            ..super::UicOperatingPeriod::default()
+        };
+        period.set_day(super::NetexData::parse_date("2022-06-14").unwrap(), false);
+        period.set_day(super::NetexData::parse_date("2022-06-21").unwrap(), true);
+        period.set_day(super::NetexData::parse_date("2022-06-23").unwrap(), false);
+        assert_eq!(period.valid_day_bits, vec![0b0001_1101, 0b1]);
+        // valid days outside of the period widen it
+        period.set_day(super::NetexData::parse_date("2022-06-22").unwrap(), true);
+        assert_eq!(period.valid_day_bits, vec![0b0001_1101, 0b11]);
+        assert_eq!(
+            period.to,
+            super::NetexData::parse_date("2022-06-22").unwrap()
+        );
+        period.set_day(super::NetexData::parse_date("2022-06-11").unwrap(), true);
+        assert_eq!(
+            period.from,
+            super::NetexData::parse_date("2022-06-11").unwrap()
+        );
+        assert_eq!(period.valid_day_bits, vec![0b0111_0101, 0b1100, 0]);
+    }
+
+    #[test]
+    fn parse_dated_service_journey() {
+        let xml = r#"<PublicationDelivery>
+            <OperatingDay id="od:1"><CalendarDate>2022-06-14</CalendarDate></OperatingDay>
+            <DatedServiceJourney id="dsj:1">
+                <ServiceJourneyRef ref="sj:1"/>
+                <OperatingDayRef ref="od:1"/>
+                <ServiceAlteration>cancellation</ServiceAlteration>
+            </DatedServiceJourney>
+        </PublicationDelivery>"#;
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        assert_eq!(
+            data.operating_days[0].date,
+            super::NetexData::parse_date("2022-06-14").unwrap()
+        );
+        let dated = &data.dated_service_journeys[0];
+        assert_eq!(dated.service_journey, super::hash_id("sj:1"));
+        assert_eq!(dated.operating_day, super::hash_id("od:1"));
+        assert!(dated.cancelled);
+    }
+
+    #[test]
+    fn coordinate_precision() {
+        let xml = r#"<PublicationDelivery>
+            <ScheduledStopPoint id="ssp:1">
+                <Longitude>6.958001</Longitude><Latitude>50.943004</Latitude>
+            </ScheduledStopPoint>
+            <ScheduledStopPoint id="ssp:2">
+                <Longitude>6.958004</Longitude><Latitude>50.943001</Latitude>
+            </ScheduledStopPoint>
+        </PublicationDelivery>"#;
+        let options = super::ParseOptions {
+            coordinate_precision: Some(4),
+            ..super::ParseOptions::default()
+        };
+        let data =
+            super::NetexData::from_xml_with_options(xml.as_bytes(), xml.len(), &options).unwrap();
+        let stops = &data.scheduled_stop_points;
+        assert_eq!((stops[0].long, stops[0].lat), (stops[1].long, stops[1].lat));
+        assert!((stops[0].long - 6.958).abs() < f32::EPSILON);
+        let data = super::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let stops = &data.scheduled_stop_points;
+        assert!((stops[0].long - stops[1].long).abs() > f32::EPSILON);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn from_async_reader() {
+        let xml = crate::fixtures::DOCUMENT;
+        let data = super::NetexData::from_async_reader(xml.as_bytes(), xml.len())
+            .await
+            .unwrap();
+        assert_eq!(data.scheduled_stop_points.len(), 3);
+        assert_eq!(data.service_journeys.len(), 1);
+    }
 }
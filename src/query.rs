@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::graph::{normalize_name_lowercase, Graph};
+use crate::timetable::{Departure, DepartureIndex, PathResult};
+
+// Read-only view of a graph with the indices repeated queries need, built once.
+// The graph itself stays plain data and is handed back by into_graph.
+#[derive(Debug)]
+pub struct GraphQuery {
+    graph: Graph,
+    // node indices by lowercase normalized name
+    by_name: HashMap<String, Vec<usize>>,
+    // destination nodes per node, see Graph::to_adjacency_list
+    adjacency: Vec<Vec<usize>>,
+    // edge indices leaving every node
+    outgoing: Vec<Vec<usize>>,
+    departures: DepartureIndex,
+}
+
+impl GraphQuery {
+    pub fn new(graph: Graph) -> Self {
+        let mut by_name = HashMap::<String, Vec<usize>>::new();
+        for (idx, node) in graph.nodes.iter().enumerate() {
+            by_name
+                .entry(normalize_name_lowercase(&node.short_name))
+                .or_default()
+                .push(idx);
+        }
+        GraphQuery {
+            by_name,
+            adjacency: graph.to_adjacency_list(),
+            outgoing: graph.outgoing_edges(),
+            departures: graph.build_departure_index(),
+            graph,
+        }
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> Graph {
+        self.graph
+    }
+
+    // Nodes named name, ignoring case and surplus whitespace.
+    pub fn nodes_named(&self, name: &str) -> &[usize] {
+        self.by_name
+            .get(&normalize_name_lowercase(name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    // Nodes reachable from node over a single edge, ascending.
+    pub fn neighbors(&self, node: usize) -> &[usize] {
+        self.adjacency.get(node).map_or(&[], Vec::as_slice)
+    }
+
+    // See DepartureIndex::next_departures
+    pub fn next_departures(&self, node: usize, after: u16, limit: usize) -> &[Departure] {
+        self.departures.next_departures(node, after, limit)
+    }
+
+    // Earliest arrival connection from one node to another, leaving at or after
+    // depart_after on the packed date day, see Graph::shortest_path_over.
+    pub fn shortest_path(
+        &self,
+        from: usize,
+        to: usize,
+        depart_after: u16,
+        day: u16,
+    ) -> Option<PathResult> {
+        self.graph
+            .shortest_path_over(&self.outgoing, from, to, depart_after, day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GraphQuery;
+
+    #[test]
+    #[allow(deprecated)]
+    fn graph_query() {
+        let query = GraphQuery::new(crate::fixtures::graph_with_trips(&["07", "09"]));
+        let hbf = query.nodes_named("köln  HBF");
+        assert_eq!(hbf.len(), 1);
+        let hbf = hbf[0];
+        assert_eq!(query.graph().nodes[hbf].short_name, "Köln Hbf");
+        assert!(query.nodes_named("Bonn Hbf").is_empty());
+        let deutz = query.nodes_named("Köln Messe/Deutz")[0];
+        assert_eq!(query.neighbors(hbf), &[deutz]);
+
+        let departures: Vec<u16> = query
+            .next_departures(hbf, 421, 5)
+            .iter()
+            .map(|departure| departure.departure)
+            .collect();
+        assert_eq!(departures, vec![540]);
+
+        // monday 2022-06-13
+        let monday = 22 + (6 << 7) + (13 << 11);
+        let muelheim = query.nodes_named("Köln-Mülheim")[0];
+        let path = query.shortest_path(hbf, muelheim, 421, monday).unwrap();
+        assert_eq!(
+            Some(path),
+            query.graph().shortest_path(hbf, muelheim, 421, monday)
+        );
+        assert_eq!(query.into_graph().nodes.len(), 3);
+    }
+}
@@ -0,0 +1,445 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use crate::graph::{format_date, unpack_date, Edge, Graph};
+
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct NetworkReport {
+    pub nodes: usize,
+    pub edges: usize,
+    pub journeys: usize,
+    pub first_day: Option<String>,
+    pub last_day: Option<String>,
+    pub lines: usize,
+    pub operators: usize,
+    // journey count per transport mode
+    pub modes: BTreeMap<String, usize>,
+    pub speed_outliers: usize,
+}
+
+// source nodes sampled by hub_scores, graphs up to this size are scored exactly
+pub const DEFAULT_HUB_SAMPLES: usize = 1000;
+
+#[derive(Debug, Default, PartialEq, serde::Serialize)]
+pub struct ModeStats {
+    pub journeys: usize,
+    // summed length of the edges served by the mode
+    pub length_m: f64,
+}
+
+impl Graph {
+    pub fn stats_per_mode(&self) -> HashMap<String, ModeStats> {
+        let mut stats = HashMap::<String, ModeStats>::new();
+        for edge in &self.edges {
+            let length = self.edge_length(edge);
+            let mut modes = HashSet::<&str>::new();
+            for journey in &edge.timetable.journeys {
+                let entry = stats.entry(journey.transport_mode.clone()).or_default();
+                entry.journeys += 1;
+                if modes.insert(&journey.transport_mode) {
+                    entry.length_m += length;
+                }
+            }
+        }
+        stats
+    }
+
+    // (min_lat, min_long, max_lat, max_long) of the nodes, optionally skipping
+    // nodes left at 0,0 by stops without coordinates
+    pub fn bounding_box(&self, ignore_default: bool) -> Option<(f32, f32, f32, f32)> {
+        self.nodes
+            .iter()
+            .filter(|node| !ignore_default || node.lat != 0.0 || node.long != 0.0)
+            .fold(None, |bounds, node| {
+                let (min_lat, min_long, max_lat, max_long) =
+                    bounds.unwrap_or((node.lat, node.long, node.lat, node.long));
+                Some((
+                    min_lat.min(node.lat),
+                    min_long.min(node.long),
+                    max_lat.max(node.lat),
+                    max_long.max(node.long),
+                ))
+            })
+    }
+
+    // busiest edges first, ties ordered by their nodes
+    pub fn edges_by_frequency(&self) -> Vec<&Edge> {
+        let mut edges: Vec<&Edge> = self.edges.iter().collect();
+        edges.sort_by_key(|edge| {
+            (
+                std::cmp::Reverse(edge.timetable.journeys.len()),
+                edge.start_node,
+                edge.end_node,
+            )
+        });
+        edges
+    }
+
+    // Number of edges by how many distinct periods their timetable holds.
+    // Periods are distinct by their days, so duplicates from before compaction show up.
+    pub fn period_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        for edge in &self.edges {
+            let distinct: HashSet<_> = edge.timetable.periods.iter().collect();
+            *histogram.entry(distinct.len()).or_default() += 1;
+        }
+        histogram
+    }
+
+    // Scheduled kilometers of all journeys operating on the given packed date.
+    pub fn service_km(&self, day: u16) -> f64 {
+        let mut meters = 0.0;
+        for edge in &self.edges {
+            let periods = &edge.timetable.periods;
+            let active: Vec<bool> = periods.iter().map(|p| p.is_valid_on(day)).collect();
+            let journeys = edge
+                .timetable
+                .journeys
+                .iter()
+                .filter(|journey| active.get(journey.operating_period) == Some(&true))
+                .count();
+            if journeys > 0 {
+                meters +=
+                    self.edge_length(edge) * f64::from(u32::try_from(journeys).unwrap_or(u32::MAX));
+            }
+        }
+        meters / 1000.0
+    }
+
+    pub fn hub_scores(&self) -> Vec<f64> {
+        self.hub_scores_sampled(DEFAULT_HUB_SAMPLES)
+    }
+
+    // Betweenness centrality over the directed topology, normalized so the
+    // highest scoring node has 1. Shortest paths count hops and are only
+    // followed from up to samples evenly spaced source nodes.
+    pub fn hub_scores_sampled(&self, samples: usize) -> Vec<f64> {
+        let count = self.nodes.len();
+        let mut successors = vec![Vec::<usize>::new(); count];
+        for edge in &self.edges {
+            if !successors[edge.start_node].contains(&edge.end_node) {
+                successors[edge.start_node].push(edge.end_node);
+            }
+        }
+        let mut scores = vec![0.0; count];
+        let step = count.div_ceil(samples.max(1)).max(1);
+        for source in (0..count).step_by(step) {
+            // brandes: count shortest paths forward, accumulate dependencies backward
+            let mut order = Vec::<usize>::with_capacity(count);
+            let mut predecessors = vec![Vec::<usize>::new(); count];
+            let mut paths = vec![0.0_f64; count];
+            let mut distance = vec![usize::MAX; count];
+            paths[source] = 1.0;
+            distance[source] = 0;
+            let mut queue = VecDeque::from([source]);
+            while let Some(idx) = queue.pop_front() {
+                order.push(idx);
+                for &next in &successors[idx] {
+                    if distance[next] == usize::MAX {
+                        distance[next] = distance[idx] + 1;
+                        queue.push_back(next);
+                    }
+                    if distance[next] == distance[idx] + 1 {
+                        paths[next] += paths[idx];
+                        predecessors[next].push(idx);
+                    }
+                }
+            }
+            let mut dependency = vec![0.0_f64; count];
+            for &idx in order.iter().rev() {
+                for &prev in &predecessors[idx] {
+                    dependency[prev] += paths[prev] / paths[idx] * (1.0 + dependency[idx]);
+                }
+                if idx != source {
+                    scores[idx] += dependency[idx];
+                }
+            }
+        }
+        let max = scores.iter().copied().fold(0.0_f64, f64::max);
+        if max > 0.0 {
+            for score in &mut scores {
+                *score /= max;
+            }
+        }
+        scores
+    }
+
+    pub fn report(&self) -> NetworkReport {
+        let mut lines = HashSet::<&str>::new();
+        let mut operators = HashSet::<&str>::new();
+        let mut modes = BTreeMap::<String, usize>::new();
+        let mut journeys = 0;
+        for journey in self.edges.iter().flat_map(|e| e.timetable.journeys.iter()) {
+            journeys += 1;
+            lines.insert(&journey.line);
+            operators.insert(&journey.controller);
+            *modes.entry(journey.transport_mode.clone()).or_default() += 1;
+        }
+        let periods = self.edges.iter().flat_map(|e| e.timetable.periods.iter());
+        let first_day = periods
+            .clone()
+            .map(|p| p.from)
+            .min_by_key(|d| unpack_date(*d));
+        let last_day = periods.map(|p| p.to).max_by_key(|d| unpack_date(*d));
+        NetworkReport {
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            journeys,
+            first_day: first_day.map(format_date),
+            last_day: last_day.map(format_date),
+            lines: lines.len(),
+            operators: operators.len(),
+            modes,
+            speed_outliers: self.speed_outliers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::{DayMinute, Edge, Graph, Journey, Node, OperatingPeriod, Timetable};
+
+    fn journey(line: &str, controller: &str, mode: &str) -> Journey {
+        Journey {
+            departure: DayMinute(600),
+            arrival: DayMinute(610),
+            transport_mode: mode.to_owned(),
+            operating_period: 0,
+            line: line.to_owned(),
+            controller: controller.to_owned(),
+            ..Journey::default()
+        }
+    }
+
+    #[test]
+    fn report() {
+        let periods = vec![
+            OperatingPeriod {
+                // 2022-06-13 to 2022-12-10
+                from: 22 + (6 << 7) + (13 << 11),
+                to: 22 + (12 << 7) + (10 << 11),
+                ..OperatingPeriod::default()
+            },
+            OperatingPeriod {
+                // 2022-01-02 to 2023-01-01
+                from: 22 + (1 << 7) + (2 << 11),
+                to: 23 + (1 << 7) + (1 << 11),
+                ..OperatingPeriod::default()
+            },
+        ];
+        let graph = Graph {
+            nodes: vec![Node::default(), Node::default()],
+            edges: vec![Edge {
+                start_node: 0,
+                end_node: 1,
+                timetable: Timetable {
+                    journeys: vec![
+                        journey("S1", "KVB", "tram"),
+                        journey("S1", "KVB", "tram"),
+                        journey("RE5", "DB", "rail"),
+                    ],
+                    periods,
+                },
+                walk_seconds: u16::MAX,
+            }],
+            speed_outliers: 4,
+            ..Graph::default()
+        };
+        let report = graph.report();
+        assert_eq!(report.nodes, 2);
+        assert_eq!(report.edges, 1);
+        assert_eq!(report.journeys, 3);
+        assert_eq!(report.first_day.as_deref(), Some("2022-01-02"));
+        assert_eq!(report.last_day.as_deref(), Some("2023-01-01"));
+        assert_eq!(report.lines, 2);
+        assert_eq!(report.operators, 2);
+        assert_eq!(report.modes["tram"], 2);
+        assert_eq!(report.modes["rail"], 1);
+        assert_eq!(report.speed_outliers, 4);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["journeys"], 3);
+    }
+
+    #[test]
+    fn period_histogram() {
+        let period = |bits: &[u8]| OperatingPeriod {
+            valid_day: bits.to_vec(),
+            ..OperatingPeriod::default()
+        };
+        let edge = |periods: Vec<OperatingPeriod>| Edge {
+            start_node: 0,
+            end_node: 1,
+            timetable: Timetable {
+                journeys: Vec::new(),
+                periods,
+            },
+            walk_seconds: u16::MAX,
+        };
+        let graph = Graph {
+            nodes: vec![Node::default(); 2],
+            edges: vec![
+                edge(vec![period(&[1])]),
+                edge(vec![period(&[1]), period(&[2]), period(&[4])]),
+                // the repeated period counts once
+                edge(vec![period(&[1]), period(&[2]), period(&[4]), period(&[2])]),
+                edge(vec![period(&[3])]),
+            ],
+            ..Graph::default()
+        };
+        let histogram = graph.period_histogram();
+        assert_eq!(histogram, [(1, 2), (3, 2)].into());
+    }
+
+    #[test]
+    fn stats_per_mode() {
+        let edge = |start_node, end_node, journeys| Edge {
+            start_node,
+            end_node,
+            timetable: Timetable {
+                journeys,
+                periods: vec![OperatingPeriod::default()],
+            },
+            walk_seconds: u16::MAX,
+        };
+        let node = |lat| Node {
+            long: 6.95,
+            lat,
+            ..Node::default()
+        };
+        let graph = Graph {
+            // about 1112 m between consecutive nodes
+            nodes: vec![node(50.93), node(50.94), node(50.95)],
+            edges: vec![
+                edge(
+                    0,
+                    1,
+                    vec![
+                        journey("132", "KVB", "bus"),
+                        journey("132", "KVB", "bus"),
+                        journey("RE5", "DB", "rail"),
+                    ],
+                ),
+                edge(1, 2, vec![journey("RE5", "DB", "rail")]),
+            ],
+            ..Graph::default()
+        };
+        let stats = graph.stats_per_mode();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats["bus"].journeys, 2);
+        assert_eq!(stats["rail"].journeys, 2);
+        assert!((stats["bus"].length_m - 1112.0).abs() < 2.0);
+        assert!((stats["rail"].length_m - 2224.0).abs() < 4.0);
+    }
+
+    #[test]
+    fn service_km() {
+        let monday = 22 + (6 << 7) + (13 << 11);
+        let period = |valid_day| OperatingPeriod {
+            from: monday,
+            to: monday,
+            valid_day,
+            ..OperatingPeriod::default()
+        };
+        let mut cancelled = journey("132", "KVB", "bus");
+        cancelled.operating_period = 1;
+        let node = |lat| Node {
+            long: 6.95,
+            lat,
+            ..Node::default()
+        };
+        let graph = Graph {
+            // about 1112 m apart
+            nodes: vec![node(50.93), node(50.94)],
+            edges: vec![Edge {
+                start_node: 0,
+                end_node: 1,
+                timetable: Timetable {
+                    journeys: vec![
+                        journey("132", "KVB", "bus"),
+                        journey("132", "KVB", "bus"),
+                        cancelled,
+                    ],
+                    periods: vec![period(vec![1]), period(vec![0])],
+                },
+                walk_seconds: u16::MAX,
+            }],
+            ..Graph::default()
+        };
+        assert!((graph.service_km(monday) - 2.224).abs() < 0.004);
+        assert!(graph.service_km(monday + (1 << 11)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn hub_scores() {
+        let edge = |start_node, end_node| Edge {
+            start_node,
+            end_node,
+            timetable: Timetable::default(),
+            walk_seconds: u16::MAX,
+        };
+        // a star around node 2 with a leaf carrying on to node 5
+        let mut edges = Vec::new();
+        for leaf in [0, 1, 3, 4] {
+            edges.push(edge(leaf, 2));
+            edges.push(edge(2, leaf));
+        }
+        edges.push(edge(4, 5));
+        edges.push(edge(5, 4));
+        let graph = Graph {
+            nodes: vec![Node::default(); 6],
+            edges,
+            ..Graph::default()
+        };
+        let scores = graph.hub_scores();
+        assert!((scores[2] - 1.0).abs() < f64::EPSILON);
+        assert!(scores[4] > scores[0] && scores[4] < scores[2]);
+        assert!(scores[0].abs() < f64::EPSILON);
+        assert!(scores[5].abs() < f64::EPSILON);
+        // sampling a subset of sources still ranks the center first
+        let sampled = graph.hub_scores_sampled(3);
+        let best = (0..6).max_by(|a, b| sampled[*a].total_cmp(&sampled[*b]));
+        assert_eq!(best, Some(2));
+    }
+
+    #[test]
+    fn edges_by_frequency() {
+        let edge = |start_node, end_node, count| Edge {
+            start_node,
+            end_node,
+            timetable: Timetable {
+                journeys: vec![journey("132", "KVB", "bus"); count],
+                periods: vec![OperatingPeriod::default()],
+            },
+            walk_seconds: u16::MAX,
+        };
+        let graph = Graph {
+            nodes: vec![Node::default(); 3],
+            edges: vec![edge(2, 1, 1), edge(1, 2, 3), edge(1, 0, 1), edge(0, 1, 2)],
+            ..Graph::default()
+        };
+        let order: Vec<(usize, usize)> = graph
+            .edges_by_frequency()
+            .iter()
+            .map(|edge| (edge.start_node, edge.end_node))
+            .collect();
+        assert_eq!(order, vec![(1, 2), (0, 1), (1, 0), (2, 1)]);
+    }
+
+    #[test]
+    fn bounding_box() {
+        let node = |long, lat| Node {
+            long,
+            lat,
+            ..Node::default()
+        };
+        let mut graph = Graph {
+            nodes: vec![node(6.958, 50.943), node(7.006, 50.963), node(6.975, 50.94)],
+            ..Graph::default()
+        };
+        let expected = Some((50.94, 6.958, 50.963, 7.006));
+        assert_eq!(graph.bounding_box(false), expected);
+        graph.nodes.push(Node::default());
+        assert_eq!(graph.bounding_box(true), expected);
+        assert_eq!(graph.bounding_box(false), Some((0.0, 0.0, 50.963, 7.006)));
+        assert_eq!(Graph::default().bounding_box(false), None);
+    }
+}
@@ -0,0 +1,391 @@
+use std::io::Write;
+
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use crate::graph::{Edge, Graph, Journey, Node, OperatingPeriod, Timetable};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SerializeOptions {
+    // full field names instead of the single letter keys
+    pub verbose: bool,
+    // journey times as second of day instead of minute of day, for consumers
+    // expecting seconds. The graph keeps whole minutes, so the values are
+    // always multiples of 60 and carry no extra precision.
+    pub seconds: bool,
+}
+
+// Topology of a graph without timetables, for routing-only consumers.
+// Node i leads to the nodes in adjacency[i].
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct AdjacencyList {
+    pub node_ids: Vec<u64>,
+    pub adjacency: Vec<Vec<usize>>,
+}
+
+// Serializes graph types with the keys selected by options.
+// Compact keys match the derived implementations.
+pub struct Serialized<'a, T> {
+    pub value: &'a T,
+    pub options: SerializeOptions,
+}
+
+impl<'a, T> Serialized<'a, T> {
+    pub fn new(value: &'a T, options: SerializeOptions) -> Self {
+        Serialized { value, options }
+    }
+
+    fn wrap<U>(&self, value: &'a U) -> Serialized<'a, U> {
+        Serialized::new(value, self.options)
+    }
+
+    fn key(&self, compact: &'static str, verbose: &'static str) -> &'static str {
+        if self.options.verbose {
+            verbose
+        } else {
+            compact
+        }
+    }
+}
+
+impl<T> Serialize for Serialized<'_, Vec<T>>
+where
+    for<'b> Serialized<'b, T>: Serialize,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.value.iter().map(|item| self.wrap(item)))
+    }
+}
+
+impl Serialize for Serialized<'_, Graph> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Graph", 5)?;
+        state.serialize_field(self.key("n", "nodes"), &self.wrap(&self.value.nodes))?;
+        state.serialize_field(self.key("e", "edges"), &self.wrap(&self.value.edges))?;
+        // journeys reference contacts, authority chains and notices by index
+        state.serialize_field(self.key("k", "contacts"), &self.value.contacts)?;
+        state.serialize_field(
+            self.key("h", "authority_chains"),
+            &self.value.authority_chains,
+        )?;
+        state.serialize_field(self.key("x", "notices"), &self.value.notices)?;
+        state.end()
+    }
+}
+
+impl Serialize for Serialized<'_, Node> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let node = self.value;
+        let mut state = serializer.serialize_struct("Node", 3)?;
+        // large u64 do not survive JSON.parse, so ids are strings
+        state.serialize_field(self.key("i", "id"), &node.id.to_string())?;
+        state.serialize_field(self.key("n", "short_name"), &node.short_name)?;
+        state.serialize_field(self.key("c", "coords"), &[node.long, node.lat])?;
+        state.end()
+    }
+}
+
+impl Serialize for Serialized<'_, Edge> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let edge = self.value;
+        let mut state = serializer.serialize_struct("Edge", 4)?;
+        state.serialize_field(self.key("s", "start_node"), &edge.start_node)?;
+        state.serialize_field(self.key("e", "end_node"), &edge.end_node)?;
+        state.serialize_field(self.key("w", "walk_seconds"), &edge.walk_seconds)?;
+        state.serialize_field(self.key("t", "timetable"), &self.wrap(&edge.timetable))?;
+        state.end()
+    }
+}
+
+impl Serialize for Serialized<'_, Timetable> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Timetable", 2)?;
+        state.serialize_field(self.key("j", "journeys"), &self.wrap(&self.value.journeys))?;
+        state.serialize_field(self.key("p", "periods"), &self.wrap(&self.value.periods))?;
+        state.end()
+    }
+}
+
+impl Serialize for Serialized<'_, Journey> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let journey = self.value;
+        let mut state = serializer.serialize_struct("Journey", 15)?;
+        if self.options.seconds {
+            let seconds = |time: crate::graph::DayMinute| u32::from(time.minute_of_day()) * 60;
+            state.serialize_field(self.key("d", "departure"), &seconds(journey.departure))?;
+            state.serialize_field(self.key("a", "arrival"), &seconds(journey.arrival))?;
+        } else {
+            state.serialize_field(self.key("d", "departure"), &journey.departure)?;
+            state.serialize_field(self.key("a", "arrival"), &journey.arrival)?;
+        }
+        state.serialize_field(self.key("t", "transport_mode"), &journey.transport_mode)?;
+        state.serialize_field(self.key("o", "operating_period"), &journey.operating_period)?;
+        state.serialize_field(self.key("l", "line"), &journey.line)?;
+        state.serialize_field(self.key("c", "controller"), &journey.controller)?;
+        if journey.controller_contact.is_some() {
+            state.serialize_field(
+                self.key("k", "controller_contact"),
+                &journey.controller_contact,
+            )?;
+        }
+        if journey.pattern_name.is_some() {
+            state.serialize_field(self.key("n", "pattern_name"), &journey.pattern_name)?;
+        }
+        if journey.authority_chain.is_some() {
+            state.serialize_field(self.key("h", "authority_chain"), &journey.authority_chain)?;
+        }
+        if journey.monitored {
+            state.serialize_field(self.key("m", "monitored"), &journey.monitored)?;
+        }
+        if journey.notices.is_some() {
+            state.serialize_field(self.key("x", "notices"), &journey.notices)?;
+        }
+        if journey.public_code.is_some() {
+            state.serialize_field(self.key("p", "public_code"), &journey.public_code)?;
+        }
+        if journey.platform.is_some() {
+            state.serialize_field(self.key("q", "platform"), &journey.platform)?;
+        }
+        if journey.network.is_some() {
+            state.serialize_field(self.key("g", "network"), &journey.network)?;
+        }
+        if journey.url.is_some() {
+            state.serialize_field(self.key("u", "url"), &journey.url)?;
+        }
+        state.end()
+    }
+}
+
+impl Serialize for Serialized<'_, OperatingPeriod> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let period = self.value;
+        let mut state = serializer.serialize_struct("OperatingPeriod", 4)?;
+        state.serialize_field(self.key("f", "from"), &period.from)?;
+        state.serialize_field(self.key("t", "to"), &period.to)?;
+        state.serialize_field(self.key("v", "valid_day_bits"), &period.valid_day_bits)?;
+        // the derived format never abbreviated it
+        state.serialize_field(self.key("valid_day", "valid_day"), &period.valid_day)?;
+        state.end()
+    }
+}
+
+impl Graph {
+    // Destination node indices per node, ascending and without duplicates.
+    pub fn to_adjacency_list(&self) -> Vec<Vec<usize>> {
+        let mut adjacency = vec![Vec::<usize>::new(); self.nodes.len()];
+        for edge in &self.edges {
+            adjacency[edge.start_node].push(edge.end_node);
+        }
+        for targets in &mut adjacency {
+            targets.sort_unstable();
+            targets.dedup();
+        }
+        adjacency
+    }
+
+    pub fn adjacency_list(&self) -> AdjacencyList {
+        AdjacencyList {
+            node_ids: self.nodes.iter().map(|node| node.id).collect(),
+            adjacency: self.to_adjacency_list(),
+        }
+    }
+
+    // Writes index, name and coordinates of every node as csv.
+    pub fn export_nodes_csv(&self, mut w: impl Write) -> std::io::Result<()> {
+        writeln!(w, "index,short_name,lat,long")?;
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let name = crate::gtfs::csv_field(&node.short_name);
+            writeln!(w, "{idx},{name},{},{}", node.lat, node.long)?;
+        }
+        Ok(())
+    }
+
+    // Compact json of nodes and edges as a value callers can modify before writing.
+    pub fn to_json_value(&self) -> serde_json::Value {
+        serde_json::to_value(Serialized::new(self, SerializeOptions::default()))
+            .expect("failed to serialize json")
+    }
+
+    // Writes one compact json edge per line, so consumers can stream them.
+    pub fn write_jsonl(&self, mut w: impl Write) -> std::io::Result<()> {
+        for edge in &self.edges {
+            serde_json::to_writer(&mut w, &Serialized::new(edge, SerializeOptions::default()))?;
+            w.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SerializeOptions, Serialized};
+    use crate::graph::{DayMinute, Journey, OperatingPeriod, Timetable};
+
+    // every field set, so that no key is skipped
+    fn full_timetable() -> Timetable {
+        let journey = Journey {
+            departure: DayMinute(480),
+            arrival: DayMinute(485),
+            transport_mode: "rail".to_owned(),
+            line: "S1".to_owned(),
+            controller: "KVB".to_owned(),
+            controller_contact: Some(0),
+            pattern_name: Some("Hbf - Mülheim".to_owned()),
+            authority_chain: Some(0),
+            monitored: true,
+            notices: Some(0),
+            public_code: Some("S1".to_owned()),
+            platform: Some("3".to_owned()),
+            network: Some("S-Bahn".to_owned()),
+            url: Some("https://kvb.koeln".to_owned()),
+            ..Journey::default()
+        };
+        Timetable {
+            journeys: vec![journey, Journey::default()],
+            periods: vec![OperatingPeriod {
+                valid_day_bits: "fw==".to_owned(),
+                valid_day: vec![127],
+                ..OperatingPeriod::default()
+            }],
+        }
+    }
+
+    #[test]
+    fn compact_matches_derive() {
+        let timetable = full_timetable();
+        let compact =
+            serde_json::to_value(Serialized::new(&timetable, SerializeOptions::default())).unwrap();
+        assert_eq!(compact, serde_json::to_value(&timetable).unwrap());
+        // verbose output names the same fields
+        let verbose = serde_json::to_value(Serialized::new(
+            &timetable,
+            SerializeOptions {
+                verbose: true,
+                ..SerializeOptions::default()
+            },
+        ))
+        .unwrap();
+        let len = |value: &serde_json::Value| value.as_object().unwrap().len();
+        for idx in 0..timetable.journeys.len() {
+            assert_eq!(len(&verbose["journeys"][idx]), len(&compact["j"][idx]));
+        }
+        assert_eq!(len(&verbose["periods"][0]), len(&compact["p"][0]));
+    }
+
+    #[test]
+    fn key_styles() {
+        let graph = crate::fixtures::graph();
+        let timetable = &graph.edges[0].timetable;
+        let compact =
+            serde_json::to_value(Serialized::new(timetable, SerializeOptions::default())).unwrap();
+        assert_eq!(compact, serde_json::to_value(timetable).unwrap());
+        assert_eq!(compact["j"][0]["l"], "S1");
+
+        let verbose = serde_json::to_value(Serialized::new(
+            timetable,
+            SerializeOptions {
+                verbose: true,
+                ..SerializeOptions::default()
+            },
+        ))
+        .unwrap();
+        let journey = &verbose["journeys"][0];
+        assert_eq!(journey["line"], "S1");
+        assert_eq!(journey["departure"], compact["j"][0]["d"]);
+        assert_eq!(journey["pattern_name"], "Hbf - Mülheim");
+        assert!(journey.get("d").is_none());
+        assert_eq!(verbose["periods"][0]["from"], compact["p"][0]["f"]);
+    }
+
+    #[test]
+    fn seconds() {
+        let graph = crate::fixtures::graph();
+        let journey = &graph.edges[0].timetable.journeys[0];
+        let options = SerializeOptions {
+            seconds: true,
+            ..SerializeOptions::default()
+        };
+        let value = serde_json::to_value(Serialized::new(journey, options)).unwrap();
+        assert_eq!(
+            value["d"],
+            u32::from(journey.departure.minute_of_day()) * 60
+        );
+        assert_eq!(value["a"], u32::from(journey.arrival.minute_of_day()) * 60);
+        let value = serde_json::to_value(Serialized::new(journey, SerializeOptions::default()));
+        assert_eq!(value.unwrap()["d"], journey.departure.minute_of_day());
+    }
+
+    #[test]
+    fn to_json_value() {
+        let graph = crate::fixtures::graph();
+        let mut value = graph.to_json_value();
+        let object = value.as_object().unwrap();
+        assert_eq!(object.len(), 5);
+        assert_eq!(object["n"].as_array().unwrap().len(), graph.nodes.len());
+        assert_eq!(object["e"].as_array().unwrap().len(), graph.edges.len());
+        assert_eq!(object["n"][0]["i"], graph.nodes[0].id.to_string());
+        assert_eq!(object["n"][0]["c"][1], graph.nodes[0].lat);
+        assert_eq!(object["k"], serde_json::json!([]));
+        assert_eq!(object["h"], serde_json::json!([]));
+        assert_eq!(object["x"], serde_json::json!([]));
+        assert_eq!(
+            object["e"][0]["t"],
+            serde_json::to_value(&graph.edges[0].timetable).unwrap()
+        );
+        value["source"] = "fixture".into();
+        assert_eq!(value["source"], "fixture");
+    }
+
+    #[test]
+    fn write_jsonl() {
+        let graph = crate::fixtures::graph();
+        let mut out = Vec::<u8>::new();
+        graph.write_jsonl(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), graph.edges.len());
+        for (line, edge) in text.lines().zip(&graph.edges) {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["s"], edge.start_node);
+            assert_eq!(value["e"], edge.end_node);
+            assert_eq!(value["t"], serde_json::to_value(&edge.timetable).unwrap());
+        }
+    }
+
+    #[test]
+    fn to_adjacency_list() {
+        let graph = crate::fixtures::graph();
+        let hbf = graph
+            .nodes
+            .iter()
+            .position(|node| node.short_name == "Köln Hbf")
+            .unwrap();
+        let deutz = graph
+            .nodes
+            .iter()
+            .position(|node| node.short_name == "Köln Messe/Deutz")
+            .unwrap();
+        let mut expected = vec![Vec::<usize>::new(); 3];
+        expected[hbf] = vec![deutz];
+        expected[deutz] = vec![3 - hbf - deutz];
+        assert_eq!(graph.to_adjacency_list(), expected);
+        let value = serde_json::to_value(graph.adjacency_list()).unwrap();
+        assert_eq!(value["adjacency"][hbf][0], deutz);
+        assert_eq!(value["node_ids"][hbf], graph.nodes[hbf].id);
+    }
+
+    #[test]
+    fn export_nodes_csv() {
+        let mut graph = crate::fixtures::graph();
+        graph.nodes[0].short_name = "Köln, \"Hbf\"".to_owned();
+        let mut out = Vec::<u8>::new();
+        graph.export_nodes_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "index,short_name,lat,long");
+        assert_eq!(lines.len(), graph.nodes.len() + 1);
+        let node = &graph.nodes[0];
+        assert_eq!(
+            lines[1],
+            format!("0,\"Köln, \"\"Hbf\"\"\",{},{}", node.lat, node.long)
+        );
+    }
+}
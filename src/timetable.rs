@@ -0,0 +1,508 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+
+use crate::graph::{Edge, Graph, Journey, Weekday};
+
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct PatternTimetable {
+    pub pattern_name: Option<String>,
+    // node indices in travel order
+    pub stops: Vec<usize>,
+    // per trip the departure at every stop except the last, where it is the arrival
+    pub trips: Vec<Vec<u16>>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct LineTimetable {
+    pub line: String,
+    // ordered by the first departure of their earliest trip
+    pub patterns: Vec<PatternTimetable>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Departure {
+    pub departure: u16,
+    pub edge: usize,
+    // index into the journeys of the edge's timetable
+    pub journey: usize,
+}
+
+// Travel time over an edge from a fixed start time, waiting included
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct WeightedEdge {
+    pub from: usize,
+    pub to: usize,
+    pub weight_seconds: u32,
+}
+
+// Departures of every node sorted by time, built once for repeated queries.
+#[derive(Debug, Default)]
+pub struct DepartureIndex {
+    departures: Vec<Vec<Departure>>,
+}
+
+impl DepartureIndex {
+    // Returns up to limit departures from node leaving at or after the given minute.
+    pub fn next_departures(&self, node: usize, after: u16, limit: usize) -> &[Departure] {
+        let Some(departures) = self.departures.get(node) else {
+            return &[];
+        };
+        let start = departures.partition_point(|d| d.departure < after);
+        let end = departures.len().min(start.saturating_add(limit));
+        &departures[start..end]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PathLeg {
+    // node reached by the leg
+    pub node: usize,
+    pub arrival: u16,
+    pub edge: usize,
+    // index into the journeys of the edge's timetable, None when walking
+    pub journey: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathResult {
+    pub legs: Vec<PathLeg>,
+}
+
+impl PathResult {
+    pub fn arrival(&self) -> Option<u16> {
+        self.legs.last().map(|leg| leg.arrival)
+    }
+}
+
+pub(crate) struct Leg<'a> {
+    pub(crate) edge: usize,
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) journey: &'a Journey,
+}
+
+impl Graph {
+    pub fn build_departure_index(&self) -> DepartureIndex {
+        let mut departures = vec![Vec::<Departure>::new(); self.nodes.len()];
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            for (journey_idx, journey) in edge.timetable.journeys.iter().enumerate() {
+                departures[edge.start_node].push(Departure {
+                    departure: journey.departure.minute_of_day(),
+                    edge: edge_idx,
+                    journey: journey_idx,
+                });
+            }
+        }
+        for node in &mut departures {
+            node.sort_by_key(|d| (d.departure, d.edge, d.journey));
+        }
+        DepartureIndex { departures }
+    }
+
+    // Journeys whose operating period has at least one valid day on weekday.
+    pub fn journeys_on_weekday(&self, weekday: Weekday) -> impl Iterator<Item = (&Edge, &Journey)> {
+        self.edges.iter().flat_map(move |edge| {
+            let periods = &edge.timetable.periods;
+            let runs: Vec<bool> = periods.iter().map(|p| p.runs_on_weekday(weekday)).collect();
+            edge.timetable
+                .journeys
+                .iter()
+                .filter(move |journey| runs.get(journey.operating_period) == Some(&true))
+                .map(move |journey| (edge, journey))
+        })
+    }
+
+    // Reassembles the trips of a line from its edges.
+    // Trips with the same stop sequence are grouped as one pattern.
+    pub fn line_timetable(&self, line: &str) -> LineTimetable {
+        let trips = self.trip_legs(|journey| journey.line == line);
+        let mut patterns = Vec::<PatternTimetable>::new();
+        let mut pattern_by_stops = HashMap::<Vec<usize>, usize>::new();
+        for legs in trips.into_values() {
+            let mut stops: Vec<usize> = legs.iter().map(|leg| leg.start).collect();
+            stops.push(legs.last().unwrap().end);
+            let mut times: Vec<u16> = legs
+                .iter()
+                .map(|leg| leg.journey.departure.minute_of_day())
+                .collect();
+            times.push(legs.last().unwrap().journey.arrival.minute_of_day());
+            let idx = *pattern_by_stops.entry(stops.clone()).or_insert_with(|| {
+                patterns.push(PatternTimetable {
+                    pattern_name: legs[0].journey.pattern_name.clone(),
+                    stops,
+                    trips: Vec::new(),
+                });
+                patterns.len() - 1
+            });
+            patterns[idx].trips.push(times);
+        }
+        for pattern in &mut patterns {
+            pattern.trips.sort_by_key(|times| times[0]);
+        }
+        patterns.sort_by_key(|pattern| pattern.trips[0][0]);
+        LineTimetable {
+            line: line.to_owned(),
+            patterns,
+        }
+    }
+
+    // Earliest arrival connection from one node to another, leaving at or after
+    // depart_after on the packed date day. Transfers take no time, walking edges
+    // are used whenever they are faster. Journeys past midnight are not followed.
+    // Collects the outgoing edges on every call, GraphQuery keeps them instead.
+    #[deprecated(note = "use GraphQuery::shortest_path")]
+    pub fn shortest_path(
+        &self,
+        from: usize,
+        to: usize,
+        depart_after: u16,
+        day: u16,
+    ) -> Option<PathResult> {
+        self.shortest_path_over(&self.outgoing_edges(), from, to, depart_after, day)
+    }
+
+    // edge indices leaving every node
+    pub(crate) fn outgoing_edges(&self) -> Vec<Vec<usize>> {
+        let mut outgoing = vec![Vec::<usize>::new(); self.nodes.len()];
+        for (idx, edge) in self.edges.iter().enumerate() {
+            outgoing[edge.start_node].push(idx);
+        }
+        outgoing
+    }
+
+    // shortest_path with the outgoing edges of every node given
+    pub(crate) fn shortest_path_over(
+        &self,
+        outgoing: &[Vec<usize>],
+        from: usize,
+        to: usize,
+        depart_after: u16,
+        day: u16,
+    ) -> Option<PathResult> {
+        if from >= self.nodes.len() || to >= self.nodes.len() {
+            return None;
+        }
+        let mut arrival = vec![u16::MAX; self.nodes.len()];
+        let mut predecessor = vec![None::<PathLeg>; self.nodes.len()];
+        let mut queue = BinaryHeap::from([Reverse((depart_after, from))]);
+        arrival[from] = depart_after;
+        while let Some(Reverse((time, node))) = queue.pop() {
+            if node == to {
+                break;
+            }
+            if time > arrival[node] {
+                continue;
+            }
+            for &edge_idx in &outgoing[node] {
+                let edge = &self.edges[edge_idx];
+                let Some((reached, journey)) = earliest_leg(edge, time, day) else {
+                    continue;
+                };
+                if reached < arrival[edge.end_node] {
+                    arrival[edge.end_node] = reached;
+                    predecessor[edge.end_node] = Some(PathLeg {
+                        node: edge.end_node,
+                        arrival: reached,
+                        edge: edge_idx,
+                        journey,
+                    });
+                    queue.push(Reverse((reached, edge.end_node)));
+                }
+            }
+        }
+        if from == to || predecessor[to].is_none() {
+            return (from == to).then(PathResult::default);
+        }
+        let mut legs = Vec::new();
+        let mut current = to;
+        while let Some(leg) = predecessor[current] {
+            legs.push(leg);
+            current = self.edges[leg.edge].start_node;
+            if current == from {
+                break;
+            }
+        }
+        legs.reverse();
+        Some(PathResult { legs })
+    }
+
+    // Weights every edge by the wait for its next departure at the minute at on
+    // the packed date day plus the ride, or by walking where that is faster.
+    // Edges without a departure left that day are omitted.
+    pub fn export_weighted(&self, day: u16, at: u16) -> Vec<WeightedEdge> {
+        self.edges
+            .iter()
+            .filter_map(|edge| {
+                let (reached, journey) = earliest_leg(edge, at, day)?;
+                Some(WeightedEdge {
+                    from: edge.start_node,
+                    to: edge.end_node,
+                    weight_seconds: match journey {
+                        Some(_) => u32::from(reached - at) * 60,
+                        None => u32::from(edge.walk_seconds),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    // The chained legs of every trip with journeys matching keep, by trip id
+    pub(crate) fn trip_legs(&self, keep: impl Fn(&Journey) -> bool) -> BTreeMap<u64, Vec<Leg<'_>>> {
+        let mut trips = BTreeMap::<u64, Vec<Leg>>::new();
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            for journey in edge.timetable.journeys.iter().filter(|j| keep(j)) {
+                trips.entry(journey.trip).or_default().push(Leg {
+                    edge: edge_idx,
+                    start: edge.start_node,
+                    end: edge.end_node,
+                    journey,
+                });
+            }
+        }
+        for legs in trips.values_mut() {
+            *legs = chain_legs(std::mem::take(legs));
+        }
+        trips
+    }
+}
+
+// arrival at the end of edge when at its start at time, either by the journey
+// arriving first or by walking
+fn earliest_leg(edge: &Edge, time: u16, day: u16) -> Option<(u16, Option<usize>)> {
+    let periods = &edge.timetable.periods;
+    let ride = edge
+        .timetable
+        .journeys
+        .iter()
+        .enumerate()
+        .filter(|(_, journey)| {
+            journey.departure.minute_of_day() >= time
+                && journey.arrival.minute_of_day() >= journey.departure.minute_of_day()
+                && periods
+                    .get(journey.operating_period)
+                    .is_some_and(|period| period.is_valid_on(day))
+        })
+        .min_by_key(|(_, journey)| journey.arrival)
+        .map(|(idx, journey)| (journey.arrival.minute_of_day(), Some(idx)));
+    let walk = (edge.walk_seconds != u16::MAX)
+        .then(|| time.checked_add(edge.walk_seconds.div_ceil(60)))
+        .flatten()
+        .map(|reached| (reached, None));
+    match (ride, walk) {
+        (Some(ride), Some(walk)) => Some(if walk.0 < ride.0 { walk } else { ride }),
+        (ride, walk) => ride.or(walk),
+    }
+}
+
+// Orders the legs of a trip by following end to start from its origin.
+// Legs that can not be chained, e.g. on circular trips, are appended by departure.
+fn chain_legs(mut legs: Vec<Leg>) -> Vec<Leg> {
+    legs.sort_by_key(|leg| leg.journey.departure);
+    let ends: HashSet<usize> = legs.iter().map(|leg| leg.end).collect();
+    let origin = legs
+        .iter()
+        .position(|leg| !ends.contains(&leg.start))
+        .unwrap_or(0);
+    let mut result = vec![legs.remove(origin)];
+    while let Some(next) = legs
+        .iter()
+        .position(|leg| leg.start == result.last().unwrap().end)
+    {
+        result.push(legs.remove(next));
+    }
+    result.extend(legs);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::Weekday;
+
+    #[test]
+    fn line_timetable() {
+        // trips inserted out of departure order
+        let graph = crate::fixtures::graph_with_trips(&["09", "07"]);
+        let timetable = graph.line_timetable("S1");
+        assert_eq!(timetable.line, "S1");
+        assert_eq!(timetable.patterns.len(), 1);
+        let pattern = &timetable.patterns[0];
+        assert_eq!(pattern.pattern_name.as_deref(), Some("Hbf - Mülheim"));
+        let names: Vec<&str> = pattern
+            .stops
+            .iter()
+            .map(|idx| graph.nodes[*idx].short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Köln Hbf", "Köln Messe/Deutz", "Köln-Mülheim"]);
+        assert_eq!(
+            pattern.trips,
+            vec![vec![420, 426, 432], vec![540, 546, 552]]
+        );
+        assert!(graph.line_timetable("S2").patterns.is_empty());
+    }
+
+    #[test]
+    fn export_weighted() {
+        let mut graph = crate::fixtures::graph_with_trips(&["07", "09"]);
+        let node = |graph: &crate::graph::Graph, name: &str| {
+            graph
+                .nodes
+                .iter()
+                .position(|node| node.short_name == name)
+                .unwrap()
+        };
+        let (hbf, deutz, muelheim) = (
+            node(&graph, "Köln Hbf"),
+            node(&graph, "Köln Messe/Deutz"),
+            node(&graph, "Köln-Mülheim"),
+        );
+        graph.edges.push(crate::graph::Edge {
+            start_node: muelheim,
+            end_node: deutz,
+            timetable: crate::graph::Timetable::default(),
+            walk_seconds: 1500,
+        });
+        // monday 2022-06-13
+        let monday = 22 + (6 << 7) + (13 << 11);
+        let mut weights = graph.export_weighted(monday, 421);
+        weights.sort_by_key(|weight| (weight.from, weight.to));
+        let mut expected = vec![
+            // waiting for the 09:00 trip, then riding 5 minutes
+            super::WeightedEdge {
+                from: hbf,
+                to: deutz,
+                weight_seconds: (540 + 5 - 421) * 60,
+            },
+            // the 07:00 trip has left Hbf, but not yet Deutz
+            super::WeightedEdge {
+                from: deutz,
+                to: muelheim,
+                weight_seconds: (426 + 6 - 421) * 60,
+            },
+            super::WeightedEdge {
+                from: muelheim,
+                to: deutz,
+                weight_seconds: 1500,
+            },
+        ];
+        expected.sort_by_key(|weight| (weight.from, weight.to));
+        assert_eq!(weights, expected);
+
+        let sunday = 22 + (6 << 7) + (19 << 11);
+        assert_eq!(graph.export_weighted(sunday, 421).len(), 1);
+        let json = serde_json::to_value(weights[0]).unwrap();
+        assert!(json.get("weight_seconds").is_some());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn shortest_path() {
+        let graph = crate::fixtures::graph_with_trips(&["07", "09"]);
+        let node = |name: &str| {
+            graph
+                .nodes
+                .iter()
+                .position(|node| node.short_name == name)
+                .unwrap()
+        };
+        let (hbf, deutz, muelheim) = (
+            node("Köln Hbf"),
+            node("Köln Messe/Deutz"),
+            node("Köln-Mülheim"),
+        );
+        // monday 2022-06-13
+        let monday = 22 + (6 << 7) + (13 << 11);
+        let path = graph.shortest_path(hbf, muelheim, 421, monday).unwrap();
+        let legs: Vec<(usize, u16)> = path
+            .legs
+            .iter()
+            .map(|leg| (leg.node, leg.arrival))
+            .collect();
+        assert_eq!(legs, vec![(deutz, 545), (muelheim, 552)]);
+        assert_eq!(path.arrival(), Some(552));
+        for leg in &path.legs {
+            let edge = &graph.edges[leg.edge];
+            assert_eq!(edge.end_node, leg.node);
+            let journey = &edge.timetable.journeys[leg.journey.unwrap()];
+            assert_eq!(journey.arrival.minute_of_day(), leg.arrival);
+        }
+        assert_eq!(graph.edges[path.legs[0].edge].start_node, hbf);
+        // the sunday is not operated and nothing leaves after the last trip
+        let sunday = 22 + (6 << 7) + (19 << 11);
+        assert_eq!(graph.shortest_path(hbf, muelheim, 421, sunday), None);
+        assert_eq!(graph.shortest_path(hbf, muelheim, 541, monday), None);
+        assert_eq!(graph.shortest_path(muelheim, hbf, 0, monday), None);
+    }
+
+    #[test]
+    fn journeys_on_weekday() {
+        use crate::graph::{DayMinute, Edge, Graph, Journey, Node, OperatingPeriod, Timetable};
+        let date = |day: u16| 22 + (6 << 7) + (day << 11);
+        // from monday 2022-06-13 over two weeks
+        let period = |bits: u16| OperatingPeriod {
+            from: date(13),
+            to: date(26),
+            valid_day: bits.to_le_bytes().to_vec(),
+            ..OperatingPeriod::default()
+        };
+        let journey = |departure, operating_period| Journey {
+            departure: DayMinute(departure),
+            operating_period,
+            ..Journey::default()
+        };
+        let graph = Graph {
+            nodes: vec![Node::default(); 2],
+            edges: vec![Edge {
+                start_node: 0,
+                end_node: 1,
+                timetable: Timetable {
+                    journeys: vec![journey(420, 0), journey(480, 1), journey(540, 2)],
+                    // mondays only, tuesday to friday, the second saturday
+                    periods: vec![
+                        period(0b0_0000_1000_0001),
+                        period(0b0_1111_0001_1110),
+                        period(1 << 12),
+                    ],
+                },
+                walk_seconds: u16::MAX,
+            }],
+            ..Graph::default()
+        };
+        let departures = |weekday| {
+            graph
+                .journeys_on_weekday(weekday)
+                .map(|(_, journey)| journey.departure.minute_of_day())
+                .collect::<Vec<u16>>()
+        };
+        assert_eq!(departures(Weekday::Monday), vec![420]);
+        assert_eq!(departures(Weekday::Thursday), vec![480]);
+        assert_eq!(departures(Weekday::Saturday), vec![540]);
+        assert!(departures(Weekday::Sunday).is_empty());
+    }
+
+    #[test]
+    fn next_departures() {
+        let graph = crate::fixtures::graph_with_trips(&["10", "07", "09", "08"]);
+        let hbf = graph
+            .nodes
+            .iter()
+            .position(|node| node.short_name == "Köln Hbf")
+            .unwrap();
+        let index = graph.build_departure_index();
+        let departures = index.next_departures(hbf, 421, 3);
+        let times: Vec<u16> = departures.iter().map(|d| d.departure).collect();
+        assert_eq!(times, vec![480, 540, 600]);
+        for departure in departures {
+            let edge = &graph.edges[departure.edge];
+            assert_eq!(edge.start_node, hbf);
+            assert_eq!(
+                edge.timetable.journeys[departure.journey]
+                    .departure
+                    .minute_of_day(),
+                departure.departure
+            );
+        }
+        assert_eq!(index.next_departures(hbf, 480, 1)[0].departure, 480);
+        assert!(index.next_departures(hbf, 601, 3).is_empty());
+        assert!(index.next_departures(graph.nodes.len(), 0, 3).is_empty());
+    }
+}
@@ -0,0 +1,821 @@
+use std::collections::{HashMap, VecDeque};
+
+use geo::{Destination, Haversine, InterpolatePoint};
+
+use crate::graph::{great_circle_distance, DayMinute, Edge, Graph, Journey, Node, Timetable};
+
+// radius in km in which similar names are considered the same stop
+pub const DEFAULT_MERGE_DISTANCE: f32 = 1.0;
+
+impl Graph {
+    // Merges nodes whose names have a Jaro-Winkler similarity of at least threshold
+    // and that are at most DEFAULT_MERGE_DISTANCE apart.
+    pub fn merge_similar_nodes(&mut self, threshold: f64) {
+        self.merge_similar_nodes_within(threshold, DEFAULT_MERGE_DISTANCE);
+    }
+
+    pub fn merge_similar_nodes_within(&mut self, threshold: f64, max_distance_km: f32) {
+        type TreeObj = rstar::primitives::GeomWithData<geo::Coord<f32>, usize>;
+        let tree = rstar::RTree::<TreeObj>::bulk_load(
+            self.nodes
+                .iter()
+                .enumerate()
+                .map(|(idx, node)| TreeObj::new(geo::Coord::from([node.long, node.lat]), idx))
+                .collect(),
+        );
+        let mut parents: Vec<usize> = (0..self.nodes.len()).collect();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let center = geo::Point::from((node.long, node.lat));
+            let corner1 = Haversine.destination(center, 45.0, max_distance_km * 1000.0);
+            let corner2 = Haversine.destination(center, 225.0, max_distance_km * 1000.0);
+            let aabb = rstar::AABB::<geo::Coord<f32>>::from_corners(corner1.into(), corner2.into());
+            for candidate in tree.locate_in_envelope(&aabb) {
+                let other = &self.nodes[candidate.data];
+                if candidate.data <= idx
+                    || strsim::jaro_winkler(&node.short_name, &other.short_name) < threshold
+                    || great_circle_distance((node.long, node.lat), (other.long, other.lat))
+                        > max_distance_km
+                {
+                    continue;
+                }
+                union(&mut parents, idx, candidate.data);
+            }
+        }
+        self.collapse_nodes(&mut parents);
+    }
+
+    // Extracts the nodes reachable within hops edges of center, ignoring edge
+    // direction, together with the edges among them. Nodes keep their relative order.
+    #[must_use]
+    pub fn subgraph_around(&self, center: usize, hops: usize) -> Graph {
+        let mut neighbors = vec![Vec::<usize>::new(); self.nodes.len()];
+        for edge in &self.edges {
+            neighbors[edge.start_node].push(edge.end_node);
+            neighbors[edge.end_node].push(edge.start_node);
+        }
+        let mut distance = vec![None; self.nodes.len()];
+        let mut queue = VecDeque::new();
+        if center < self.nodes.len() {
+            distance[center] = Some(0);
+            queue.push_back(center);
+        }
+        while let Some(idx) = queue.pop_front() {
+            let next = distance[idx].unwrap() + 1;
+            if next > hops {
+                continue;
+            }
+            for &neighbor in &neighbors[idx] {
+                if distance[neighbor].is_none() {
+                    distance[neighbor] = Some(next);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        let mut old_to_new = vec![None; self.nodes.len()];
+        let mut nodes = Vec::<Node>::new();
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if distance[idx].is_some() {
+                old_to_new[idx] = Some(nodes.len());
+                nodes.push(node.clone());
+            }
+        }
+        let edges = self
+            .edges
+            .iter()
+            .filter_map(|edge| {
+                Some(Edge {
+                    start_node: old_to_new[edge.start_node]?,
+                    end_node: old_to_new[edge.end_node]?,
+                    ..edge.clone()
+                })
+            })
+            .collect();
+        Graph {
+            nodes,
+            edges,
+            hubs: self.hubs.clone(),
+            contacts: self.contacts.clone(),
+            authority_chains: self.authority_chains.clone(),
+            notices: self.notices.clone(),
+            ..Graph::default()
+        }
+    }
+
+    // Partitions the journeys by transport mode. Edges serving several modes
+    // appear in each sub-graph with only the matching journeys.
+    pub fn split_by_mode(&self) -> HashMap<String, Graph> {
+        let mut edges_by_mode = HashMap::<String, Vec<Edge>>::new();
+        for edge in &self.edges {
+            let mut by_mode = HashMap::<&str, Edge>::new();
+            for journey in &edge.timetable.journeys {
+                by_mode
+                    .entry(&journey.transport_mode)
+                    .or_insert_with(|| Edge {
+                        start_node: edge.start_node,
+                        end_node: edge.end_node,
+                        // periods are kept as is so local indices stay valid
+                        timetable: Timetable {
+                            journeys: Vec::new(),
+                            periods: edge.timetable.periods.clone(),
+                        },
+                        walk_seconds: edge.walk_seconds,
+                    })
+                    .timetable
+                    .journeys
+                    .push(journey.clone());
+            }
+            for (mode, edge) in by_mode {
+                edges_by_mode.entry(mode.to_owned()).or_default().push(edge);
+            }
+        }
+        edges_by_mode
+            .into_iter()
+            .map(|(mode, edges)| (mode, self.with_edges(edges)))
+            .collect()
+    }
+
+    // Builds a graph of the given edges, keeping only the nodes they reference.
+    fn with_edges(&self, mut edges: Vec<Edge>) -> Graph {
+        let mut old_to_new = vec![None; self.nodes.len()];
+        for edge in &edges {
+            old_to_new[edge.start_node] = Some(0);
+            old_to_new[edge.end_node] = Some(0);
+        }
+        let mut nodes = Vec::<Node>::new();
+        for (idx, new) in old_to_new.iter_mut().enumerate() {
+            if new.is_some() {
+                *new = Some(nodes.len());
+                nodes.push(self.nodes[idx].clone());
+            }
+        }
+        for edge in &mut edges {
+            edge.start_node = old_to_new[edge.start_node].unwrap();
+            edge.end_node = old_to_new[edge.end_node].unwrap();
+        }
+        Graph {
+            nodes,
+            edges,
+            hubs: self.hubs.clone(),
+            contacts: self.contacts.clone(),
+            authority_chains: self.authority_chains.clone(),
+            notices: self.notices.clone(),
+            ..Graph::default()
+        }
+    }
+
+    // Replaces every node by its union-find representative and rewires edges.
+    // Edges collapsing into self-loops are dropped, parallel edges are combined.
+    fn collapse_nodes(&mut self, parents: &mut [usize]) {
+        let mut old_to_new = vec![0; self.nodes.len()];
+        let mut nodes = Vec::<Node>::new();
+        let mut root_to_new = HashMap::<usize, usize>::new();
+        for (idx, new) in old_to_new.iter_mut().enumerate() {
+            let root = find(parents, idx);
+            let new_idx = *root_to_new.entry(root).or_insert_with(|| {
+                nodes.push(self.nodes[root].clone());
+                nodes.len() - 1
+            });
+            if root != idx {
+                nodes[new_idx].absorb(&self.nodes[idx]);
+            }
+            *new = new_idx;
+        }
+        self.edges.retain_mut(|edge| {
+            edge.start_node = old_to_new[edge.start_node];
+            edge.end_node = old_to_new[edge.end_node];
+            edge.start_node != edge.end_node
+        });
+        self.nodes = nodes;
+        self.combine_parallel_edges();
+    }
+
+    // Combines edges between the same pair of nodes into the first of them
+    // and removes duplicated journeys and periods from every timetable.
+    pub fn merge_parallel_edges(&mut self) {
+        self.combine_parallel_edges();
+        for edge in &mut self.edges {
+            edge.timetable.compact();
+        }
+    }
+
+    // Removes journeys arriving when or before they depart, returning how many.
+    // Times past midnight carry their day, so an arrival that is not after the
+    // departure is broken data rather than a wrap.
+    pub fn drop_nonpositive_legs(&mut self) -> usize {
+        let mut removed = 0;
+        for edge in &mut self.edges {
+            let before = edge.timetable.journeys.len();
+            edge.timetable
+                .journeys
+                .retain(|journey| journey.arrival > journey.departure);
+            removed += before - edge.timetable.journeys.len();
+        }
+        removed
+    }
+
+    // Puts nodes, edges, periods and journeys into an order that only depends
+    // on their content, so output is reproducible regardless of input order.
+    // Nodes are ordered by name and coordinates, edges by their nodes, periods
+    // by their days and journeys by departure and line.
+    pub fn sort_deterministic(&mut self) {
+        let mut order: Vec<usize> = (0..self.nodes.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&self.nodes[*a], &self.nodes[*b]);
+            a.short_name
+                .cmp(&b.short_name)
+                .then(a.lat.total_cmp(&b.lat))
+                .then(a.long.total_cmp(&b.long))
+                .then(a.id.cmp(&b.id))
+        });
+        let mut old_to_new = vec![0; self.nodes.len()];
+        for (new, old) in order.iter().enumerate() {
+            old_to_new[*old] = new;
+        }
+        let mut nodes = std::mem::take(&mut self.nodes);
+        self.nodes = order
+            .into_iter()
+            .map(|idx| std::mem::take(&mut nodes[idx]))
+            .collect();
+        for edge in &mut self.edges {
+            edge.start_node = old_to_new[edge.start_node];
+            edge.end_node = old_to_new[edge.end_node];
+            edge.timetable.sort_deterministic();
+        }
+        self.edges
+            .sort_by_key(|edge| (edge.start_node, edge.end_node, edge.walk_seconds));
+    }
+
+    // Keeps only the largest connected component, ignoring edge direction.
+    // Of equally large components the one with the first node survives.
+    pub fn retain_largest_component(&mut self) {
+        let (roots, sizes) = self.components();
+        let Some(largest) = (0..sizes.len()).max_by_key(|root| (sizes[*root], usize::MAX - root))
+        else {
+            return;
+        };
+        self.retain_nodes(|idx| roots[idx] == largest);
+    }
+
+    // Keeps the connected components of at least min_nodes nodes, ignoring edge direction.
+    pub fn retain_components(&mut self, min_nodes: usize) {
+        let (roots, sizes) = self.components();
+        self.retain_nodes(|idx| sizes[roots[idx]] >= min_nodes);
+    }
+
+    // component root of every node and the node count per root
+    fn components(&self) -> (Vec<usize>, Vec<usize>) {
+        let mut parents: Vec<usize> = (0..self.nodes.len()).collect();
+        for edge in &self.edges {
+            union(&mut parents, edge.start_node, edge.end_node);
+        }
+        let roots: Vec<usize> = (0..self.nodes.len())
+            .map(|idx| find(&mut parents, idx))
+            .collect();
+        let mut sizes = vec![0; self.nodes.len()];
+        for root in &roots {
+            sizes[*root] += 1;
+        }
+        (roots, sizes)
+    }
+
+    // drops the nodes not kept along with their edges, keeping the order of the rest
+    fn retain_nodes(&mut self, keep: impl Fn(usize) -> bool) {
+        let mut old_to_new = vec![None; self.nodes.len()];
+        let mut nodes = Vec::<Node>::new();
+        for (idx, node) in std::mem::take(&mut self.nodes).into_iter().enumerate() {
+            if keep(idx) {
+                old_to_new[idx] = Some(nodes.len());
+                nodes.push(node);
+            }
+        }
+        self.nodes = nodes;
+        self.edges.retain_mut(|edge| {
+            let (Some(start), Some(end)) = (old_to_new[edge.start_node], old_to_new[edge.end_node])
+            else {
+                return false;
+            };
+            edge.start_node = start;
+            edge.end_node = end;
+            true
+        });
+    }
+
+    // Keeps the journeys departing within [from_min, to_min] minutes of the day
+    // and drops edges left without journeys, unless they can be walked.
+    // A window with from_min after to_min crosses midnight.
+    pub fn filter_time_window(&mut self, from_min: u16, to_min: u16) {
+        let inside = |departure: u16| {
+            let minute = departure % (24 * 60);
+            if from_min <= to_min {
+                (from_min..=to_min).contains(&minute)
+            } else {
+                minute >= from_min || minute <= to_min
+            }
+        };
+        self.edges.retain_mut(|edge| {
+            edge.timetable
+                .journeys
+                .retain(|journey| inside(journey.departure.minute_of_day()));
+            !edge.timetable.journeys.is_empty() || edge.walk_seconds != u16::MAX
+        });
+    }
+
+    // Splits edges longer than max_segment_m into equal segments joined by
+    // virtual nodes along the great circle. Virtual nodes have id 0 and no name.
+    // Journey times and walk durations are split proportionally to distance,
+    // so every segment carries all journeys of the original edge.
+    pub fn densify(&mut self, max_segment_m: f64) {
+        if max_segment_m.is_nan() || max_segment_m <= 0.0 {
+            return;
+        }
+        let mut edges = Vec::<Edge>::with_capacity(self.edges.len());
+        for edge in std::mem::take(&mut self.edges) {
+            let length = self.edge_length(&edge);
+            if length <= max_segment_m {
+                edges.push(edge);
+                continue;
+            }
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+            let segments = (length / max_segment_m).ceil() as u32;
+            let start = &self.nodes[edge.start_node];
+            let end = &self.nodes[edge.end_node];
+            let from = geo::Point::new(f64::from(start.long), f64::from(start.lat));
+            let to = geo::Point::new(f64::from(end.long), f64::from(end.lat));
+            let mut stops = vec![edge.start_node];
+            for step in 1..segments {
+                let point = Haversine.point_at_ratio_between(
+                    from,
+                    to,
+                    f64::from(step) / f64::from(segments),
+                );
+                #[allow(clippy::cast_possible_truncation)]
+                self.nodes.push(Node {
+                    long: point.x() as f32,
+                    lat: point.y() as f32,
+                    ..Node::default()
+                });
+                stops.push(self.nodes.len() - 1);
+            }
+            stops.push(edge.end_node);
+            for (step, pair) in (0..segments).zip(stops.windows(2)) {
+                edges.push(edge_segment(&edge, pair[0], pair[1], step, segments));
+            }
+        }
+        self.edges = edges;
+    }
+
+    fn combine_parallel_edges(&mut self) {
+        let mut edges = Vec::<Edge>::new();
+        let mut edge_map = HashMap::<(usize, usize), usize>::new();
+        for edge in std::mem::take(&mut self.edges) {
+            if let Some(existing) = edge_map.get(&(edge.start_node, edge.end_node)) {
+                edges[*existing].absorb(edge);
+            } else {
+                edge_map.insert((edge.start_node, edge.end_node), edges.len());
+                edges.push(edge);
+            }
+        }
+        self.edges = edges;
+    }
+}
+
+// The step-th of segments equal parts of edge, running from start_node to end_node.
+fn edge_segment(edge: &Edge, start_node: usize, end_node: usize, step: u32, segments: u32) -> Edge {
+    // rounds the share of total at the given step
+    let share = |total: u32, step: u32| (total * step + segments / 2) / segments;
+    let mut segment = Edge {
+        start_node,
+        end_node,
+        timetable: Timetable {
+            journeys: Vec::with_capacity(edge.timetable.journeys.len()),
+            periods: edge.timetable.periods.clone(),
+        },
+        walk_seconds: edge.walk_seconds,
+    };
+    if edge.walk_seconds != u16::MAX {
+        let walk = share(u32::from(edge.walk_seconds), step + 1)
+            - share(u32::from(edge.walk_seconds), step);
+        segment.walk_seconds = u16::try_from(walk).unwrap_or(u16::MAX);
+    }
+    for journey in &edge.timetable.journeys {
+        // times carry their day, broken arrivals before the departure are
+        // clamped to it
+        let departure = u32::from(journey.departure.minute_of_day());
+        let duration = u32::from(journey.arrival.minute_of_day()).saturating_sub(departure);
+        let time = |step| {
+            let minute = departure + share(duration, step);
+            DayMinute(u16::try_from(minute).unwrap_or(u16::MAX))
+        };
+        segment.timetable.journeys.push(Journey {
+            departure: time(step),
+            arrival: time(step + 1),
+            ..journey.clone()
+        });
+    }
+    segment
+}
+
+fn find(parents: &mut [usize], idx: usize) -> usize {
+    let mut root = idx;
+    while parents[root] != root {
+        root = parents[root];
+    }
+    parents[idx] = root;
+    root
+}
+
+// the smaller index becomes the representative, so the first seen node survives
+fn union(parents: &mut [usize], a: usize, b: usize) {
+    let a = find(parents, a);
+    let b = find(parents, b);
+    parents[a.max(b)] = a.min(b);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::graph::{DayMinute, Edge, Graph, Journey, Node, OperatingPeriod, Timetable};
+
+    fn node(name: &str, long: f32, lat: f32) -> Node {
+        Node {
+            short_name: name.to_owned(),
+            long,
+            lat,
+            ..Node::default()
+        }
+    }
+
+    fn edge(start_node: usize, end_node: usize) -> Edge {
+        Edge {
+            start_node,
+            end_node,
+            timetable: Timetable::default(),
+            walk_seconds: u16::MAX,
+        }
+    }
+
+    #[test]
+    fn merge_similar_nodes() {
+        let mut graph = Graph {
+            nodes: vec![
+                node("Köln Hauptbahnhof", 6.958, 50.943),
+                node("Köln Hbf", 6.959, 50.942),
+                node("Köln Messe/Deutz", 6.975, 50.940),
+            ],
+            edges: vec![edge(0, 2), edge(1, 2), edge(0, 1)],
+            ..Graph::default()
+        };
+        graph.merge_similar_nodes(0.85);
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes[0].short_name, "Köln Hauptbahnhof");
+        assert_eq!(graph.edges.len(), 1);
+        assert_eq!((graph.edges[0].start_node, graph.edges[0].end_node), (0, 1));
+    }
+
+    #[test]
+    fn merge_similar_nodes_respects_distance() {
+        let mut graph = Graph {
+            nodes: vec![
+                node("Köln Hauptbahnhof", 6.958, 50.943),
+                node("Köln Hbf", 6.959, 50.942),
+            ],
+            ..Graph::default()
+        };
+        graph.merge_similar_nodes_within(0.85, 0.01);
+        assert_eq!(graph.nodes.len(), 2);
+    }
+
+    #[test]
+    fn merge_parallel_edges() {
+        let period = |from| OperatingPeriod {
+            from,
+            ..OperatingPeriod::default()
+        };
+        let journey = |departure, operating_period| Journey {
+            departure: DayMinute(departure),
+            arrival: DayMinute(departure + 5),
+            operating_period,
+            ..Journey::default()
+        };
+        let mut first = edge(0, 1);
+        first.timetable = Timetable {
+            journeys: vec![journey(480, 0), journey(540, 1)],
+            periods: vec![period(1), period(2)],
+        };
+        let mut second = edge(0, 1);
+        second.timetable = Timetable {
+            // the journey at 540 is also on the first edge
+            journeys: vec![journey(540, 0), journey(600, 1)],
+            periods: vec![period(2), period(1)],
+        };
+        let mut graph = Graph {
+            nodes: vec![
+                node("Köln Hbf", 6.958, 50.943),
+                node("Köln Messe/Deutz", 6.975, 50.940),
+            ],
+            edges: vec![first, second, edge(1, 0)],
+            ..Graph::default()
+        };
+        graph.merge_parallel_edges();
+        assert_eq!(graph.edges.len(), 2);
+        let timetable = &graph.edges[0].timetable;
+        assert_eq!(timetable.periods.len(), 2);
+        let journeys: Vec<(u16, u16)> = timetable
+            .journeys
+            .iter()
+            .map(|j| {
+                (
+                    j.departure.minute_of_day(),
+                    timetable.periods[j.operating_period].from,
+                )
+            })
+            .collect();
+        assert_eq!(journeys, vec![(480, 1), (540, 2), (600, 1)]);
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn subgraph_around() {
+        let graph = Graph {
+            nodes: vec![
+                node("Köln Hbf", 6.958, 50.943),
+                node("Köln Messe/Deutz", 6.975, 50.940),
+                node("Köln-Mülheim", 7.006, 50.963),
+                node("Köln West", 6.937, 50.944),
+            ],
+            edges: vec![edge(0, 1), edge(1, 2), edge(3, 0), edge(2, 3)],
+            ..Graph::default()
+        };
+        let subgraph = graph.subgraph_around(1, 1);
+        let names: Vec<&str> = subgraph
+            .nodes
+            .iter()
+            .map(|node| node.short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Köln Hbf", "Köln Messe/Deutz", "Köln-Mülheim"]);
+        let edges: Vec<(usize, usize)> = subgraph
+            .edges
+            .iter()
+            .map(|edge| (edge.start_node, edge.end_node))
+            .collect();
+        assert_eq!(edges, vec![(0, 1), (1, 2)]);
+        assert_eq!(graph.subgraph_around(1, 0).nodes.len(), 1);
+        assert_eq!(graph.subgraph_around(1, 2).edges.len(), 4);
+        assert!(graph.subgraph_around(4, 1).nodes.is_empty());
+    }
+
+    #[test]
+    fn split_by_mode() {
+        let journey = |transport_mode: &str| Journey {
+            transport_mode: transport_mode.to_owned(),
+            ..Journey::default()
+        };
+        let mut mixed = edge(0, 1);
+        mixed.timetable.journeys = vec![journey("bus"), journey("tram"), journey("bus")];
+        let mut tram = edge(1, 2);
+        tram.timetable.journeys = vec![journey("tram")];
+        let graph = Graph {
+            nodes: vec![
+                node("Köln Hbf", 6.958, 50.943),
+                node("Köln Messe/Deutz", 6.975, 50.940),
+                node("Köln-Mülheim", 7.006, 50.963),
+            ],
+            edges: vec![mixed, tram],
+            ..Graph::default()
+        };
+        let modes = graph.split_by_mode();
+        assert_eq!(modes.len(), 2);
+        let bus = &modes["bus"];
+        assert_eq!(bus.nodes.len(), 2);
+        assert_eq!(bus.edges.len(), 1);
+        assert_eq!((bus.edges[0].start_node, bus.edges[0].end_node), (0, 1));
+        assert_eq!(bus.edges[0].timetable.journeys.len(), 2);
+        let tram = &modes["tram"];
+        assert_eq!(tram.nodes.len(), 3);
+        assert_eq!(tram.edges.len(), 2);
+        assert!(tram
+            .edges
+            .iter()
+            .all(|edge| edge.timetable.journeys.len() == 1));
+    }
+
+    #[test]
+    fn sort_deterministic() {
+        let snapshot = |mut graph: Graph| {
+            graph.sort_deterministic();
+            let nodes: Vec<String> = graph.nodes.iter().map(ToString::to_string).collect();
+            let edges: Vec<(usize, usize, serde_json::Value)> = graph
+                .edges
+                .iter()
+                .map(|edge| {
+                    let timetable = serde_json::to_value(&edge.timetable).unwrap();
+                    (edge.start_node, edge.end_node, timetable)
+                })
+                .collect();
+            (nodes, edges)
+        };
+        let (nodes, edges) = snapshot(crate::fixtures::graph_with_trips(&["07", "09", "08"]));
+        assert_eq!(
+            snapshot(crate::fixtures::graph_with_trips(&["09", "08", "07"])),
+            (nodes.clone(), edges.clone())
+        );
+        let names: Vec<&str> = nodes
+            .iter()
+            .map(|node| node.split(" @ ").next().unwrap())
+            .collect();
+        assert!(names.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(edges[0].2["j"][0]["d"], 420);
+        assert_eq!(edges[0].2["j"][2]["d"], 540);
+    }
+
+    #[test]
+    fn retain_largest_component() {
+        let graph = || Graph {
+            nodes: (0..7).map(|idx| node(&idx.to_string(), 0.0, 0.0)).collect(),
+            // island of 5 and 6 between the main nodes, one edge pointing backwards
+            edges: vec![edge(0, 1), edge(1, 2), edge(4, 2), edge(2, 3), edge(5, 6)],
+            ..Graph::default()
+        };
+        let mut largest = graph();
+        largest.retain_largest_component();
+        let names: Vec<&str> = largest
+            .nodes
+            .iter()
+            .map(|n| n.short_name.as_str())
+            .collect();
+        assert_eq!(names, vec!["0", "1", "2", "3", "4"]);
+        let edges: Vec<(usize, usize)> = largest
+            .edges
+            .iter()
+            .map(|e| (e.start_node, e.end_node))
+            .collect();
+        assert_eq!(edges, vec![(0, 1), (1, 2), (4, 2), (2, 3)]);
+
+        let mut kept = graph();
+        kept.retain_components(2);
+        assert_eq!(kept.nodes.len(), 7);
+        kept.retain_components(3);
+        assert_eq!(kept.nodes.len(), 5);
+        assert_eq!(kept.edges.len(), 4);
+    }
+
+    #[test]
+    fn filter_time_window() {
+        let journey = |departure: u16| Journey {
+            departure: DayMinute(departure),
+            arrival: DayMinute(departure + 5),
+            ..Journey::default()
+        };
+        let timed = |start_node, end_node, departures: &[u16]| Edge {
+            timetable: Timetable {
+                journeys: departures.iter().map(|d| journey(*d)).collect(),
+                periods: vec![OperatingPeriod::default()],
+            },
+            ..edge(start_node, end_node)
+        };
+        let graph = || Graph {
+            nodes: vec![
+                node("Köln Hbf", 6.958, 50.943),
+                node("Köln Messe/Deutz", 6.975, 50.940),
+                node("Köln-Mülheim", 7.006, 50.963),
+            ],
+            edges: vec![
+                timed(0, 1, &[360, 420, 480, 540, 600]),
+                timed(1, 2, &[1200, 1380]),
+                Edge {
+                    walk_seconds: 300,
+                    ..edge(1, 0)
+                },
+            ],
+            ..Graph::default()
+        };
+        let mut rush = graph();
+        rush.filter_time_window(7 * 60, 9 * 60);
+        assert_eq!(rush.edges.len(), 2);
+        let departures: Vec<u16> = rush.edges[0]
+            .timetable
+            .journeys
+            .iter()
+            .map(|j| j.departure.minute_of_day())
+            .collect();
+        assert_eq!(departures, vec![420, 480, 540]);
+        assert_eq!(rush.edges[1].walk_seconds, 300);
+
+        // 22:00 to 06:00 keeps the late trip and the early one
+        let mut night = graph();
+        night.filter_time_window(22 * 60, 6 * 60);
+        let kept: Vec<(usize, u16)> = night
+            .edges
+            .iter()
+            .flat_map(|e| {
+                e.timetable
+                    .journeys
+                    .iter()
+                    .map(|j| (e.start_node, j.departure.minute_of_day()))
+            })
+            .collect();
+        assert_eq!(kept, vec![(0, 360), (1, 1380)]);
+    }
+
+    #[test]
+    fn densify() {
+        let journey = |departure, arrival| Journey {
+            departure: DayMinute(departure),
+            arrival: DayMinute(arrival),
+            ..Journey::default()
+        };
+        // about 100 km north of Köln
+        let mut long = edge(0, 1);
+        long.timetable.journeys = vec![
+            journey(480, 520),
+            journey(1430, 1450),
+            // the arrival lacks the day offset of the departure
+            journey(2890, 100),
+        ];
+        long.timetable.periods = vec![OperatingPeriod::default()];
+        let mut graph = Graph {
+            nodes: vec![
+                node("Köln Hbf", 6.958, 50.943),
+                node("Münster Hbf", 6.958, 51.843),
+                node("Köln Messe/Deutz", 6.975, 50.940),
+            ],
+            edges: vec![long, edge(0, 2)],
+            ..Graph::default()
+        };
+        graph.densify(30_000.0);
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(graph.edges.len(), 5);
+        let chain: Vec<(usize, usize)> = graph.edges[..4]
+            .iter()
+            .map(|edge| (edge.start_node, edge.end_node))
+            .collect();
+        assert_eq!(chain, vec![(0, 3), (3, 4), (4, 5), (5, 1)]);
+        assert!((graph.nodes[4].lat - 51.393).abs() < 0.01);
+        assert!((graph.nodes[4].long - 6.958).abs() < 0.01);
+        let times: Vec<(u16, u16)> = graph.edges[..4]
+            .iter()
+            .map(|edge| {
+                let journey = &edge.timetable.journeys[0];
+                (
+                    journey.departure.minute_of_day(),
+                    journey.arrival.minute_of_day(),
+                )
+            })
+            .collect();
+        assert_eq!(times, vec![(480, 490), (490, 500), (500, 510), (510, 520)]);
+        let wrapped = &graph.edges[3].timetable.journeys[1];
+        assert_eq!(
+            (
+                wrapped.departure.minute_of_day(),
+                wrapped.arrival.minute_of_day()
+            ),
+            (1445, 1450)
+        );
+        let broken = &graph.edges[3].timetable.journeys[2];
+        assert_eq!(
+            (
+                broken.departure.minute_of_day(),
+                broken.arrival.minute_of_day()
+            ),
+            (2890, 2890)
+        );
+        assert_eq!((graph.edges[4].start_node, graph.edges[4].end_node), (0, 2));
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn drop_nonpositive_legs() {
+        let journey = |departure, arrival| Journey {
+            departure: DayMinute(departure),
+            arrival: DayMinute(arrival),
+            ..Journey::default()
+        };
+        let mut graph = Graph {
+            nodes: vec![node("A", 6.95, 50.94), node("B", 6.96, 50.94)],
+            edges: vec![Edge {
+                timetable: Timetable {
+                    journeys: vec![
+                        journey(480, 485),
+                        journey(480, 480),
+                        // 23:58 to 00:03 the next day
+                        journey(1438, 1443),
+                        journey(600, 595),
+                        // the arrival lacks the day offset of the departure
+                        journey(1438, 3),
+                        journey(2890, 100),
+                    ],
+                    periods: vec![OperatingPeriod::default()],
+                },
+                ..edge(0, 1)
+            }],
+            ..Graph::default()
+        };
+        assert_eq!(graph.drop_nonpositive_legs(), 4);
+        let kept: Vec<(u16, u16)> = graph.edges[0]
+            .timetable
+            .journeys
+            .iter()
+            .map(|j| (j.departure.minute_of_day(), j.arrival.minute_of_day()))
+            .collect();
+        assert_eq!(kept, vec![(480, 485), (1438, 1443)]);
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use crate::graph::Graph;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum GraphError {
+    NodeOutOfRange {
+        edge: usize,
+        node: usize,
+    },
+    PeriodOutOfRange {
+        edge: usize,
+        journey: usize,
+        period: usize,
+    },
+    DuplicateEdge {
+        start_node: usize,
+        end_node: usize,
+    },
+}
+
+impl std::fmt::Display for GraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphError::NodeOutOfRange { edge, node } => {
+                write!(f, "edge {edge} references missing node {node}")
+            }
+            GraphError::PeriodOutOfRange {
+                edge,
+                journey,
+                period,
+            } => write!(
+                f,
+                "journey {journey} of edge {edge} references missing period {period}"
+            ),
+            GraphError::DuplicateEdge {
+                start_node,
+                end_node,
+            } => write!(f, "multiple edges from {start_node} to {end_node}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl Graph {
+    // Checks that all indices stored on edges and journeys are in range
+    // and that every node pair has at most one edge.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        let mut pairs = HashSet::<(usize, usize)>::new();
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            for node in [edge.start_node, edge.end_node] {
+                if node >= self.nodes.len() {
+                    return Err(GraphError::NodeOutOfRange {
+                        edge: edge_idx,
+                        node,
+                    });
+                }
+            }
+            if !pairs.insert((edge.start_node, edge.end_node)) {
+                return Err(GraphError::DuplicateEdge {
+                    start_node: edge.start_node,
+                    end_node: edge.end_node,
+                });
+            }
+            for (journey_idx, journey) in edge.timetable.journeys.iter().enumerate() {
+                if journey.operating_period >= edge.timetable.periods.len() {
+                    return Err(GraphError::PeriodOutOfRange {
+                        edge: edge_idx,
+                        journey: journey_idx,
+                        period: journey.operating_period,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Nodes at exactly 0,0, which is where stops without coordinates end up.
+    pub fn flag_default_coordinates(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.lat == 0.0 && node.long == 0.0)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    // (start, end) node pairs connected only in that direction, ascending.
+    // Two-way routes showing up here may be missing data for one direction.
+    pub fn one_way_edges(&self) -> Vec<(usize, usize)> {
+        let pairs: HashSet<(usize, usize)> = self
+            .edges
+            .iter()
+            .map(|edge| (edge.start_node, edge.end_node))
+            .collect();
+        let mut result: Vec<(usize, usize)> = pairs
+            .iter()
+            .filter(|(start, end)| !pairs.contains(&(*end, *start)))
+            .copied()
+            .collect();
+        result.sort_unstable();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GraphError;
+
+    #[test]
+    fn validate() {
+        let mut graph = crate::fixtures::graph();
+        assert_eq!(graph.validate(), Ok(()));
+
+        graph.edges[1].timetable.journeys[0].operating_period = 1;
+        assert_eq!(
+            graph.validate(),
+            Err(GraphError::PeriodOutOfRange {
+                edge: 1,
+                journey: 0,
+                period: 1
+            })
+        );
+
+        graph.edges[0].end_node = 3;
+        assert_eq!(
+            graph.validate(),
+            Err(GraphError::NodeOutOfRange { edge: 0, node: 3 })
+        );
+        assert_eq!(
+            graph.validate().unwrap_err().to_string(),
+            "edge 0 references missing node 3"
+        );
+
+        let mut graph = crate::fixtures::graph();
+        let (start_node, end_node) = (graph.edges[0].start_node, graph.edges[0].end_node);
+        graph.edges.push(graph.edges[0].clone());
+        assert_eq!(
+            graph.validate(),
+            Err(GraphError::DuplicateEdge {
+                start_node,
+                end_node
+            })
+        );
+        assert_eq!(
+            graph.validate().unwrap_err().to_string(),
+            format!("multiple edges from {start_node} to {end_node}")
+        );
+    }
+
+    #[test]
+    fn flag_default_coordinates() {
+        let xml = crate::fixtures::DOCUMENT.replace(
+            "<ServiceJourneyPattern ",
+            r#"<ScheduledStopPoint id="ssp:d"><Name>Köln Süd</Name></ScheduledStopPoint>
+            <ServiceJourneyPattern "#,
+        );
+        let data = crate::parser::NetexData::from_xml(xml.as_bytes(), xml.len()).unwrap();
+        let graph = crate::graph::Graph::from_data(&[data], &[]);
+        let flagged = graph.flag_default_coordinates();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(graph.nodes[flagged[0]].short_name, "Köln Süd");
+        assert!(crate::fixtures::graph()
+            .flag_default_coordinates()
+            .is_empty());
+    }
+
+    #[test]
+    fn one_way_edges() {
+        let edge = |start_node, end_node| crate::graph::Edge {
+            start_node,
+            end_node,
+            timetable: crate::graph::Timetable::default(),
+            walk_seconds: u16::MAX,
+        };
+        let graph = crate::graph::Graph {
+            nodes: vec![crate::graph::Node::default(); 3],
+            edges: vec![edge(0, 1), edge(1, 0), edge(1, 2), edge(1, 2)],
+            ..crate::graph::Graph::default()
+        };
+        assert_eq!(graph.one_way_edges(), vec![(1, 2)]);
+    }
+}
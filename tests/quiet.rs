@@ -0,0 +1,40 @@
+use std::io::Write;
+
+const DOCUMENT: &str = r#"<PublicationDelivery>
+    <ScheduledStopPoint id="ssp:1">
+        <Name>Köln Hbf</Name>
+        <Location><Longitude>6.95</Longitude><Latitude>50.94</Latitude></Location>
+    </ScheduledStopPoint>
+</PublicationDelivery>"#;
+
+#[test]
+fn quiet_prints_nothing() {
+    let dir = std::env::temp_dir().join(format!("netex-parse-quiet-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let zip_path = dir.join("netex.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&zip_path).unwrap());
+    zip.start_file("stops.xml", zip::write::SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(DOCUMENT.as_bytes()).unwrap();
+    zip.finish().unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_netex-parse"))
+        .current_dir(&dir)
+        .args(["--quiet", "--output-format", "csv"])
+        .arg(&zip_path)
+        .output()
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(output.status.success());
+    assert!(
+        output.stderr.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        output.stdout.is_empty(),
+        "{}",
+        String::from_utf8_lossy(&output.stdout)
+    );
+}